@@ -0,0 +1,43 @@
+// Shared by the doc examples in src/lib.rs, included as hidden lines so that they run without a Chroma server.
+use wiremock::{matchers::any, Mock, MockServer, Request, ResponseTemplate};
+
+/// Start a mock Chroma server standing in for http://localhost:8000, and options pointing the client at it.
+/// Keep the server alive for as long as the example uses the client.
+async fn mock_chroma() -> (MockServer, chromadb::client::ChromaClientOptions) {
+    let server = MockServer::start().await;
+    Mock::given(any())
+        .respond_with(|request: &Request| {
+            let body: serde_json::Value =
+                serde_json::from_slice(&request.body).unwrap_or_default();
+            let path = request.url.path();
+            let json = if path.ends_with("/collections") {
+                serde_json::json!({"id": "c1", "name": body["name"], "metadata": null})
+            } else if path.ends_with("/count") {
+                serde_json::json!(1)
+            } else if path.ends_with("/get") {
+                serde_json::json!({"ids": []})
+            } else if path.ends_with("/query") {
+                let n = body["query_embeddings"].as_array().map_or(1, Vec::len);
+                serde_json::json!({
+                    "ids": vec![["demo-id-1"]; n],
+                    "documents": vec![["9 octopus recipes"]; n],
+                    "distances": vec![[0.1]; n],
+                })
+            } else if ["/add", "/upsert", "/delete"]
+                .iter()
+                .any(|suffix| path.ends_with(suffix))
+            {
+                serde_json::json!(true)
+            } else {
+                return ResponseTemplate::new(404);
+            };
+            ResponseTemplate::new(200).set_body_json(json)
+        })
+        .mount(&server)
+        .await;
+    let options = chromadb::client::ChromaClientOptions::builder()
+        .url(server.uri())
+        .build()
+        .unwrap();
+    (server, options)
+}
@@ -0,0 +1,333 @@
+use std::future::Future;
+use std::sync::Arc;
+
+use serde_json::Value;
+use tokio::runtime::{Handle, Runtime};
+
+use super::{
+    client::ChromaClientOptions,
+    collection::{
+        CollectionConfiguration, CollectionEntries, GetOptions, GetResult, QueryOptions,
+        QueryResult,
+    },
+    commons::{Metadata, Result},
+    embeddings::EmbeddingFunction,
+    error::ChromaError,
+};
+
+/// A runtime that can be dropped anywhere. Dropping a [Runtime] from within an async context panics.
+struct BlockingRuntime(Option<Runtime>);
+
+impl BlockingRuntime {
+    fn new() -> Result<Self> {
+        let runtime = tokio::runtime::Builder::new_current_thread()
+            .enable_all()
+            .build()?;
+        Ok(Self(Some(runtime)))
+    }
+
+    /// Run the future to completion on this runtime, failing instead of panicking when called from within an async context.
+    fn block_on<T>(&self, future: impl Future<Output = Result<T>>) -> Result<T> {
+        check_not_async()?;
+        self.0
+            .as_ref()
+            .expect("The runtime is only taken when dropped")
+            .block_on(future)
+    }
+}
+
+impl Drop for BlockingRuntime {
+    fn drop(&mut self) {
+        if let Some(runtime) = self.0.take() {
+            runtime.shutdown_background();
+        }
+    }
+}
+
+fn check_not_async() -> Result<()> {
+    if Handle::try_current().is_ok() {
+        return Err(ChromaError::invalid_argument(
+            "The blocking client cannot be used from within an async runtime, use chromadb::ChromaClient instead",
+        ));
+    }
+    Ok(())
+}
+
+/// A blocking [ChromaClient](crate::ChromaClient), for synchronous applications that do not run a tokio runtime.
+///
+/// It wraps the async client with an internal single-threaded runtime, like `reqwest::blocking` does.
+/// Every method blocks the calling thread until the request completes, and fails with
+/// [ChromaError::InvalidArgument] when called from within an async runtime, where blocking would stall it.
+///
+/// ```no_run
+/// use chromadb::blocking::ChromaClient;
+///
+///# fn doc_blocking_demo() -> anyhow::Result<()> {
+/// let client = ChromaClient::new(Default::default())?;
+/// let collection = client.get_or_create_collection("my_collection", None)?;
+/// println!("{} records", collection.count()?);
+///# Ok(())
+///# }
+/// ```
+pub struct ChromaClient {
+    inner: crate::ChromaClient,
+    runtime: Arc<BlockingRuntime>,
+}
+
+impl ChromaClient {
+    /// Create a new Chroma client. See [crate::ChromaClient::new].
+    pub fn new(options: ChromaClientOptions) -> Result<ChromaClient> {
+        check_not_async()?;
+        let runtime = BlockingRuntime::new()?;
+        let inner = runtime.block_on(crate::ChromaClient::new(options))?;
+        Ok(ChromaClient {
+            inner,
+            runtime: Arc::new(runtime),
+        })
+    }
+
+    /// The async client, e.g. for the methods without a blocking variant. It must be awaited on a runtime of its own.
+    pub fn as_async(&self) -> &crate::ChromaClient {
+        &self.inner
+    }
+
+    fn collection(&self, inner: crate::ChromaCollection) -> ChromaCollection {
+        ChromaCollection {
+            inner,
+            runtime: self.runtime.clone(),
+        }
+    }
+
+    /// Create a new collection. See [crate::ChromaClient::create_collection].
+    pub fn create_collection(
+        &self,
+        name: &str,
+        metadata: Option<Metadata>,
+        configuration: Option<CollectionConfiguration>,
+        get_or_create: bool,
+    ) -> Result<ChromaCollection> {
+        let inner = self.runtime.block_on(self.inner.create_collection(
+            name,
+            metadata,
+            configuration,
+            get_or_create,
+        ))?;
+        Ok(self.collection(inner))
+    }
+
+    /// Get or create a collection. See [crate::ChromaClient::get_or_create_collection].
+    pub fn get_or_create_collection(
+        &self,
+        name: &str,
+        metadata: Option<Metadata>,
+    ) -> Result<ChromaCollection> {
+        let inner = self
+            .runtime
+            .block_on(self.inner.get_or_create_collection(name, metadata))?;
+        Ok(self.collection(inner))
+    }
+
+    /// Get an existing collection. See [crate::ChromaClient::get_collection].
+    pub fn get_collection(&self, name: &str) -> Result<ChromaCollection> {
+        let inner = self.runtime.block_on(self.inner.get_collection(name))?;
+        Ok(self.collection(inner))
+    }
+
+    /// List all collections. See [crate::ChromaClient::list_collections].
+    pub fn list_collections(&self) -> Result<Vec<ChromaCollection>> {
+        let collections = self.runtime.block_on(self.inner.list_collections())?;
+        Ok(collections
+            .into_iter()
+            .map(|inner| self.collection(inner))
+            .collect())
+    }
+
+    /// Delete a collection. See [crate::ChromaClient::delete_collection].
+    pub fn delete_collection(&self, name: &str) -> Result<()> {
+        self.runtime.block_on(self.inner.delete_collection(name))
+    }
+}
+
+/// A blocking [ChromaCollection](crate::ChromaCollection), returned by the blocking [ChromaClient].
+pub struct ChromaCollection {
+    inner: crate::ChromaCollection,
+    runtime: Arc<BlockingRuntime>,
+}
+
+impl ChromaCollection {
+    /// The async collection, e.g. for its id, name and metadata.
+    pub fn as_async(&self) -> &crate::ChromaCollection {
+        &self.inner
+    }
+
+    /// The id of the collection.
+    pub fn id(&self) -> &str {
+        self.inner.id()
+    }
+
+    /// The name of the collection.
+    pub fn name(&self) -> &str {
+        self.inner.name()
+    }
+
+    /// The number of records in the collection. See [crate::ChromaCollection::count].
+    pub fn count(&self) -> Result<usize> {
+        self.runtime.block_on(self.inner.count())
+    }
+
+    /// Add records to the collection. See [crate::ChromaCollection::add].
+    pub fn add(
+        &self,
+        collection_entries: CollectionEntries<'_>,
+        embedding_function: Option<Box<dyn EmbeddingFunction>>,
+    ) -> Result<Value> {
+        self.runtime
+            .block_on(self.inner.add(collection_entries, embedding_function))
+    }
+
+    /// Add or update records of the collection. See [crate::ChromaCollection::upsert].
+    pub fn upsert(
+        &self,
+        collection_entries: CollectionEntries<'_>,
+        embedding_function: Option<Box<dyn EmbeddingFunction>>,
+    ) -> Result<Value> {
+        self.runtime
+            .block_on(self.inner.upsert(collection_entries, embedding_function))
+    }
+
+    /// Get records of the collection. See [crate::ChromaCollection::get].
+    pub fn get(&self, get_options: GetOptions) -> Result<GetResult> {
+        self.runtime.block_on(self.inner.get(get_options))
+    }
+
+    /// Query the nearest records. See [crate::ChromaCollection::query].
+    pub fn query(
+        &self,
+        query_options: QueryOptions<'_>,
+        embedding_function: Option<Box<dyn EmbeddingFunction>>,
+    ) -> Result<QueryResult> {
+        self.runtime
+            .block_on(self.inner.query(query_options, embedding_function))
+    }
+
+    /// Delete records of the collection. See [crate::ChromaCollection::delete].
+    pub fn delete(
+        &self,
+        ids: Option<Vec<&str>>,
+        where_metadata: Option<Value>,
+        where_document: Option<Value>,
+    ) -> Result<()> {
+        self.runtime
+            .block_on(self.inner.delete(ids, where_metadata, where_document))
+    }
+
+    /// Delete every record of the collection. See [crate::ChromaCollection::delete_all].
+    pub fn delete_all(&self) -> Result<Vec<String>> {
+        self.runtime.block_on(self.inner.delete_all())
+    }
+
+    /// Delete the records whose metadata matches the filter. See [crate::ChromaCollection::delete_by_metadata].
+    pub fn delete_by_metadata(&self, filter: Value) -> Result<Vec<String>> {
+        self.runtime.block_on(self.inner.delete_by_metadata(filter))
+    }
+
+    /// Delete the records whose document matches the filter. See [crate::ChromaCollection::delete_by_document].
+    pub fn delete_by_document(&self, filter: Value) -> Result<Vec<String>> {
+        self.runtime.block_on(self.inner.delete_by_document(filter))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+    use wiremock::matchers::{method, path_regex};
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+
+    #[test]
+    fn test_blocking_client() {
+        // The mock server runs on a thread of its own, the test runtime only starts it.
+        let test_runtime = tokio::runtime::Builder::new_current_thread()
+            .enable_all()
+            .build()
+            .unwrap();
+        let server = test_runtime.block_on(MockServer::start());
+        let collection = json!({"id": "c1", "name": "blocking", "metadata": null});
+        test_runtime.block_on(async {
+            Mock::given(method("POST"))
+                .and(path_regex("/collections$"))
+                .respond_with(ResponseTemplate::new(200).set_body_json(&collection))
+                .expect(1)
+                .mount(&server)
+                .await;
+            Mock::given(method("GET"))
+                .and(path_regex("/collections$"))
+                .respond_with(ResponseTemplate::new(200).set_body_json(json!([collection])))
+                .expect(1)
+                .mount(&server)
+                .await;
+            Mock::given(method("GET"))
+                .and(path_regex("/collections/c1/count$"))
+                .respond_with(ResponseTemplate::new(200).set_body_json(2))
+                .expect(1)
+                .mount(&server)
+                .await;
+            Mock::given(method("POST"))
+                .and(path_regex("/collections/c1/get$"))
+                .respond_with(ResponseTemplate::new(200).set_body_json(json!({"ids": ["a", "b"]})))
+                .expect(1)
+                .mount(&server)
+                .await;
+            Mock::given(method("DELETE"))
+                .and(path_regex("/collections/blocking$"))
+                .respond_with(ResponseTemplate::new(200).set_body_json(json!(null)))
+                .expect(1)
+                .mount(&server)
+                .await;
+        });
+
+        let options = ChromaClientOptions::builder()
+            .url(server.uri())
+            .build()
+            .unwrap();
+        let client = ChromaClient::new(options).unwrap();
+        let collection = client.get_or_create_collection("blocking", None).unwrap();
+        assert_eq!((collection.id(), collection.name()), ("c1", "blocking"));
+        assert_eq!(collection.count().unwrap(), 2);
+        assert_eq!(
+            collection.get(GetOptions::default()).unwrap().ids,
+            vec!["a", "b"]
+        );
+        let collections = client.list_collections().unwrap();
+        assert_eq!(collections[0].id(), "c1");
+        client.delete_collection("blocking").unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_blocking_client_in_async_context() {
+        let error = ChromaClient::new(Default::default()).err().unwrap();
+        assert!(matches!(error, ChromaError::InvalidArgument { .. }));
+        assert!(error.to_string().contains("async runtime"));
+    }
+
+    #[test]
+    fn test_blocking_collection_in_async_context() {
+        let test_runtime = tokio::runtime::Builder::new_current_thread()
+            .enable_all()
+            .build()
+            .unwrap();
+        let server = test_runtime.block_on(MockServer::start());
+        let options = ChromaClientOptions::builder()
+            .url(server.uri())
+            .build()
+            .unwrap();
+        let client = ChromaClient::new(options).unwrap();
+
+        // Called from a task rather than failing with a panic, then dropped there without one.
+        test_runtime.block_on(async move {
+            let error = client.list_collections().err().unwrap();
+            assert!(matches!(error, ChromaError::InvalidArgument { .. }));
+            drop(client);
+        });
+    }
+}
@@ -1,26 +1,223 @@
-use anyhow::bail;
+use futures::{Stream, StreamExt};
 use serde::{Deserialize, Serialize};
 use serde_json::{json, Value};
-use std::{collections::HashSet, sync::Arc, vec};
+use std::{
+    collections::{BTreeMap, HashMap, HashSet},
+    sync::Arc,
+    time::{Duration, Instant},
+    vec,
+};
 
 use super::{
-    api::APIClientAsync,
-    commons::{Documents, Embedding, Embeddings, Metadata, Metadatas, Result, ConfigurationJson},
-    embeddings::EmbeddingFunction,
+    api::{read_json, read_json_or_null, APIClientAsync},
+    commons::{ConfigurationJson, Documents, Embedding, Embeddings, Metadata, Metadatas, Result},
+    embeddings::{EmbeddingFunction, EMBEDDING_FUNCTION_KEY},
+    error::ChromaError,
+    schema::MetadataSchema,
+    search::{SearchRequest, SearchResponse, SearchResult},
+    trace::{QueryLogger, QueryTrace},
+    warnings::{Warning, Warnings},
 };
 
+pub use super::api::ResponseMeta;
+
 /// A collection representation for interacting with the associated ChromaDB collection.
-#[derive(Deserialize, Debug)]
+#[derive(Deserialize)]
 pub struct ChromaCollection {
     #[serde(skip)]
     pub(super) api: Arc<APIClientAsync>,
+    #[serde(skip)]
+    pub(super) embedding_function: Option<Arc<dyn EmbeddingFunction>>,
     pub(super) id: String,
     pub(super) metadata: Option<Metadata>,
     pub(super) name: String,
-    pub(super) configuration_json: Option<ConfigurationJson>
+    pub(super) configuration_json: Option<ConfigurationJson>,
+    #[serde(default)]
+    pub(super) dimension: Option<usize>,
+    #[serde(skip, default = "default_max_validation_violations")]
+    pub(super) max_validation_violations: usize,
+    #[serde(skip)]
+    pub(super) float_precision: Option<u8>,
+    #[serde(skip)]
+    pub(super) metadata_schema: Option<Arc<MetadataSchema>>,
+    #[serde(skip)]
+    pub(super) strict_embedding_function: bool,
+    #[serde(skip)]
+    pub(super) strict_embeddings: bool,
+    #[serde(skip)]
+    pub(super) query_logger: Option<Arc<dyn QueryLogger>>,
+    #[serde(skip)]
+    pub(super) exclusion_filters: Vec<Value>,
+}
+
+const DEFAULT_MAX_VALIDATION_VIOLATIONS: usize = 100;
+/// The number of results Chroma returns per query when `n_results` is not set.
+pub(crate) const DEFAULT_N_RESULTS: usize = 10;
+/// The number of single-record gets in flight at once in [ChromaCollection::sample].
+const SAMPLE_CONCURRENCY: usize = 8;
+/// The number of texts embedded and written per request by [ChromaCollection::add_texts].
+const ADD_TEXTS_CHUNK_SIZE: usize = 100;
+
+fn default_max_validation_violations() -> usize {
+    DEFAULT_MAX_VALIDATION_VIOLATIONS
+}
+
+impl std::fmt::Debug for ChromaCollection {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ChromaCollection")
+            .field("api", &self.api)
+            .field("embedding_function", &self.embedding_function.is_some())
+            .field("id", &self.id)
+            .field("metadata", &self.metadata)
+            .field("name", &self.name)
+            .field("configuration_json", &self.configuration_json)
+            .field("dimension", &self.dimension)
+            .field("max_validation_violations", &self.max_validation_violations)
+            .field("float_precision", &self.float_precision)
+            .field("metadata_schema", &self.metadata_schema)
+            .field("strict_embedding_function", &self.strict_embedding_function)
+            .field("strict_embeddings", &self.strict_embeddings)
+            .field("query_logger", &self.query_logger.is_some())
+            .field("exclusion_filters", &self.exclusion_filters)
+            .finish()
+    }
 }
 
 impl ChromaCollection {
+    /// Attach an embedding function to this collection handle. It is used by the convenience methods, like [similarity_search](Self::similarity_search), that embed text on the caller's behalf.
+    pub fn with_embedding_function(
+        mut self,
+        embedding_function: Arc<dyn EmbeddingFunction>,
+    ) -> Self {
+        self.embedding_function = Some(embedding_function);
+        self
+    }
+
+    /// Add a metadata filter that [similarity_search](Self::similarity_search) always applies, `$and`-merged with its own filter,
+    /// e.g. `{"deleted": {"$ne": true}}` to leave soft-deleted entries out. Can be called several times.
+    pub fn with_exclusion_filter(mut self, filter: Value) -> Self {
+        self.exclusion_filters.push(filter);
+        self
+    }
+
+    /// Set the maximum number of violations reported in a [ValidationError]. Defaults to 100.
+    pub fn with_max_validation_violations(mut self, max_violations: usize) -> Self {
+        self.max_validation_violations = max_violations;
+        self
+    }
+
+    /// Round embedding components to `precision` decimal places when sending them in add, upsert and update.
+    ///
+    /// Fewer digits make for much smaller request bodies, e.g. 4 decimal places roughly halve them for typical embeddings.
+    /// The rounding error is at most half a unit in the last place kept, which leaves recall unchanged in practice
+    /// for 4 or more decimal places, but can reorder near-ties between close neighbors at lower precisions.
+    pub fn with_float_precision(mut self, precision: u8) -> Self {
+        self.float_precision = Some(precision);
+        self
+    }
+
+    /// Get the embedding function attached to this collection handle, if any.
+    pub fn embedding_function(&self) -> Option<&Arc<dyn EmbeddingFunction>> {
+        self.embedding_function.as_ref()
+    }
+
+    fn resolve_embedding_function(
+        &self,
+        embedding_function: Option<Box<dyn EmbeddingFunction>>,
+    ) -> Option<Box<dyn EmbeddingFunction>> {
+        embedding_function.or_else(|| {
+            self.embedding_function
+                .clone()
+                .map(|ef| Box::new(ef) as Box<dyn EmbeddingFunction>)
+        })
+    }
+
+    /// Fail queries embedded with another embedding function than the one recorded by
+    /// [save_embedding_function](Self::save_embedding_function), instead of reporting a [Warning::EmbeddingFunctionMismatch].
+    pub fn with_strict_embedding_function(mut self, strict: bool) -> Self {
+        self.strict_embedding_function = strict;
+        self
+    }
+
+    /// Fail gets and queries that request [Include::Embeddings] when the server leaves them out, as some managed deployments
+    /// do for large results, with [ChromaError::EmbeddingsUnavailable] instead of reporting a [Warning::EmbeddingsUnavailable].
+    pub fn with_strict_embeddings(mut self, strict: bool) -> Self {
+        self.strict_embeddings = strict;
+        self
+    }
+
+    /// Report the records whose embeddings were requested but not returned, failing if the handle is strict.
+    fn check_embeddings_returned(&self, missing: usize, total: usize) -> Result<()> {
+        if missing == 0 {
+            return Ok(());
+        }
+        if self.strict_embeddings {
+            return Err(ChromaError::EmbeddingsUnavailable { missing, total });
+        }
+        self.api
+            .warn(Warning::EmbeddingsUnavailable { missing, total });
+        Ok(())
+    }
+
+    /// Pass every successful query of this handle to `query_logger`, with its results and latency.
+    /// It covers [query](Self::query) and the helpers built on it, like [similarity_search](Self::similarity_search).
+    pub fn with_query_logger(mut self, query_logger: Arc<dyn QueryLogger>) -> Self {
+        self.query_logger = Some(query_logger);
+        self
+    }
+
+    /// The [identity](EmbeddingFunction::identity) of the embedding function recorded in the collection metadata, if any.
+    pub fn recorded_embedding_function(&self) -> Option<&str> {
+        self.metadata
+            .as_ref()?
+            .get(EMBEDDING_FUNCTION_KEY)?
+            .as_str()
+    }
+
+    /// Record the identity of the embedding function in the collection metadata, keeping its other keys,
+    /// so that queries through any handle can detect a different model.
+    ///
+    /// # Errors
+    ///
+    /// * If the embedding function has no [identity](EmbeddingFunction::identity)
+    pub async fn save_embedding_function(
+        &mut self,
+        embedding_function: &dyn EmbeddingFunction,
+    ) -> Result<()> {
+        let Some(identity) = embedding_function.identity() else {
+            return Err(ChromaError::invalid_argument(
+                "The embedding function has no identity to record",
+            ));
+        };
+        let mut metadata = self.metadata.clone().unwrap_or_default();
+        metadata.insert(EMBEDDING_FUNCTION_KEY.to_string(), Value::String(identity));
+        self.modify(None, Some(&metadata)).await?;
+        self.metadata = Some(metadata);
+        Ok(())
+    }
+
+    /// Compare the embedding function of a query with the one recorded for the collection.
+    fn check_embedding_function(&self, embedding_function: &dyn EmbeddingFunction) -> Result<()> {
+        let (Some(collection), Some(query)) = (
+            self.recorded_embedding_function(),
+            embedding_function.identity(),
+        ) else {
+            return Ok(());
+        };
+        if collection == query {
+            return Ok(());
+        }
+        let warning = Warning::EmbeddingFunctionMismatch {
+            collection: collection.to_string(),
+            query,
+        };
+        if self.strict_embedding_function {
+            return Err(ChromaError::invalid_argument(warning.to_string()));
+        }
+        self.api.warn(warning);
+        Ok(())
+    }
+
     /// Get the UUID of the collection.
     pub fn id(&self) -> &str {
         self.id.as_ref()
@@ -36,11 +233,103 @@ impl ChromaCollection {
         self.metadata.as_ref()
     }
 
+    /// Get the index configuration of the collection, as reported by the server.
+    /// `None` if the server did not report one, or it could not be parsed.
+    pub fn configuration(&self) -> Option<CollectionConfiguration> {
+        CollectionConfiguration::try_from(self.configuration_json.clone()?).ok()
+    }
+
+    /// Get the distance function of the collection's index, from the `hnsw:space` key of its metadata, or else from its configuration.
+    /// `None` if neither sets a known one, in which case the server uses [DistanceFunction::L2].
+    pub fn distance_function(&self) -> Option<DistanceFunction> {
+        let space = self
+            .metadata
+            .as_ref()
+            .and_then(|metadata| metadata.get(DISTANCE_FUNCTION_KEY))
+            .and_then(Value::as_str)
+            .map(str::to_string)
+            .or_else(|| self.configuration()?.hnsw?.space)?;
+        space.parse().ok()
+    }
+
+    /// Get the dimensionality of the embeddings in the collection.
+    /// Only populated by servers that report it, and `None` until the first embedding is added.
+    /// Call [refresh](Self::refresh) to pick up changes made after this handle was fetched.
+    pub fn dimension(&self) -> Option<usize> {
+        self.dimension
+    }
+
+    /// Check the metadata of every record written through this handle against the schema, failing with a [ValidationError] listing the records that break it.
+    /// Use [load_metadata_schema](Self::load_metadata_schema) instead to enforce the schema shared by every writer of the collection.
+    pub fn with_metadata_schema(mut self, schema: MetadataSchema) -> Self {
+        self.metadata_schema = Some(Arc::new(schema));
+        self
+    }
+
+    /// The metadata schema enforced by this handle, if any.
+    pub fn metadata_schema(&self) -> Option<&MetadataSchema> {
+        self.metadata_schema.as_deref()
+    }
+
+    /// Re-fetch the collection and enforce the metadata schema stored in its metadata, if any. Returns whether one was found.
+    ///
+    /// # Errors
+    ///
+    /// * If the stored schema is invalid
+    pub async fn load_metadata_schema(&mut self) -> Result<bool> {
+        self.refresh().await?;
+        let schema = MetadataSchema::from_collection_metadata(self.metadata.as_ref())?;
+        let found = schema.is_some();
+        self.metadata_schema = schema.map(Arc::new);
+        Ok(found)
+    }
+
+    /// Store the schema in the collection metadata, keeping its other keys, so that every writer can [load](Self::load_metadata_schema) it,
+    /// and enforce it through this handle.
+    pub async fn save_metadata_schema(&mut self, schema: MetadataSchema) -> Result<()> {
+        let mut metadata = self.metadata.clone().unwrap_or_default();
+        schema.write_to_collection_metadata(&mut metadata);
+        self.modify(None, Some(&metadata)).await?;
+        self.metadata = Some(metadata);
+        self.metadata_schema = Some(Arc::new(schema));
+        Ok(())
+    }
+
+    /// Re-fetch the collection from the server by name, updating the metadata, configuration and dimension of this handle.
+    ///
+    /// # Errors
+    ///
+    /// * [ChromaError::CollectionNotFound] if the name no longer resolves to this collection, e.g. because it was renamed elsewhere.
+    ///   Use [ChromaClient::get_collection_by_id](crate::ChromaClient::get_collection_by_id) to find it under its new name.
+    pub async fn refresh(&mut self) -> Result<()> {
+        let path = format!("/collections/{}", self.name);
+        let response = self.api.get_database(&path).await?;
+        let collection = read_json::<ChromaCollection>(response).await?;
+        if collection.id != self.id {
+            return Err(ChromaError::CollectionNotFound(format!(
+                "Collection {:?} is now {}, not {}",
+                self.name, collection.id, self.id
+            )));
+        }
+        self.metadata = collection.metadata;
+        self.configuration_json = collection.configuration_json;
+        self.dimension = collection.dimension;
+        Ok(())
+    }
+
     /// The total number of embeddings added to the database.
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(
+            name = "chroma.collection.count",
+            skip_all,
+            fields(collection_id = %self.id)
+        )
+    )]
     pub async fn count(&self) -> Result<usize> {
         let path = format!("/collections/{}/count", self.id);
         let response = self.api.get_database(&path).await?;
-        let count = response.json::<usize>().await?;
+        let count = read_json::<usize>(response).await?;
         Ok(count)
     }
 
@@ -54,6 +343,14 @@ impl ChromaCollection {
     /// # Errors
     ///
     /// * If the collection name is invalid
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(
+            name = "chroma.collection.modify",
+            skip_all,
+            fields(collection_id = %self.id)
+        )
+    )]
     pub async fn modify(&self, name: Option<&str>, metadata: Option<&Metadata>) -> Result<()> {
         let json_body = json!({
             "new_name": name,
@@ -65,6 +362,7 @@ impl ChromaCollection {
     }
 
     /// Add embeddings to the data store. Ignore the insert if the ID already exists.
+    /// An empty batch is not sent: it is a no-op reported as a [Warning::EmptyBatch].
     ///
     /// # Arguments
     ///
@@ -82,36 +380,39 @@ impl ChromaCollection {
     /// * If you provide documents and don't provide an embedding function when embeddings is None
     /// * If you provide an embedding function and don't provide documents
     /// * If you provide both embeddings and embedding_function
+    /// * If a record's metadata does not match the [MetadataSchema] of this handle
     ///
     pub async fn add<'a>(
         &self,
         collection_entries: CollectionEntries<'a>,
         embedding_function: Option<Box<dyn EmbeddingFunction>>,
     ) -> Result<Value> {
-        let collection_entries = validate(true, collection_entries, embedding_function).await?;
-
-        let CollectionEntries {
-            ids,
-            embeddings,
-            metadatas,
-            documents,
-        } = collection_entries;
-
-        let json_body = json!({
-            "ids": ids,
-            "embeddings": embeddings,
-            "metadatas": metadatas,
-            "documents": documents,
-        });
-
-        let path = format!("/collections/{}/add", self.id);
-        let response = self.api.post_database(&path, Some(json_body)).await?;
-        let response = response.json::<Value>().await?;
+        self.add_with_options(collection_entries, embedding_function, Default::default())
+            .await
+    }
 
-        Ok(response)
+    /// The same as [add](Self::add), with [UpsertOptions] controlling how the embeddings are computed.
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(
+            name = "chroma.collection.add",
+            skip_all,
+            fields(collection_id = %self.id,
+            entry_count = collection_entries.ids.len())
+        )
+    )]
+    pub async fn add_with_options<'a>(
+        &self,
+        collection_entries: CollectionEntries<'a>,
+        embedding_function: Option<Box<dyn EmbeddingFunction>>,
+        options: UpsertOptions,
+    ) -> Result<Value> {
+        self.write("add", collection_entries, embedding_function, options)
+            .await
     }
 
     /// Add embeddings to the data store. Update the entry if an ID already exists.
+    /// An empty batch is not sent: it is a no-op reported as a [Warning::EmptyBatch].
     ///
     /// # Arguments
     ///
@@ -129,35 +430,250 @@ impl ChromaCollection {
     /// * If you provide documents and don't provide an embedding function when embeddings is None
     /// * If you provide an embedding function and don't provide documents
     /// * If you provide both embeddings and embedding_function
+    /// * If a record's metadata does not match the [MetadataSchema] of this handle
     ///
     pub async fn upsert<'a>(
         &self,
         collection_entries: CollectionEntries<'a>,
         embedding_function: Option<Box<dyn EmbeddingFunction>>,
     ) -> Result<Value> {
-        let collection_entries = validate(true, collection_entries, embedding_function).await?;
+        self.upsert_with_options(collection_entries, embedding_function, Default::default())
+            .await
+    }
 
-        let CollectionEntries {
-            ids,
-            embeddings,
-            metadatas,
-            documents,
-        } = collection_entries;
+    /// The same as [upsert](Self::upsert), with [UpsertOptions] controlling how the embeddings are computed.
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(
+            name = "chroma.collection.upsert",
+            skip_all,
+            fields(collection_id = %self.id,
+            entry_count = collection_entries.ids.len())
+        )
+    )]
+    pub async fn upsert_with_options<'a>(
+        &self,
+        collection_entries: CollectionEntries<'a>,
+        embedding_function: Option<Box<dyn EmbeddingFunction>>,
+        options: UpsertOptions,
+    ) -> Result<Value> {
+        self.write("upsert", collection_entries, embedding_function, options)
+            .await
+    }
 
-        let json_body = json!({
-            "ids": ids,
-            "embeddings": embeddings,
-            "metadatas": metadatas,
-            "documents": documents,
-        });
+    async fn write(
+        &self,
+        operation: &'static str,
+        collection_entries: CollectionEntries<'_>,
+        embedding_function: Option<Box<dyn EmbeddingFunction>>,
+        options: UpsertOptions,
+    ) -> Result<Value> {
+        if self.skip_empty_batch(operation, &collection_entries, embedding_function.is_some())? {
+            return Ok(json!({}));
+        }
+        self.check_metadata_schema(&collection_entries, false)?;
+        let collection_entries = validate(
+            true,
+            collection_entries,
+            embedding_function,
+            options.concurrency.unwrap_or(1),
+            self.max_validation_violations,
+        )
+        .await?;
 
-        let path = format!("/collections/{}/upsert", self.id);
-        let response = self.api.post_database(&path, Some(json_body)).await?;
-        let response = response.json::<Value>().await?;
+        let path = format!("/collections/{}/{operation}", self.id);
+        let body = WriteBody::new(&collection_entries, self.float_precision);
+        let response = self.api.post_database_json(&path, &body).await?;
+        let response = read_json_or_null(response).await?;
 
         Ok(response)
     }
 
+    /// Add embeddings to the data store in chunks of at most `chunk_size` entries, for batches too large for a single request.
+    ///
+    /// The entries are validated as a whole before anything is written, then each chunk goes through [add](Self::add).
+    /// Returns the response of every chunk, in order, along with any warnings.
+    ///
+    /// # Errors
+    ///
+    /// * The same as [add](Self::add), for the whole batch
+    /// * If `chunk_size` is 0. It is lowered to the server's max_batch_size if it is known, see [ChromaClient::pre_flight_checks](crate::ChromaClient::pre_flight_checks).
+    /// * If the embedding function fails its health check and `options.preflight_embeddings` is set
+    /// * [ChromaError::ChunkFailed] with the index of the first chunk that failed. With a concurrency above 1, later chunks may have been written too.
+    ///
+    pub async fn add_chunked<'a>(
+        &self,
+        collection_entries: CollectionEntries<'a>,
+        chunk_size: usize,
+        embedding_function: Option<Box<dyn EmbeddingFunction>>,
+        options: AddOptions,
+    ) -> Result<AddResult> {
+        self.write_chunked(
+            false,
+            collection_entries,
+            chunk_size,
+            embedding_function,
+            options,
+        )
+        .await
+    }
+
+    /// Add embeddings to the data store in chunks of at most `chunk_size` entries. Update the entry if an ID already exists.
+    ///
+    /// The entries are validated as a whole before anything is written, then each chunk goes through [upsert](Self::upsert).
+    /// Returns the response of every chunk, in order, along with any warnings.
+    ///
+    /// # Errors
+    ///
+    /// * The same as [upsert](Self::upsert), for the whole batch
+    /// * If `chunk_size` is 0. It is lowered to the server's max_batch_size if it is known, see [ChromaClient::pre_flight_checks](crate::ChromaClient::pre_flight_checks).
+    /// * If the embedding function fails its health check and `options.preflight_embeddings` is set
+    /// * [ChromaError::ChunkFailed] with the index of the first chunk that failed. With a concurrency above 1, later chunks may have been written too.
+    ///
+    pub async fn upsert_chunked<'a>(
+        &self,
+        collection_entries: CollectionEntries<'a>,
+        chunk_size: usize,
+        embedding_function: Option<Box<dyn EmbeddingFunction>>,
+        options: AddOptions,
+    ) -> Result<AddResult> {
+        self.write_chunked(
+            true,
+            collection_entries,
+            chunk_size,
+            embedding_function,
+            options,
+        )
+        .await
+    }
+
+    async fn write_chunked(
+        &self,
+        upsert: bool,
+        collection_entries: CollectionEntries<'_>,
+        chunk_size: usize,
+        embedding_function: Option<Box<dyn EmbeddingFunction>>,
+        options: AddOptions,
+    ) -> Result<AddResult> {
+        if chunk_size == 0 {
+            return Err(ChromaError::invalid_argument(
+                "chunk_size must be at least 1",
+            ));
+        }
+        check_entries(
+            true,
+            &collection_entries,
+            embedding_function.is_some(),
+            self.max_validation_violations,
+        )?;
+        self.check_metadata_schema(&collection_entries, false)?;
+        let embedding_function: Option<Arc<dyn EmbeddingFunction>> =
+            embedding_function.map(Arc::from);
+        let mut warnings = Warnings::default();
+        match (options.preflight_embeddings, &embedding_function) {
+            (true, Some(embedding_function)) => embedding_function
+                .health_check()
+                .await
+                .map_err(ChromaError::EmbeddingError)?,
+            (true, None) => warnings.push(Warning::PreflightSkipped, self.api.warning_callback()),
+            (false, _) => {}
+        }
+
+        let total = collection_entries.ids.len();
+        let chunk_size = match self.api.max_batch_size() {
+            Some(max_batch_size) => chunk_size.min(max_batch_size),
+            None => chunk_size,
+        };
+        let chunks = split_entries(
+            collection_entries,
+            chunk_size,
+            options.max_chunk_bytes,
+            self.float_precision,
+        );
+        let mut responses = futures::stream::iter(chunks.into_iter().enumerate())
+            .map(|(chunk, collection_entries)| {
+                let embedding_function = embedding_function
+                    .clone()
+                    .map(|f| Box::new(f) as Box<dyn EmbeddingFunction>);
+                async move {
+                    let len = collection_entries.ids.len();
+                    let response = if upsert {
+                        self.upsert(collection_entries, embedding_function).await
+                    } else {
+                        self.add(collection_entries, embedding_function).await
+                    };
+                    response
+                        .map(|response| (len, response))
+                        .map_err(|e| ChromaError::ChunkFailed {
+                            chunk,
+                            source: Box::new(e),
+                        })
+                }
+            })
+            .buffered(options.concurrency.max(1));
+
+        let mut results = Vec::new();
+        let mut written = 0;
+        while let Some(result) = responses.next().await {
+            let (len, response) = result?;
+            written += len;
+            if let Some(progress) = &options.progress {
+                progress(written, total);
+            }
+            results.push(response);
+        }
+        Ok(AddResult {
+            responses: results,
+            warnings,
+        })
+    }
+
+    /// Embed and upsert the given texts under freshly generated UUID ids.
+    ///
+    /// The texts are written with [upsert_chunked](Self::upsert_chunked), in chunks of 100 or the server's max_batch_size if lower.
+    ///
+    /// # Arguments
+    ///
+    /// * `texts` - The documents to add.
+    /// * `metadatas` - The metadata to associate with each document. Optional.
+    /// * `embedding_function` - The function to use to compute the embeddings. Falls back to the function attached with [with_embedding_function](Self::with_embedding_function). Optional.
+    ///
+    /// Returns the generated ids, in the same order as `texts`.
+    ///
+    /// # Errors
+    ///
+    /// * If no embedding function is provided or attached
+    /// * If the length of texts and metadatas don't match
+    /// * [ChromaError::ChunkFailed] if a chunk could not be embedded or written. The chunks before it were written.
+    ///
+    pub async fn add_texts(
+        &self,
+        texts: Vec<&str>,
+        metadatas: Option<Metadatas>,
+        embedding_function: Option<Box<dyn EmbeddingFunction>>,
+    ) -> Result<Vec<String>> {
+        let Some(embedding_function) = self.resolve_embedding_function(embedding_function) else {
+            return Err(ChromaError::invalid_argument(
+                "You must provide or attach an embedding function to use add_texts",
+            ));
+        };
+        let ids = generate_ids(texts.len());
+        let collection_entries = CollectionEntries {
+            ids: ids.iter().map(String::as_str).collect(),
+            metadatas,
+            documents: Some(texts),
+            embeddings: None,
+        };
+        self.upsert_chunked(
+            collection_entries,
+            ADD_TEXTS_CHUNK_SIZE,
+            Some(embedding_function),
+            AddOptions::default(),
+        )
+        .await?;
+        Ok(ids)
+    }
+
     /// Get embeddings and their associated data from the collection. If no ids or filter is provided returns all embeddings up to limit starting at offset.
     ///
     /// # Arguments
@@ -167,9 +683,27 @@ impl ChromaCollection {
     /// * `limit` - The maximum number of documents to return. Optional.
     /// * `offset` - The offset to start returning results from. Useful for paging results with limit. Optional.
     /// * `where_document` - Used to filter by the documents. E.g. {"$contains": "hello"}. See <https://docs.trychroma.com/usage-guide#filtering-by-document-contents> for more information on document content filters. Optional.
-    /// * `include` - A list of what to include in the results. Can contain `"embeddings"`, `"metadatas"`, `"documents"`. Ids are always included. Defaults to `["metadatas", "documents"]`. Optional.
+    /// * `include` - A list of what to include in the results. Can contain [Include::Embeddings], [Include::Metadatas], [Include::Documents]. Ids are always included. Defaults to metadatas and documents. Optional.
+    /// * `sort` - The order to sort the returned records in, client-side. Only the records of this page are sorted, use [get_all](Self::get_all) for a global order. Optional.
     ///
     pub async fn get(&self, get_options: GetOptions) -> Result<GetResult> {
+        Ok(self.get_with_meta(get_options).await?.0)
+    }
+
+    /// The same as [get](Self::get), but also returns the [ResponseMeta] of the request, e.g. to log the server's request id.
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(
+            name = "chroma.collection.get",
+            skip_all,
+            fields(collection_id = %self.id,
+            limit = get_options.limit)
+        )
+    )]
+    pub async fn get_with_meta(
+        &self,
+        get_options: GetOptions,
+    ) -> Result<(GetResult, ResponseMeta)> {
         let GetOptions {
             ids,
             where_metadata,
@@ -177,7 +711,12 @@ impl ChromaCollection {
             offset,
             where_document,
             include,
+            sort,
         } = get_options;
+        check_filters(&where_metadata, &where_document)?;
+        let embeddings_requested = include
+            .as_ref()
+            .is_some_and(|include| include.contains(&Include::Embeddings));
         let mut json_body = json!({
             "ids": if !ids.is_empty() { Some(ids) } else { None },
             "where": where_metadata,
@@ -193,12 +732,163 @@ impl ChromaCollection {
             .retain(|_, v| !v.is_null());
 
         let path = format!("/collections/{}/get", self.id);
+        let start = Instant::now();
         let response = self.api.post_database(&path, Some(json_body)).await?;
-        let get_result = response.json::<GetResult>().await?;
+        let meta = self.api.response_meta(&response, start.elapsed());
+        let mut get_result = read_json::<GetResult>(response).await?;
+        if embeddings_requested {
+            self.check_embeddings_returned(get_result.missing_embeddings(), get_result.ids.len())?;
+        }
+        if let Some(sort) = &sort {
+            get_result.sort(sort);
+        }
+        Ok((get_result, meta))
+    }
+
+    /// The same as [get](Self::get), but also returns the number of records matching the options, ignoring `limit` and `offset`,
+    /// e.g. to compute the number of pages. The total is also set on [GetResult::total].
+    ///
+    /// Chroma can only count whole collections, so with ids or filters, the ids of every matching record are fetched to count them.
+    /// The total is read before the page, so records added or deleted in between make them disagree.
+    pub async fn get_with_count(&self, get_options: GetOptions) -> Result<(GetResult, usize)> {
+        let total = if get_options.ids.is_empty()
+            && get_options.where_metadata.is_none()
+            && get_options.where_document.is_none()
+        {
+            self.count().await?
+        } else {
+            self.get(GetOptions {
+                ids: get_options.ids.clone(),
+                where_metadata: get_options.where_metadata.clone(),
+                where_document: get_options.where_document.clone(),
+                include: Some(vec![]),
+                ..Default::default()
+            })
+            .await?
+            .ids
+            .len()
+        };
+        let mut get_result = self.get(get_options).await?;
+        get_result.total = Some(total);
+        Ok((get_result, total))
+    }
+
+    /// Get every record matching the options, fetching them `page_size` at a time.
+    /// `limit` and `offset` in the options are ignored. Since the whole result is held, `sort` orders it globally.
+    ///
+    /// Records added or deleted while paging may be skipped or returned twice.
+    ///
+    /// # Errors
+    ///
+    /// * If `page_size` is 0
+    ///
+    pub async fn get_all(&self, get_options: GetOptions, page_size: usize) -> Result<GetResult> {
+        if page_size == 0 {
+            return Err(ChromaError::invalid_argument(
+                "page_size must be at least 1",
+            ));
+        }
+        let GetOptions {
+            ids,
+            where_metadata,
+            limit: _,
+            offset: _,
+            where_document,
+            include,
+            sort,
+        } = get_options;
+
+        let mut get_result = GetResult::default();
+        loop {
+            let page = self
+                .get(GetOptions {
+                    ids: ids.clone(),
+                    where_metadata: where_metadata.clone(),
+                    limit: Some(page_size),
+                    offset: Some(get_result.ids.len()),
+                    where_document: where_document.clone(),
+                    include: include.clone(),
+                    sort: None,
+                })
+                .await?;
+            let last_page = page.ids.len() < page_size;
+            get_result.extend(page);
+            if last_page {
+                break;
+            }
+        }
+        if let Some(sort) = &sort {
+            get_result.sort(sort);
+        }
         Ok(get_result)
     }
 
+    /// Stream the records matching the options, fetching them lazily `page_size` at a time, one [GetResult] per page.
+    /// `ids`, the filters and `include` apply to every page. The stream starts at `offset` and ends after `limit` records, if set.
+    /// It also ends after the first page with fewer records than requested, and never yields an empty page.
+    ///
+    /// Records added or deleted while streaming may be skipped or yielded twice.
+    ///
+    /// # Errors
+    ///
+    /// * If `page_size` is 0, as the only item of the stream
+    /// * If `sort` is set, as the only item of the stream. Sorting page by page would not order the stream, use [get_all](Self::get_all) instead.
+    ///
+    pub fn iter(
+        &self,
+        get_options: GetOptions,
+        page_size: usize,
+    ) -> impl Stream<Item = Result<GetResult>> + '_ {
+        let start = (get_options.offset.unwrap_or(0), get_options.limit);
+        futures::stream::try_unfold(Some(start), move |state| {
+            let get_options = get_options.clone();
+            async move {
+                let Some((offset, remaining)) = state else {
+                    return Ok(None);
+                };
+                if page_size == 0 {
+                    return Err(ChromaError::invalid_argument(
+                        "page_size must be at least 1",
+                    ));
+                }
+                if get_options.sort.is_some() {
+                    return Err(ChromaError::invalid_argument(
+                        "iter cannot sort across pages, use get_all to sort every record",
+                    ));
+                }
+                let limit = remaining.map_or(page_size, |remaining| remaining.min(page_size));
+                if limit == 0 {
+                    return Ok(None);
+                }
+                let page = self
+                    .get(GetOptions {
+                        limit: Some(limit),
+                        offset: Some(offset),
+                        ..get_options
+                    })
+                    .await?;
+                if page.ids.is_empty() {
+                    return Ok(None);
+                }
+                let fetched = page.ids.len();
+                let next = (fetched == limit).then(|| {
+                    (
+                        offset + fetched,
+                        remaining.map(|remaining| remaining - fetched),
+                    )
+                });
+                Ok(Some((page, next)))
+            }
+        })
+    }
+
+    /// Stream every record of the collection `page_size` at a time. See [iter](Self::iter).
+    pub fn iter_all(&self, page_size: usize) -> impl Stream<Item = Result<GetResult>> + '_ {
+        self.iter(GetOptions::default(), page_size)
+    }
+
     /// Update the embeddings, metadatas or documents for provided ids.
+    /// An empty batch is not sent: it is a no-op reported as a [Warning::EmptyBatch].
     ///
     /// # Arguments
     ///
@@ -215,30 +905,38 @@ impl ChromaCollection {
     /// * If you provide documents and don't provide an embedding function when embeddings is None
     /// * If you provide an embedding function and don't provide documents
     /// * If you provide both embeddings and embedding_function
+    /// * If a record's metadata does not match the types of the [MetadataSchema] of this handle. Required keys may be left out, since updates merge into the existing metadata.
     ///
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(
+            name = "chroma.collection.update",
+            skip_all,
+            fields(collection_id = %self.id,
+            entry_count = collection_entries.ids.len())
+        )
+    )]
     pub async fn update<'a>(
         &self,
         collection_entries: CollectionEntries<'a>,
         embedding_function: Option<Box<dyn EmbeddingFunction>>,
     ) -> Result<()> {
-        let collection_entries = validate(false, collection_entries, embedding_function).await?;
-
-        let CollectionEntries {
-            ids,
-            embeddings,
-            metadatas,
-            documents,
-        } = collection_entries;
-
-        let json_body = json!({
-            "ids": ids,
-            "embeddings": embeddings,
-            "metadatas": metadatas,
-            "documents": documents,
-        });
+        if self.skip_empty_batch("update", &collection_entries, embedding_function.is_some())? {
+            return Ok(());
+        }
+        self.check_metadata_schema(&collection_entries, true)?;
+        let collection_entries = validate(
+            false,
+            collection_entries,
+            embedding_function,
+            1,
+            self.max_validation_violations,
+        )
+        .await?;
 
         let path = format!("/collections/{}/update", self.id);
-        let response = self.api.post_database(&path, Some(json_body)).await?;
+        let body = WriteBody::new(&collection_entries, self.float_precision);
+        let response = self.api.post_database_json(&path, &body).await?;
 
         match response.error_for_status() {
             Ok(_) => Ok(()),
@@ -246,6 +944,61 @@ impl ChromaCollection {
         }
     }
 
+    /// Update the metadata of existing entries, leaving their embeddings and documents as they are.
+    /// A shorthand for [update](Self::update) with only `ids` and `metadatas` set.
+    ///
+    /// # Errors
+    ///
+    /// * If the length of ids and metadatas don't match
+    /// * If you provide duplicates in ids, empty ids
+    /// * If a record's metadata does not match the types of the [MetadataSchema] of this handle
+    ///
+    pub async fn update_metadata(&self, ids: &[&str], metadatas: Metadatas) -> Result<()> {
+        let collection_entries = CollectionEntries {
+            ids: ids.to_vec(),
+            embeddings: None,
+            metadatas: Some(metadatas),
+            documents: None,
+        };
+        self.update(collection_entries, None).await
+    }
+
+    /// Replace the documents of existing entries, and their embeddings with ones computed from the new documents.
+    /// Their metadata is left as is.
+    ///
+    /// # Arguments
+    ///
+    /// * `ids` - The ids of the entries to update.
+    /// * `documents` - The new documents, in the same order as `ids`.
+    /// * `embedding_function` - The function to use to compute the embeddings. Falls back to the function attached with [with_embedding_function](Self::with_embedding_function). Optional.
+    ///
+    /// # Errors
+    ///
+    /// * If no embedding function is provided or attached
+    /// * If the length of ids and documents don't match
+    /// * If you provide duplicates in ids, empty ids
+    ///
+    pub async fn update_documents(
+        &self,
+        ids: &[&str],
+        documents: &[&str],
+        embedding_function: Option<Box<dyn EmbeddingFunction>>,
+    ) -> Result<()> {
+        let Some(embedding_function) = self.resolve_embedding_function(embedding_function) else {
+            return Err(ChromaError::invalid_argument(
+                "You must provide or attach an embedding function to use update_documents",
+            ));
+        };
+        let collection_entries = CollectionEntries {
+            ids: ids.to_vec(),
+            embeddings: None,
+            metadatas: None,
+            documents: Some(documents.to_vec()),
+        };
+        self.update(collection_entries, Some(embedding_function))
+            .await
+    }
+
     ///Get the n_results nearest neighbor embeddings for provided query_embeddings or query_texts.
     ///
     /// # Arguments
@@ -255,7 +1008,8 @@ impl ChromaCollection {
     /// * `n_results` - The number of neighbors to return for each query_embedding or query_texts. Optional.
     /// * `where_metadata` - Used to filter results by metadata. E.g. {"$and": ["color" : "red", "price": {"$gte": 4.20}]}. Optional.
     /// * `where_document` - Used to filter results by documents. E.g. {$contains: "some text"}. Optional.
-    /// * `include` - A list of what to include in the results. Can contain "embeddings", "metadatas", "documents", "distances". Ids are always included. Defaults to ["metadatas", "documents", "distances"]. Optional.
+    /// * `include` - A list of what to include in the results. Can contain [Include::Embeddings], [Include::Metadatas], [Include::Documents], [Include::Distances]. Ids are always included. Defaults to metadatas, documents and distances. Optional.
+    /// * `exclude_ids` - Ids to leave out of the results, filtered client-side after over-fetching. A [Warning::FewerResults] is reported for each query left with fewer than `n_results` hits. Optional.
     /// * `embedding_function` - The function to use to compute the embeddings. If None, embeddings must be provided. Optional.
     ///
     /// # Errors
@@ -269,29 +1023,230 @@ impl ChromaCollection {
         query_options: QueryOptions<'a>,
         embedding_function: Option<Box<dyn EmbeddingFunction>>,
     ) -> Result<QueryResult> {
-        let QueryOptions {
-            mut query_embeddings,
+        Ok(self
+            .query_inner(query_options, embedding_function, None)
+            .await?
+            .0)
+    }
+
+    /// The same as [query](Self::query), but also returns the [ResponseMeta] of the request, e.g. to log the server's request id.
+    pub async fn query_with_meta<'a>(
+        &self,
+        query_options: QueryOptions<'a>,
+        embedding_function: Option<Box<dyn EmbeddingFunction>>,
+    ) -> Result<(QueryResult, ResponseMeta)> {
+        self.query_inner(query_options, embedding_function, None)
+            .await
+    }
+
+    /// The same as [query](Self::query), but with a timeout for this request only, overriding the client's `request_timeout`.
+    pub async fn query_with_timeout<'a>(
+        &self,
+        query_options: QueryOptions<'a>,
+        embedding_function: Option<Box<dyn EmbeddingFunction>>,
+        timeout: Duration,
+    ) -> Result<QueryResult> {
+        Ok(self
+            .query_inner(query_options, embedding_function, Some(timeout))
+            .await?
+            .0)
+    }
+
+    /// Embed the texts with `embedding_function` and get the `n_results` nearest neighbors of each.
+    /// A shorthand for [query](Self::query) with only `query_texts` and `n_results` set.
+    ///
+    /// # Errors
+    ///
+    /// * If `texts` is empty
+    pub async fn query_texts(
+        &self,
+        texts: &[&str],
+        n_results: usize,
+        embedding_function: Box<dyn EmbeddingFunction>,
+    ) -> Result<QueryResult> {
+        let query_options = QueryOptions {
+            query_texts: Some(texts.to_vec()),
+            n_results: Some(n_results),
+            ..Default::default()
+        };
+        self.query(query_options, Some(embedding_function)).await
+    }
+
+    /// Get the `n_results` nearest neighbors of each embedding.
+    /// A shorthand for [query](Self::query) with only `query_embeddings` and `n_results` set.
+    ///
+    /// # Errors
+    ///
+    /// * If `embeddings` is empty
+    pub async fn query_embeddings(
+        &self,
+        embeddings: Embeddings,
+        n_results: usize,
+    ) -> Result<QueryResult> {
+        let query_options = QueryOptions {
+            query_embeddings: Some(embeddings),
+            n_results: Some(n_results),
+            ..Default::default()
+        };
+        self.query(query_options, None).await
+    }
+
+    /// Get the `n_results` nearest neighbors of many embeddings, in batches of at most `batch_size` queries
+    /// with up to `concurrency` batches in flight, for more queries than fit in a single request.
+    /// The results are reassembled in the order of `embeddings`.
+    ///
+    /// # Arguments
+    ///
+    /// * `embeddings` - The embeddings to get the closest neighbors of.
+    /// * `n_results` - The number of neighbors to return for each embedding.
+    /// * `filter` - Applied to every batch. Optional.
+    /// * `batch_size` - The maximum number of embeddings per request.
+    /// * `concurrency` - How many batches to query at once. 0 or 1 queries the batches sequentially.
+    /// * `allow_partial` - Keep going when a batch fails, leaving its queries without hits and reporting it in [BatchedQueryResult::failed], instead of failing the whole call.
+    ///
+    /// # Errors
+    ///
+    /// * If `batch_size` is 0
+    /// * [ChromaError::ChunkFailed] with the index of the first batch that failed, unless `allow_partial` is set
+    ///
+    pub async fn query_batched(
+        &self,
+        embeddings: Embeddings,
+        n_results: usize,
+        filter: Option<Filter>,
+        batch_size: usize,
+        concurrency: usize,
+        allow_partial: bool,
+    ) -> Result<BatchedQueryResult> {
+        if batch_size == 0 {
+            return Err(ChromaError::invalid_argument(
+                "batch_size must be at least 1",
+            ));
+        }
+        let (where_metadata, where_document) = filter.map(Filter::into_parts).unwrap_or_default();
+        let batches: Vec<Embeddings> = embeddings
+            .chunks(batch_size)
+            .map(<[Embedding]>::to_vec)
+            .collect();
+        let mut results = futures::stream::iter(batches.into_iter().enumerate())
+            .map(|(batch, query_embeddings)| {
+                let query_options = QueryOptions {
+                    query_embeddings: Some(query_embeddings),
+                    n_results: Some(n_results),
+                    where_metadata: where_metadata.clone(),
+                    where_document: where_document.clone(),
+                    ..Default::default()
+                };
+                async move {
+                    let len = query_options.query_embeddings.as_ref().map_or(0, Vec::len);
+                    let result = self.query(query_options, None).await;
+                    (batch, len, result)
+                }
+            })
+            .buffered(concurrency.max(1));
+
+        let mut batched = BatchedQueryResult {
+            result: QueryResult::empty(0),
+            failed: Vec::new(),
+        };
+        while let Some((batch, len, result)) = results.next().await {
+            match result {
+                Ok(result) => batched.result.extend(result),
+                Err(e) => {
+                    let error = ChromaError::ChunkFailed {
+                        chunk: batch,
+                        source: Box::new(e),
+                    };
+                    if !allow_partial {
+                        return Err(error);
+                    }
+                    batched.result.extend(QueryResult::empty(len));
+                    batched.failed.push(error);
+                }
+            }
+        }
+        Ok(batched)
+    }
+
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(
+            name = "chroma.collection.query",
+            skip_all,
+            fields(collection_id = %self.id,
+            query_count = query_options.query_embeddings.as_ref().map_or(0, Vec::len)
+                + query_options.query_texts.as_ref().map_or(0, Vec::len),
+            n_results = query_options.n_results)
+        )
+    )]
+    async fn query_inner(
+        &self,
+        query_options: QueryOptions<'_>,
+        embedding_function: Option<Box<dyn EmbeddingFunction>>,
+        timeout: Option<Duration>,
+    ) -> Result<(QueryResult, ResponseMeta)> {
+        let QueryOptions {
+            mut query_embeddings,
             query_texts,
             n_results,
             where_metadata,
             where_document,
             include,
+            exclude_ids,
+            exclude_overfetch,
+            timeout: query_timeout,
         } = query_options;
+        let timeout = timeout.or(query_timeout);
+        if matches!(&query_embeddings, Some(e) if e.is_empty())
+            || matches!(&query_texts, Some(t) if t.is_empty())
+        {
+            return Err(ChromaError::invalid_argument(
+                "query_embeddings and query_texts cannot be empty",
+            ));
+        }
         if query_embeddings.is_some() && query_texts.is_some() {
-            bail!("You can only provide query_embeddings or query_texts, not both");
+            return Err(ChromaError::invalid_argument(
+                "You can only provide query_embeddings or query_texts, not both",
+            ));
         } else if query_embeddings.is_none() && query_texts.is_none() {
-            bail!("You must provide either query_embeddings or query_texts");
+            return Err(ChromaError::invalid_argument(
+                "You must provide either query_embeddings or query_texts",
+            ));
         } else if query_texts.is_some() && embedding_function.is_none() {
-            bail!("You must provide an embedding function when providing query_texts");
+            return Err(ChromaError::invalid_argument(
+                "You must provide an embedding function when providing query_texts",
+            ));
         } else if query_embeddings.is_none() && embedding_function.is_some() {
+            let embedding_function = embedding_function.unwrap();
+            self.check_embedding_function(embedding_function.as_ref())?;
             query_embeddings = Some(
                 embedding_function
-                    .unwrap()
                     .embed(query_texts.as_ref().unwrap())
-                    .await?,
+                    .await
+                    .map_err(ChromaError::EmbeddingError)?,
             );
         };
 
+        check_filters(&where_metadata, &where_document)?;
+
+        let exclude_ids = exclude_ids.filter(|ids| !ids.is_empty());
+        let requested = n_results.unwrap_or(DEFAULT_N_RESULTS);
+        let n_results = match &exclude_ids {
+            Some(exclude_ids) => {
+                let overfetch = exclude_overfetch.unwrap_or(1.0);
+                if !overfetch.is_finite() || overfetch < 0.0 {
+                    return Err(ChromaError::invalid_argument(format!(
+                        "exclude_overfetch must be a non-negative number, got {overfetch}"
+                    )));
+                }
+                Some(requested + (exclude_ids.len() as f32 * overfetch).ceil() as usize)
+            }
+            None => n_results,
+        };
+
+        let embeddings_requested = include
+            .as_ref()
+            .is_some_and(|include| include.contains(&Include::Embeddings));
         let mut json_body = json!({
             "query_embeddings": query_embeddings,
             "n_results": n_results,
@@ -306,9 +1261,404 @@ impl ChromaCollection {
             .retain(|_, v| !v.is_null());
 
         let path = format!("/collections/{}/query", self.id);
-        let response = self.api.post_database(&path, Some(json_body)).await?;
-        let query_result = response.json::<QueryResult>().await?;
-        Ok(query_result)
+        let start = Instant::now();
+        let response = match timeout {
+            Some(timeout) => {
+                self.api
+                    .post_database_with_timeout(&path, Some(json_body), timeout)
+                    .await?
+            }
+            None => self.api.post_database(&path, Some(json_body)).await?,
+        };
+        let meta = self.api.response_meta(&response, start.elapsed());
+        let mut query_result = read_json::<QueryResult>(response).await?;
+        let latency = start.elapsed();
+        if embeddings_requested {
+            let total = query_result.ids.iter().map(Vec::len).sum();
+            self.check_embeddings_returned(query_result.missing_embeddings(), total)?;
+        }
+        if let Some(exclude_ids) = exclude_ids {
+            let excluded: HashSet<&str> = exclude_ids.iter().map(String::as_str).collect();
+            query_result.exclude(&excluded, requested);
+            for ids in &query_result.ids {
+                if ids.len() < requested {
+                    self.api.warn(Warning::FewerResults {
+                        requested,
+                        returned: ids.len(),
+                    });
+                }
+            }
+        }
+        if let Some(query_logger) = &self.query_logger {
+            query_logger.on_query(&QueryTrace {
+                collection: self.name.clone(),
+                query_texts: query_texts
+                    .map(|texts| texts.iter().map(|text| text.to_string()).collect()),
+                query_embeddings: query_embeddings.filter(|_| query_logger.include_embeddings()),
+                n_results: requested,
+                where_metadata,
+                where_document,
+                ids: query_result.ids.clone(),
+                distances: query_result.distances.clone(),
+                latency,
+            });
+        }
+        Ok((query_result, meta))
+    }
+
+    /// Embed `text` and return the `k` most similar entries, with their documents, metadata and distances.
+    ///
+    /// # Arguments
+    ///
+    /// * `text` - The text to search for.
+    /// * `k` - The number of hits to return.
+    /// * `filter` - Used to filter results by metadata. E.g. {"color": "red"}. `$and`-merged with the [exclusion filters](Self::with_exclusion_filter) of this handle. Optional.
+    /// * `embedding_function` - The function to embed `text` with. Falls back to the function attached with [with_embedding_function](Self::with_embedding_function). Optional.
+    ///
+    /// # Errors
+    ///
+    /// * If no embedding function is provided or attached
+    ///
+    pub async fn similarity_search(
+        &self,
+        text: &str,
+        k: usize,
+        filter: Option<Value>,
+        embedding_function: Option<Arc<dyn EmbeddingFunction>>,
+    ) -> Result<QueryHits> {
+        let embedding_function = self.resolve_embedding_function(
+            embedding_function.map(|ef| Box::new(ef) as Box<dyn EmbeddingFunction>),
+        );
+        let Some(embedding_function) = embedding_function else {
+            return Err(ChromaError::invalid_argument(
+                "You must provide or attach an embedding function to use similarity_search",
+            ));
+        };
+        let filters: Vec<Value> = self
+            .exclusion_filters
+            .iter()
+            .cloned()
+            .chain(filter)
+            .collect();
+        let filter = (!filters.is_empty()).then(|| combine(AND, filters.into_iter()));
+        let query = QueryOptions {
+            query_texts: Some(vec![text]),
+            query_embeddings: None,
+            where_metadata: filter,
+            where_document: None,
+            n_results: Some(k),
+            include: Some(vec![
+                Include::Documents,
+                Include::Metadatas,
+                Include::Distances,
+            ]),
+            ..Default::default()
+        };
+        let query_result = self.query(query, Some(embedding_function)).await?;
+        let hits = query_result
+            .into_hits()
+            .into_iter()
+            .next()
+            .unwrap_or_default();
+        Ok(self.query_hits(hits, k))
+    }
+
+    /// Query with several weighted embeddings and merge the results into a single ranked list.
+    /// Each hit's distance is the weighted sum of its distances to every query embedding.
+    ///
+    /// # Arguments
+    ///
+    /// * `queries` - The query embeddings, each paired with its weight.
+    /// * `n_results` - The number of hits to return. Also the number of neighbors fetched for each query embedding.
+    /// * `where_metadata` - Used to filter results by metadata. E.g. {"color": "red"}. Optional.
+    /// * `missing_distance` - The distance used for a query embedding that did not return a hit. Defaults to the largest distance returned for that query embedding. Optional.
+    ///
+    /// # Errors
+    ///
+    /// * If `queries` is empty
+    ///
+    pub async fn query_weighted(
+        &self,
+        queries: Vec<(Embedding, f32)>,
+        n_results: usize,
+        where_metadata: Option<Value>,
+        missing_distance: Option<f32>,
+    ) -> Result<QueryHits> {
+        if queries.is_empty() {
+            return Err(ChromaError::invalid_argument(
+                "You must provide at least one weighted query embedding",
+            ));
+        }
+        let (query_embeddings, weights): (Embeddings, Vec<f32>) = queries.into_iter().unzip();
+        let query = QueryOptions {
+            query_texts: None,
+            query_embeddings: Some(query_embeddings),
+            where_metadata,
+            where_document: None,
+            n_results: Some(n_results),
+            include: Some(vec![
+                Include::Documents,
+                Include::Metadatas,
+                Include::Distances,
+            ]),
+            ..Default::default()
+        };
+        let query_result = self.query(query, None).await?;
+        let hits = combine_weighted_hits(
+            query_result.into_hits(),
+            &weights,
+            missing_distance,
+            n_results,
+        );
+        Ok(self.query_hits(hits, n_results))
+    }
+
+    /// Find the entries most similar to an existing entry, using its stored embedding.
+    /// The seed entry itself is excluded from the hits.
+    ///
+    /// # Arguments
+    ///
+    /// * `id` - The id of the entry to search around.
+    /// * `n_results` - The number of hits to return.
+    /// * `where_metadata` - Used to filter results by metadata. E.g. {"color": "red"}. Optional.
+    ///
+    /// # Errors
+    ///
+    /// * If no entry exists with the given id
+    /// * If the entry was stored without an embedding
+    ///
+    pub async fn query_similar_to(
+        &self,
+        id: &str,
+        n_results: usize,
+        where_metadata: Option<Value>,
+    ) -> Result<QueryHits> {
+        let get_query = GetOptions {
+            ids: vec![id.to_string()],
+            include: Some(vec![Include::Embeddings]),
+            ..Default::default()
+        };
+        let get_result = self.get(get_query).await?;
+        if get_result.ids.is_empty() {
+            return Err(ChromaError::invalid_argument(format!(
+                "No entry found with id {id:?}"
+            )));
+        }
+        let Some(embedding) = get_result
+            .embeddings
+            .and_then(|embeddings| embeddings.into_iter().next().flatten())
+        else {
+            return Err(ChromaError::invalid_argument(format!(
+                "The entry with id {id:?} has no stored embedding"
+            )));
+        };
+
+        // Chroma cannot exclude an id server-side, so overfetch by one to make room for the seed.
+        let query = QueryOptions {
+            query_texts: None,
+            query_embeddings: Some(vec![embedding]),
+            where_metadata,
+            where_document: None,
+            n_results: Some(n_results + 1),
+            include: Some(vec![
+                Include::Documents,
+                Include::Metadatas,
+                Include::Distances,
+            ]),
+            ..Default::default()
+        };
+        let query_result = self.query(query, None).await?;
+        let hits = query_result
+            .into_hits()
+            .into_iter()
+            .next()
+            .unwrap_or_default();
+        let hits = exclude_hits(hits, &[id], n_results);
+        Ok(self.query_hits(hits, n_results))
+    }
+
+    /// Check the records' metadata against the schema attached to this handle, if any.
+    /// With `partial`, as for updates, records may leave out required keys.
+    fn check_metadata_schema(
+        &self,
+        collection_entries: &CollectionEntries<'_>,
+        partial: bool,
+    ) -> Result<()> {
+        let Some(schema) = &self.metadata_schema else {
+            return Ok(());
+        };
+        // Updates without metadata leave it unchanged.
+        if partial && collection_entries.metadatas.is_none() {
+            return Ok(());
+        }
+        let mut error = ValidationError::new(self.max_validation_violations);
+        for (index, id) in collection_entries.ids.iter().enumerate() {
+            let metadata = collection_entries
+                .metadatas
+                .as_ref()
+                .and_then(|metadatas| metadatas.get(index));
+            for violation in schema.violations(metadata, partial) {
+                error.push(
+                    self.max_validation_violations,
+                    Some(index),
+                    Some(id),
+                    ValidationRule::MetadataSchema,
+                    violation,
+                );
+            }
+        }
+        Ok(error.into_result()?)
+    }
+
+    /// Whether to skip a write because it has no ids, reporting a [Warning::EmptyBatch].
+    /// The other fields must still be consistent with the empty ids.
+    fn skip_empty_batch(
+        &self,
+        operation: &'static str,
+        collection_entries: &CollectionEntries<'_>,
+        has_embedding_function: bool,
+    ) -> Result<bool> {
+        if !collection_entries.ids.is_empty() {
+            return Ok(false);
+        }
+        check_entries(
+            false,
+            collection_entries,
+            has_embedding_function,
+            self.max_validation_violations,
+        )?;
+        self.api.warn(Warning::EmptyBatch { operation });
+        Ok(true)
+    }
+
+    /// Attach a [Warning::FewerResults] to the hits if there are fewer than requested.
+    fn query_hits(&self, hits: Vec<Hit>, requested: usize) -> QueryHits {
+        let mut warnings = Warnings::default();
+        if hits.len() < requested {
+            warnings.push(
+                Warning::FewerResults {
+                    requested,
+                    returned: hits.len(),
+                },
+                self.api.warning_callback(),
+            );
+        }
+        QueryHits { hits, warnings }
+    }
+
+    /// Page through the nearest neighbors of a single query, `page_size` hits at a time.
+    ///
+    /// Chroma's query has no offset, so the returned [QueryPager] over-fetches, doubling `n_results` each time a page
+    /// runs past the hits fetched so far, and serves pages from the ranked list it holds. `n_results` in the options is ignored.
+    ///
+    /// Every re-fetch runs the query again, so later pages reflect a newer snapshot of the collection than earlier ones:
+    /// entries added or removed in between can shift the ranking, and a hit may be repeated or skipped at a page boundary.
+    ///
+    /// # Errors
+    ///
+    /// * If the options do not hold exactly one query embedding or query text
+    /// * If query_texts are provided and no embedding function is attached
+    /// * If `page_size` is 0
+    ///
+    pub async fn query_paged<'c>(
+        &'c self,
+        query_options: QueryOptions<'_>,
+        page_size: usize,
+    ) -> Result<QueryPager<'c>> {
+        let QueryOptions {
+            query_embeddings,
+            query_texts,
+            n_results: _,
+            where_metadata,
+            where_document,
+            include,
+            exclude_ids,
+            exclude_overfetch,
+            timeout,
+        } = query_options;
+        if page_size == 0 {
+            return Err(ChromaError::invalid_argument(
+                "page_size must be at least 1",
+            ));
+        }
+        let query_embeddings = match (query_embeddings, query_texts) {
+            (Some(query_embeddings), None) => query_embeddings,
+            (None, Some(query_texts)) => {
+                let Some(embedding_function) = self.resolve_embedding_function(None) else {
+                    return Err(ChromaError::invalid_argument(
+                        "You must attach an embedding function to page through query_texts",
+                    ));
+                };
+                self.check_embedding_function(embedding_function.as_ref())?;
+                embedding_function
+                    .embed(&query_texts)
+                    .await
+                    .map_err(ChromaError::EmbeddingError)?
+            }
+            _ => {
+                return Err(ChromaError::invalid_argument(
+                    "You must provide either query_embeddings or query_texts",
+                ))
+            }
+        };
+        let [query_embedding] = <[Embedding; 1]>::try_from(query_embeddings).map_err(|_| {
+            ChromaError::invalid_argument("query_paged supports exactly one query at a time")
+        })?;
+        Ok(QueryPager {
+            collection: self,
+            query_embedding,
+            where_metadata,
+            where_document,
+            include: include.unwrap_or_else(|| {
+                vec![Include::Documents, Include::Metadatas, Include::Distances]
+            }),
+            exclude_ids,
+            exclude_overfetch,
+            timeout,
+            page_size,
+            hits: vec![],
+            fetched: 0,
+            position: 0,
+            exhausted: false,
+        })
+    }
+
+    /// Search the collection with the search endpoint of Chroma 1.x servers, ranking by distance to an embedding,
+    /// with fields of the endpoint that are not modeled yet set through [SearchRequest::raw].
+    ///
+    /// # Errors
+    ///
+    /// * [ChromaError::UnsupportedServer] if the server does not serve the search endpoint
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(
+            name = "chroma.collection.search",
+            skip_all,
+            fields(collection_id = %self.id, limit = search_request.limit)
+        )
+    )]
+    pub async fn search(&self, search_request: SearchRequest) -> Result<SearchResult> {
+        let unsupported = || ChromaError::UnsupportedServer {
+            feature: "search".to_string(),
+            version: self.api.server_version().cloned(),
+        };
+        if matches!(self.api.server_version(), Some(version) if !version.at_least(1, 0, 0)) {
+            return Err(unsupported());
+        }
+        let path = format!("/collections/{}/search", self.id);
+        let response = match self
+            .api
+            .post_database(&path, Some(search_request.to_json()))
+            .await
+        {
+            Ok(response) => response,
+            // Servers without the endpoint answer as for any unknown route, not with a missing collection.
+            Err(ChromaError::ServerError {
+                status: 404 | 405, ..
+            }) => return Err(unsupported()),
+            Err(e) => return Err(e),
+        };
+        Ok(read_json::<SearchResponse>(response).await?.into_result())
     }
 
     ///Get the first entries in the collection up to the limit
@@ -325,24 +1675,116 @@ impl ChromaCollection {
             offset: None,
             where_document: None,
             include: None,
+            sort: None,
         };
         self.get(get_query).await
     }
 
+    /// Get a random sample of up to `n` distinct records, matching the filter if any, e.g. for labeling or evaluation.
+    ///
+    /// Unlike [peek](Self::peek), this does not download the whole collection: the records are fetched one at a time at
+    /// random offsets below [count](Self::count), so it sends about `n` requests. Pass a `seed` to get the same sample
+    /// again, as long as the collection does not change.
+    ///
+    /// Without a filter, the sample is uniform. Chroma can only count whole collections, so with a filter the offsets
+    /// are drawn below the collection's count and lowered whenever one lands past the matching records. The sample is
+    /// still uniform over the matching records, but when few records match, many offsets miss and it takes more requests.
+    /// Records added or deleted while sampling shift the offsets, which can skip or repeat records; repeats are dropped.
+    ///
+    /// # Arguments
+    ///
+    /// * `n` - The number of records to return. Fewer are returned if fewer records match.
+    /// * `filter` - The metadata and document filters the records must match. Optional.
+    /// * `seed` - Seeds the choice of records, for reproducible samples. Optional.
+    ///
+    pub async fn sample(
+        &self,
+        n: usize,
+        filter: Option<Filter>,
+        seed: Option<u64>,
+    ) -> Result<GetResult> {
+        let (where_metadata, where_document) = filter.map(Filter::into_parts).unwrap_or_default();
+        let mut rng =
+            SplitMix64::new(seed.unwrap_or_else(|| uuid::Uuid::new_v4().as_u128() as u64));
+        let mut sample = GetResult::default();
+        let mut sampled_ids = HashSet::new();
+        let mut drawn = HashSet::new();
+        // The offsets at and past `bound` are known to miss.
+        let mut bound = self.count().await?;
+        while sample.ids.len() < n {
+            let drawn_below_bound = drawn.iter().filter(|&&offset| offset < bound).count();
+            let wanted = (n - sample.ids.len()).min(bound - drawn_below_bound);
+            if wanted == 0 {
+                break;
+            }
+            let mut offsets = Vec::with_capacity(wanted);
+            while offsets.len() < wanted {
+                let offset = rng.below(bound);
+                if drawn.insert(offset) {
+                    offsets.push(offset);
+                }
+            }
+            let results: Vec<(usize, GetResult)> = futures::stream::iter(offsets)
+                .map(|offset| {
+                    let get_options = GetOptions {
+                        where_metadata: where_metadata.clone(),
+                        where_document: where_document.clone(),
+                        limit: Some(1),
+                        offset: Some(offset),
+                        ..Default::default()
+                    };
+                    async move { Ok::<_, ChromaError>((offset, self.get(get_options).await?)) }
+                })
+                .buffered(SAMPLE_CONCURRENCY)
+                .collect::<Vec<_>>()
+                .await
+                .into_iter()
+                .collect::<Result<_>>()?;
+            for (offset, result) in results {
+                match result.ids.first() {
+                    None => bound = bound.min(offset),
+                    Some(id) if offset < bound && sampled_ids.insert(id.clone()) => {
+                        sample.extend(result)
+                    }
+                    Some(_) => {}
+                }
+            }
+        }
+        Ok(sample)
+    }
+
     /// Delete the embeddings based on ids and/or a where filter. Deletes all the entries if None are provided
     ///
+    /// An empty list of ids deletes nothing: no request is sent and a [Warning::EmptyBatch] is reported.
+    ///
     /// # Arguments
     ///
     /// * `ids` - The ids of the embeddings to delete. Optional
     /// * `where_metadata` -  Used to filter deletion by metadata. E.g. {"$and": ["color" : "red", "price": {"$gte": 4.20}]}. Optional.
     /// * `where_document` - Used to filter the deletion by the document content. E.g. {$contains: "some text"}. Optional.. Optional.
     ///
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(
+            name = "chroma.collection.delete",
+            skip_all,
+            fields(collection_id = %self.id,
+            id_count = ids.as_ref().map(Vec::len))
+        )
+    )]
     pub async fn delete(
         &self,
         ids: Option<Vec<&str>>,
         where_metadata: Option<Value>,
         where_document: Option<Value>,
     ) -> Result<()> {
+        check_filters(&where_metadata, &where_document)?;
+        if matches!(&ids, Some(ids) if ids.is_empty()) {
+            self.api.warn(Warning::EmptyBatch {
+                operation: "delete",
+            });
+            return Ok(());
+        }
         let json_body = json!({
             "ids": ids,
             "where": where_metadata,
@@ -357,166 +1799,3488 @@ impl ChromaCollection {
             Err(e) => Err(e.into()),
         }
     }
-}
 
-#[derive(Deserialize, Debug)]
-pub struct GetResult {
-    pub ids: Vec<String>,
-    pub metadatas: Option<Vec<Option<Vec<Option<Metadata>>>>>,
-    pub documents: Option<Vec<Option<String>>>,
-    pub embeddings: Option<Vec<Option<Embedding>>>,
-}
+    /// Delete every entry of the collection, keeping the collection itself. Same as `delete(None, None, None)`.
+    ///
+    /// Returns the ids of the entries the collection held just before the delete.
+    pub async fn delete_all(&self) -> Result<Vec<String>> {
+        self.delete_matching(None, None).await
+    }
 
-#[derive(Serialize, Debug, Default)]
-pub struct GetOptions {
-    pub ids: Vec<String>,
-    pub where_metadata: Option<Value>,
-    pub limit: Option<usize>,
-    pub offset: Option<usize>,
-    pub where_document: Option<Value>,
-    pub include: Option<Vec<String>>,
+    /// Delete the entries whose metadata matches the filter, e.g. a [WhereFilter]. Same as `delete(None, Some(filter), None)`.
+    ///
+    /// Returns the ids of the entries that matched just before the delete.
+    ///
+    /// # Errors
+    ///
+    /// * If the filter is an empty object, use [delete_all](Self::delete_all) instead
+    pub async fn delete_by_metadata(&self, filter: Value) -> Result<Vec<String>> {
+        self.delete_matching(Some(filter), None).await
+    }
+
+    /// Delete the entries whose document matches the filter, e.g. `{"$contains": "some text"}`. Same as `delete(None, None, Some(filter))`.
+    ///
+    /// Returns the ids of the entries that matched just before the delete.
+    ///
+    /// # Errors
+    ///
+    /// * If the filter is an empty object, use [delete_all](Self::delete_all) instead
+    pub async fn delete_by_document(&self, filter: Value) -> Result<Vec<String>> {
+        self.delete_matching(None, Some(filter)).await
+    }
+
+    /// Fetch the ids of the matching entries, then send a single delete with the same filters.
+    async fn delete_matching(
+        &self,
+        where_metadata: Option<Value>,
+        where_document: Option<Value>,
+    ) -> Result<Vec<String>> {
+        check_filters(&where_metadata, &where_document)?;
+        let matching = self
+            .get(GetOptions {
+                where_metadata: where_metadata.clone(),
+                where_document: where_document.clone(),
+                include: Some(vec![]),
+                ..Default::default()
+            })
+            .await?;
+        self.delete(None, where_metadata, where_document).await?;
+        Ok(matching.ids)
+    }
 }
 
-#[derive(Serialize, Debug, Default)]
-pub struct QueryOptions<'a> {
-    pub query_embeddings: Option<Embeddings>,
-    pub query_texts: Option<Vec<&'a str>>,
-    pub n_results: Option<usize>,
-    pub where_metadata: Option<Value>,
-    pub where_document: Option<Value>,
-    pub include: Option<Vec<&'a str>>,
+#[cfg(test)]
+impl ChromaCollection {
+    /// A handle on a collection with the id and name "mock", sending its requests through `api`, for tests against a mock server.
+    pub(crate) fn mock(api: APIClientAsync) -> Self {
+        ChromaCollection {
+            api: Arc::new(api),
+            embedding_function: None,
+            id: "mock".to_string(),
+            metadata: None,
+            name: "mock".to_string(),
+            configuration_json: None,
+            dimension: None,
+            max_validation_violations: 100,
+            float_precision: None,
+            metadata_schema: None,
+            strict_embedding_function: false,
+            strict_embeddings: false,
+            query_logger: None,
+            exclusion_filters: Vec::new(),
+        }
+    }
 }
 
-#[derive(Deserialize, Debug)]
-pub struct QueryResult {
-    pub ids: Vec<Vec<String>>,
-    pub metadatas: Option<Vec<Vec<Option<Metadata>>>>,
-    pub documents: Option<Vec<Vec<String>>>,
-    pub embeddings: Option<Vec<Vec<Embedding>>>,
-    pub distances: Option<Vec<Vec<f32>>>,
+/// The index configuration of a collection, set when creating it with [ChromaClient::create_collection](crate::ChromaClient::create_collection).
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq)]
+pub struct CollectionConfiguration {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub hnsw: Option<HnswConfiguration>,
 }
 
-#[derive(Serialize, Debug, Default)]
-pub struct CollectionEntries<'a> {
-    pub ids: Vec<&'a str>,
-    pub metadatas: Option<Metadatas>,
-    pub documents: Option<Documents<'a>>,
-    pub embeddings: Option<Embeddings>,
+/// The parameters of the HNSW index. Those left unset take the server's defaults.
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq)]
+pub struct HnswConfiguration {
+    /// The distance function, `l2`, `ip` or `cosine`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub space: Option<String>,
+    /// The size of the candidate list while building the index. Higher is more accurate and slower.
+    #[serde(alias = "construction_ef", skip_serializing_if = "Option::is_none")]
+    pub ef_construction: Option<usize>,
+    /// The size of the candidate list while searching. Higher is more accurate and slower.
+    #[serde(alias = "search_ef", skip_serializing_if = "Option::is_none")]
+    pub ef_search: Option<usize>,
+    /// The number of neighbors of each node in the graph, known as `M`.
+    #[serde(alias = "M", skip_serializing_if = "Option::is_none")]
+    pub max_neighbors: Option<usize>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub num_threads: Option<usize>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub resize_factor: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub batch_size: Option<usize>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub sync_threshold: Option<usize>,
 }
 
-async fn validate(
-    require_embeddings_or_documents: bool,
-    collection_entries: CollectionEntries<'_>,
-    embedding_function: Option<Box<dyn EmbeddingFunction>>,
-) -> Result<CollectionEntries<'_>> {
-    let CollectionEntries {
-        ids,
-        mut embeddings,
-        metadatas,
-        documents,
-    } = collection_entries;
-    if require_embeddings_or_documents && embeddings.is_none() && documents.is_none() {
-        bail!("Embeddings and documents cannot both be None",);
-    }
+/// The metadata key holding the [DistanceFunction] of a collection.
+pub const DISTANCE_FUNCTION_KEY: &str = "hnsw:space";
 
-    if embeddings.is_none() && documents.is_some() && embedding_function.is_none() {
-        bail!(
-            "embedding_function cannot be None if documents are provided and embeddings are None",
-        );
-    }
+/// The distance function of a collection's HNSW index, set when creating it with
+/// [ChromaClient::create_collection_with_config](crate::ChromaClient::create_collection_with_config).
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DistanceFunction {
+    /// Squared Euclidean distance, `l2`. The server's default.
+    #[serde(rename = "l2")]
+    L2,
+    /// One minus the inner product, `ip`.
+    #[serde(rename = "ip")]
+    InnerProduct,
+    /// One minus the cosine similarity, `cosine`.
+    #[serde(rename = "cosine")]
+    Cosine,
+}
 
-    if embeddings.is_some() && embedding_function.is_some() {
-        bail!("embedding_function should be None if embeddings are provided",);
+impl DistanceFunction {
+    /// The name of the distance function in the `hnsw:space` metadata key and the HNSW configuration.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::L2 => "l2",
+            Self::InnerProduct => "ip",
+            Self::Cosine => "cosine",
+        }
     }
+}
 
-    if embeddings.is_none() && documents.is_some() && embedding_function.is_some() {
-        embeddings = Some(
-            embedding_function
-                .unwrap()
-                .embed(documents.as_ref().unwrap())
-                .await?,
-        );
-    }
+impl std::str::FromStr for DistanceFunction {
+    type Err = ChromaError;
 
-    for id in &ids {
-        if id.is_empty() {
-            bail!("Found empty string in IDs");
+    fn from_str(space: &str) -> Result<Self> {
+        match space {
+            "l2" => Ok(Self::L2),
+            "ip" => Ok(Self::InnerProduct),
+            "cosine" => Ok(Self::Cosine),
+            _ => Err(ChromaError::invalid_argument(format!(
+                "Unknown distance function {space:?}, expected l2, ip or cosine"
+            ))),
         }
     }
+}
 
-    if (embeddings.is_some() && embeddings.as_ref().unwrap().len() != ids.len())
-        || (metadatas.is_some() && metadatas.as_ref().unwrap().len() != ids.len())
-        || (documents.is_some() && documents.as_ref().unwrap().len() != ids.len())
-    {
-        bail!("IDs, embeddings, metadatas, and documents must all be the same length",);
+/// Parse the configuration reported by the server: its `hnsw` section, the `hnsw_configuration`
+/// section of older servers, or flat `hnsw:*` keys like `hnsw:space` and `hnsw:M`.
+impl TryFrom<ConfigurationJson> for CollectionConfiguration {
+    type Error = ChromaError;
+
+    fn try_from(configuration_json: ConfigurationJson) -> Result<Self> {
+        let section = ["hnsw", "hnsw_configuration"]
+            .into_iter()
+            .find_map(|key| configuration_json.get(key).cloned());
+        let hnsw = match section {
+            Some(Value::Null) => None,
+            Some(section) => Some(section),
+            None => {
+                let flat: ConfigurationJson = configuration_json
+                    .iter()
+                    .filter_map(|(key, value)| {
+                        Some((key.strip_prefix("hnsw:")?.to_string(), value.clone()))
+                    })
+                    .collect();
+                (!flat.is_empty()).then_some(Value::Object(flat))
+            }
+        };
+        let hnsw = hnsw.map(serde_json::from_value).transpose().map_err(|e| {
+            ChromaError::invalid_argument(format!("Invalid HNSW configuration: {e}"))
+        })?;
+        Ok(Self { hnsw })
+    }
+}
+
+#[derive(Deserialize, Debug, Default)]
+pub struct GetResult {
+    pub ids: Vec<String>,
+    /// Only populated when [Include::Metadatas] is requested.
+    pub metadatas: Option<Vec<Option<Metadata>>>,
+    /// Only populated when [Include::Documents] is requested.
+    pub documents: Option<Vec<Option<String>>>,
+    /// Only populated when [Include::Embeddings] is requested.
+    pub embeddings: Option<Vec<Option<Embedding>>>,
+    /// The number of records matching the request, ignoring `limit` and `offset`.
+    /// Set by [get_with_count](ChromaCollection::get_with_count), or by servers that report it.
+    #[serde(default)]
+    pub total: Option<usize>,
+}
+
+impl GetResult {
+    /// The number of records returned without an embedding.
+    fn missing_embeddings(&self) -> usize {
+        match &self.embeddings {
+            Some(embeddings) => {
+                let returned = embeddings.iter().flatten().count();
+                self.ids.len().saturating_sub(returned)
+            }
+            None => self.ids.len(),
+        }
+    }
+
+    /// Sort the records in place, keeping ids, metadatas, documents and embeddings in sync.
+    pub fn sort(&mut self, sort_by: &SortBy) {
+        let mut order: Vec<usize> = (0..self.ids.len()).collect();
+        match sort_by {
+            SortBy::Id => order.sort_by(|&a, &b| self.ids[a].cmp(&self.ids[b])),
+            SortBy::Metadata(key) => {
+                let value = |i: usize| {
+                    self.metadatas
+                        .as_ref()
+                        .and_then(|metadatas| metadatas.get(i))
+                        .and_then(Option::as_ref)
+                        .and_then(|metadata| metadata.get(key))
+                };
+                order.sort_by(|&a, &b| {
+                    compare_metadata_values(value(a), value(b))
+                        .then_with(|| self.ids[a].cmp(&self.ids[b]))
+                })
+            }
+        }
+
+        fn permute<T>(items: &mut Vec<T>, order: &[usize]) {
+            let mut items_by_index: Vec<Option<T>> = items.drain(..).map(Some).collect();
+            items.extend(order.iter().filter_map(|&i| items_by_index[i].take()));
+        }
+        permute(&mut self.ids, &order);
+        if let Some(metadatas) = &mut self.metadatas {
+            permute(metadatas, &order);
+        }
+        if let Some(documents) = &mut self.documents {
+            permute(documents, &order);
+        }
+        if let Some(embeddings) = &mut self.embeddings {
+            permute(embeddings, &order);
+        }
+    }
+
+    /// Append the records of another result.
+    fn extend(&mut self, other: GetResult) {
+        fn extend_optional<T>(items: &mut Option<Vec<T>>, other: Option<Vec<T>>) {
+            match (items.as_mut(), other) {
+                (Some(items), Some(other)) => items.extend(other),
+                (None, Some(other)) => *items = Some(other),
+                (_, None) => {}
+            }
+        }
+        self.ids.extend(other.ids);
+        extend_optional(&mut self.metadatas, other.metadatas);
+        extend_optional(&mut self.documents, other.documents);
+        extend_optional(&mut self.embeddings, other.embeddings);
+    }
+}
+
+/// A client-side order for the records of a [GetResult]. Ties are broken by id, ascending.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SortBy {
+    /// By id, ascending.
+    Id,
+    /// By the value of a metadata key, ascending. Numbers sort before strings,
+    /// and records without the key, or with a value of another type, sort last.
+    Metadata(String),
+}
+
+fn compare_metadata_values(a: Option<&Value>, b: Option<&Value>) -> std::cmp::Ordering {
+    fn rank(value: Option<&Value>) -> u8 {
+        match value {
+            Some(Value::Number(_)) => 0,
+            Some(Value::String(_)) => 1,
+            _ => 2,
+        }
+    }
+    // Integers are compared exactly, large ones are not all representable as f64.
+    fn as_integer(n: &serde_json::Number) -> Option<i128> {
+        n.as_i64()
+            .map(i128::from)
+            .or_else(|| n.as_u64().map(i128::from))
+    }
+    match (a, b) {
+        (Some(Value::Number(a)), Some(Value::Number(b))) => match (as_integer(a), as_integer(b)) {
+            (Some(a), Some(b)) => a.cmp(&b),
+            _ => a
+                .as_f64()
+                .unwrap_or(f64::NAN)
+                .total_cmp(&b.as_f64().unwrap_or(f64::NAN)),
+        },
+        (Some(Value::String(a)), Some(Value::String(b))) => a.cmp(b),
+        _ => rank(a).cmp(&rank(b)),
+    }
+}
+
+/// What to include in the results of [get](ChromaCollection::get) and [query](ChromaCollection::query), besides the ids.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[serde(rename_all = "lowercase")]
+pub enum Include {
+    Documents,
+    Embeddings,
+    Metadatas,
+    /// Only valid for queries.
+    Distances,
+    Uris,
+}
+
+#[derive(Serialize, Debug, Default, Clone)]
+pub struct GetOptions {
+    pub ids: Vec<String>,
+    pub where_metadata: Option<Value>,
+    pub limit: Option<usize>,
+    pub offset: Option<usize>,
+    pub where_document: Option<Value>,
+    pub include: Option<Vec<Include>>,
+    #[serde(skip)]
+    pub sort: Option<SortBy>,
+}
+
+impl GetOptions {
+    /// Start building [GetOptions], only setting the fields you need.
+    pub fn builder() -> GetOptionsBuilder {
+        GetOptionsBuilder::default()
+    }
+}
+
+/// A builder for [GetOptions]. See [GetOptions::builder].
+#[derive(Debug, Default)]
+pub struct GetOptionsBuilder {
+    options: GetOptions,
+}
+
+impl GetOptionsBuilder {
+    pub fn ids(mut self, ids: Vec<String>) -> Self {
+        self.options.ids = ids;
+        self
+    }
+
+    /// Filter by metadata, e.g. with a [WhereFilter].
+    pub fn where_metadata(mut self, where_metadata: impl Into<Value>) -> Self {
+        self.options.where_metadata = Some(where_metadata.into());
+        self
+    }
+
+    pub fn limit(mut self, limit: usize) -> Self {
+        self.options.limit = Some(limit);
+        self
+    }
+
+    pub fn offset(mut self, offset: usize) -> Self {
+        self.options.offset = Some(offset);
+        self
+    }
+
+    /// Filter by document contents, e.g. with a [WhereDocumentFilter].
+    pub fn where_document(mut self, where_document: impl Into<Value>) -> Self {
+        self.options.where_document = Some(where_document.into());
+        self
+    }
+
+    pub fn include(mut self, include: Vec<Include>) -> Self {
+        self.options.include = Some(include);
+        self
+    }
+
+    pub fn sort(mut self, sort: SortBy) -> Self {
+        self.options.sort = Some(sort);
+        self
+    }
+
+    /// Set both the metadata and document filters, see [Filter].
+    pub fn filter(mut self, filter: Filter) -> Self {
+        (self.options.where_metadata, self.options.where_document) = filter.into_parts();
+        self
+    }
+
+    /// Build the [GetOptions]. Every field is optional, so this cannot fail.
+    pub fn build(self) -> GetOptions {
+        self.options
+    }
+}
+
+/// A metadata filter, for the `where_metadata` of [GetOptions] and [QueryOptions].
+///
+/// ```
+/// use chromadb::collection::WhereFilter;
+/// use serde_json::json;
+///
+/// let filter = WhereFilter::and([
+///     WhereFilter::field("price").gte(10.0),
+///     WhereFilter::field("color").eq("red"),
+/// ]);
+/// assert_eq!(
+///     filter.into_value(),
+///     json!({"$and": [{"price": {"$gte": 10.0}}, {"color": {"$eq": "red"}}]})
+/// );
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub struct WhereFilter(Value);
+
+impl WhereFilter {
+    /// Start a condition on a metadata key.
+    pub fn field(key: impl Into<String>) -> WhereField {
+        WhereField { key: key.into() }
+    }
+
+    /// Match the entries matching all the filters. A single filter is used as is.
+    pub fn and(filters: impl IntoIterator<Item = WhereFilter>) -> Self {
+        Self(combine(AND, filters.into_iter().map(|filter| filter.0)))
+    }
+
+    /// Match the entries matching any of the filters. A single filter is used as is.
+    pub fn or(filters: impl IntoIterator<Item = WhereFilter>) -> Self {
+        Self(combine(OR, filters.into_iter().map(|filter| filter.0)))
+    }
+
+    pub fn into_value(self) -> Value {
+        self.0
+    }
+}
+
+impl From<WhereFilter> for Value {
+    fn from(filter: WhereFilter) -> Self {
+        filter.0
+    }
+}
+
+/// A metadata key to put a condition on. See [WhereFilter::field].
+#[derive(Debug, Clone, PartialEq)]
+pub struct WhereField {
+    key: String,
+}
+
+impl WhereField {
+    fn condition(self, operator: &str, value: Value) -> WhereFilter {
+        WhereFilter(json!({ self.key: { operator: value } }))
+    }
+
+    pub fn eq(self, value: impl Into<Value>) -> WhereFilter {
+        self.condition(EQ, value.into())
+    }
+
+    pub fn ne(self, value: impl Into<Value>) -> WhereFilter {
+        self.condition(NE, value.into())
+    }
+
+    pub fn gt(self, value: impl Into<Value>) -> WhereFilter {
+        self.condition(GT, value.into())
+    }
+
+    pub fn gte(self, value: impl Into<Value>) -> WhereFilter {
+        self.condition(GTE, value.into())
+    }
+
+    pub fn lt(self, value: impl Into<Value>) -> WhereFilter {
+        self.condition(LT, value.into())
+    }
+
+    pub fn lte(self, value: impl Into<Value>) -> WhereFilter {
+        self.condition(LTE, value.into())
+    }
+
+    /// Match the entries whose value is one of `values`.
+    pub fn in_<V: Into<Value>>(self, values: impl IntoIterator<Item = V>) -> WhereFilter {
+        let values: Vec<Value> = values.into_iter().map(Into::into).collect();
+        self.condition(IN, values.into())
+    }
+
+    /// Match the entries whose value is none of `values`.
+    pub fn nin<V: Into<Value>>(self, values: impl IntoIterator<Item = V>) -> WhereFilter {
+        let values: Vec<Value> = values.into_iter().map(Into::into).collect();
+        self.condition(NIN, values.into())
+    }
+}
+
+/// A document contents filter, for the `where_document` of [GetOptions] and [QueryOptions].
+#[derive(Debug, Clone, PartialEq)]
+pub struct WhereDocumentFilter(Value);
+
+impl WhereDocumentFilter {
+    /// Match the documents containing `text`.
+    pub fn contains(text: impl Into<String>) -> Self {
+        Self(json!({ CONTAINS: text.into() }))
+    }
+
+    /// Match the documents not containing `text`.
+    pub fn not_contains(text: impl Into<String>) -> Self {
+        Self(json!({ NOT_CONTAINS: text.into() }))
+    }
+
+    /// Match the documents matching the regular expression `pattern`.
+    pub fn regex(pattern: impl Into<String>) -> Self {
+        Self(json!({ REGEX: pattern.into() }))
+    }
+
+    /// Match the documents not matching the regular expression `pattern`.
+    pub fn not_regex(pattern: impl Into<String>) -> Self {
+        Self(json!({ NOT_REGEX: pattern.into() }))
+    }
+
+    /// Match the documents matching all the filters. A single filter is used as is.
+    pub fn and(filters: impl IntoIterator<Item = WhereDocumentFilter>) -> Self {
+        Self(combine(AND, filters.into_iter().map(|filter| filter.0)))
+    }
+
+    /// Match the documents matching any of the filters. A single filter is used as is.
+    pub fn or(filters: impl IntoIterator<Item = WhereDocumentFilter>) -> Self {
+        Self(combine(OR, filters.into_iter().map(|filter| filter.0)))
+    }
+
+    pub fn into_value(self) -> Value {
+        self.0
+    }
+}
+
+impl From<WhereDocumentFilter> for Value {
+    fn from(filter: WhereDocumentFilter) -> Self {
+        filter.0
+    }
+}
+
+const EQ: &str = "$eq";
+const NE: &str = "$ne";
+const GT: &str = "$gt";
+const GTE: &str = "$gte";
+const LT: &str = "$lt";
+const LTE: &str = "$lte";
+const IN: &str = "$in";
+const NIN: &str = "$nin";
+const CONTAINS: &str = "$contains";
+const NOT_CONTAINS: &str = "$not_contains";
+const REGEX: &str = "$regex";
+const NOT_REGEX: &str = "$not_regex";
+const AND: &str = "$and";
+const OR: &str = "$or";
+
+/// The operators of [WhereField] conditions, the only ones a metadata filter may use besides `$and` and `$or`.
+const METADATA_OPERATORS: [&str; 8] = [EQ, NE, GT, GTE, LT, LTE, IN, NIN];
+/// The operators of [WhereDocumentFilter], the only ones a document filter may use besides `$and` and `$or`.
+const DOCUMENT_OPERATORS: [&str; 4] = [CONTAINS, NOT_CONTAINS, REGEX, NOT_REGEX];
+const LOGICAL_OPERATORS: [&str; 2] = [AND, OR];
+
+/// A metadata filter and a document filter for a single get or query.
+///
+/// Chroma takes them as two separate fields, `where` and `where_document`, rather than one tree. This keeps the
+/// sections apart and rejects operators put in the wrong one, like `$contains` in the metadata section,
+/// instead of letting the server fail the request.
+///
+/// ```
+/// use chromadb::collection::{Filter, QueryOptions, WhereDocumentFilter, WhereFilter};
+///
+///# fn doc_filter() -> Result<(), chromadb::ChromaError> {
+/// let filter = Filter::new()
+///     .metadata(WhereFilter::field("year").gte(2020))?
+///     .document(WhereDocumentFilter::contains("octopus"))?;
+/// let query = QueryOptions::builder()
+///     .query_texts(vec!["recipes"])
+///     .filter(filter)
+///     .build()?;
+/// assert!(query.where_metadata.is_some() && query.where_document.is_some());
+///# Ok(())
+///# }
+/// ```
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Filter {
+    where_metadata: Option<Value>,
+    where_document: Option<Value>,
+}
+
+impl Filter {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Set the metadata section, e.g. with a [WhereFilter].
+    ///
+    /// # Errors
+    ///
+    /// * If the filter uses a document operator, or an operator that is not a metadata operator
+    pub fn metadata(mut self, filter: impl Into<Value>) -> Result<Self> {
+        let filter = filter.into();
+        check_metadata_filter(&filter)?;
+        self.where_metadata = Some(filter);
+        Ok(self)
+    }
+
+    /// Set the document section, e.g. with a [WhereDocumentFilter].
+    ///
+    /// # Errors
+    ///
+    /// * If the filter uses a metadata operator or a metadata key, or an operator that is not a document operator
+    pub fn document(mut self, filter: impl Into<Value>) -> Result<Self> {
+        let filter = filter.into();
+        check_document_filter(&filter)?;
+        self.where_document = Some(filter);
+        Ok(self)
+    }
+
+    pub fn where_metadata(&self) -> Option<&Value> {
+        self.where_metadata.as_ref()
+    }
+
+    pub fn where_document(&self) -> Option<&Value> {
+        self.where_document.as_ref()
+    }
+
+    /// The metadata and document sections, for the `where_metadata` and `where_document` of [GetOptions] and [QueryOptions].
+    pub fn into_parts(self) -> (Option<Value>, Option<Value>) {
+        (self.where_metadata, self.where_document)
+    }
+}
+
+/// A small, seedable pseudo-random number generator for [ChromaCollection::sample]. Not for cryptographic use.
+struct SplitMix64(u64);
+
+impl SplitMix64 {
+    fn new(seed: u64) -> Self {
+        Self(seed)
+    }
+
+    fn next_u64(&mut self) -> u64 {
+        self.0 = self.0.wrapping_add(0x9e37_79b9_7f4a_7c15);
+        let mut z = self.0;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        z ^ (z >> 31)
+    }
+
+    /// A number in `0..bound`, with a negligible modulo bias for the bounds of a collection's count.
+    fn below(&mut self, bound: usize) -> usize {
+        (self.next_u64() % bound as u64) as usize
+    }
+}
+
+/// Check that a metadata filter only combines conditions on keys with metadata operators.
+fn check_metadata_filter(filter: &Value) -> Result<()> {
+    let Value::Object(filter) = filter else {
+        return Err(ChromaError::invalid_argument(format!(
+            "A metadata filter must be an object, got {filter}"
+        )));
+    };
+    for (key, value) in filter {
+        if LOGICAL_OPERATORS.contains(&key.as_str()) {
+            for filter in logical_operands(key, value)? {
+                check_metadata_filter(filter)?;
+            }
+        } else if key.starts_with('$') {
+            return Err(misplaced_operator(key, "metadata"));
+        } else if let Value::Object(condition) = value {
+            for operator in condition.keys() {
+                if !METADATA_OPERATORS.contains(&operator.as_str()) {
+                    return Err(misplaced_operator(operator, "metadata"));
+                }
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Check that a document filter only combines document operators.
+fn check_document_filter(filter: &Value) -> Result<()> {
+    let Value::Object(filter) = filter else {
+        return Err(ChromaError::invalid_argument(format!(
+            "A document filter must be an object, got {filter}"
+        )));
+    };
+    for (key, value) in filter {
+        if LOGICAL_OPERATORS.contains(&key.as_str()) {
+            for filter in logical_operands(key, value)? {
+                check_document_filter(filter)?;
+            }
+        } else if !DOCUMENT_OPERATORS.contains(&key.as_str()) {
+            return Err(misplaced_operator(key, "document"));
+        }
+    }
+    Ok(())
+}
+
+fn logical_operands<'v>(operator: &str, value: &'v Value) -> Result<&'v Vec<Value>> {
+    value.as_array().ok_or_else(|| {
+        ChromaError::invalid_argument(format!("{operator} expects a list of filters, got {value}"))
+    })
+}
+
+fn misplaced_operator(key: &str, section: &str) -> ChromaError {
+    let hint = if METADATA_OPERATORS.contains(&key) {
+        ", it is a metadata operator and belongs in the metadata section"
+    } else if DOCUMENT_OPERATORS.contains(&key) {
+        ", it is a document operator and belongs in the document section"
+    } else if !key.starts_with('$') {
+        ", document filters have no metadata keys"
+    } else {
+        ""
+    };
+    ChromaError::invalid_argument(format!(
+        "{key:?} is not allowed in the {section} section of a filter{hint}"
+    ))
+}
+
+/// Combine filters with `$and` or `$or`. Chroma rejects combinators with fewer than two filters,
+/// so a single filter is returned as is.
+fn combine(operator: &str, filters: impl Iterator<Item = Value>) -> Value {
+    let mut filters: Vec<Value> = filters.collect();
+    if filters.len() == 1 {
+        filters.remove(0)
+    } else {
+        json!({ operator: filters })
+    }
+}
+
+#[derive(Serialize, Debug, Default)]
+pub struct QueryOptions<'a> {
+    pub query_embeddings: Option<Embeddings>,
+    pub query_texts: Option<Vec<&'a str>>,
+    pub n_results: Option<usize>,
+    pub where_metadata: Option<Value>,
+    pub where_document: Option<Value>,
+    pub include: Option<Vec<Include>>,
+    /// Ids to leave out of the hits. Chroma cannot exclude ids server-side, so the query over-fetches and the
+    /// excluded hits are dropped client-side, see `exclude_overfetch`.
+    #[serde(skip)]
+    pub exclude_ids: Option<Vec<String>>,
+    /// How many extra results to fetch per excluded id, defaulting to 1.0. Each excluded id drops at most one hit,
+    /// so the default always leaves `n_results` hits when enough entries match. Lower it to fetch less when few of the
+    /// excluded ids are expected near the queries.
+    #[serde(skip)]
+    pub exclude_overfetch: Option<f32>,
+    /// The timeout of this query, overriding the client's `request_timeout`, e.g. for large batches of queries.
+    #[serde(skip)]
+    pub timeout: Option<Duration>,
+}
+
+impl<'a> QueryOptions<'a> {
+    /// Start building [QueryOptions], only setting the fields you need.
+    pub fn builder() -> QueryOptionsBuilder<'a> {
+        QueryOptionsBuilder::default()
+    }
+}
+
+/// A builder for [QueryOptions]. See [QueryOptions::builder].
+#[derive(Debug, Default)]
+pub struct QueryOptionsBuilder<'a> {
+    options: QueryOptions<'a>,
+}
+
+impl<'a> QueryOptionsBuilder<'a> {
+    pub fn query_embeddings(mut self, query_embeddings: Embeddings) -> Self {
+        self.options.query_embeddings = Some(query_embeddings);
+        self
+    }
+
+    pub fn query_texts(mut self, query_texts: Vec<&'a str>) -> Self {
+        self.options.query_texts = Some(query_texts);
+        self
+    }
+
+    pub fn n_results(mut self, n_results: usize) -> Self {
+        self.options.n_results = Some(n_results);
+        self
+    }
+
+    /// Filter by metadata, e.g. with a [WhereFilter].
+    pub fn where_metadata(mut self, where_metadata: impl Into<Value>) -> Self {
+        self.options.where_metadata = Some(where_metadata.into());
+        self
+    }
+
+    /// Filter by document contents, e.g. with a [WhereDocumentFilter].
+    pub fn where_document(mut self, where_document: impl Into<Value>) -> Self {
+        self.options.where_document = Some(where_document.into());
+        self
+    }
+
+    /// Set both the metadata and document filters, see [Filter].
+    pub fn filter(mut self, filter: Filter) -> Self {
+        (self.options.where_metadata, self.options.where_document) = filter.into_parts();
+        self
+    }
+
+    pub fn include(mut self, include: Vec<Include>) -> Self {
+        self.options.include = Some(include);
+        self
+    }
+
+    /// Leave these ids out of the hits, see [QueryOptions::exclude_ids].
+    pub fn exclude_ids(mut self, exclude_ids: Vec<String>) -> Self {
+        self.options.exclude_ids = Some(exclude_ids);
+        self
+    }
+
+    /// See [QueryOptions::exclude_overfetch].
+    pub fn exclude_overfetch(mut self, exclude_overfetch: f32) -> Self {
+        self.options.exclude_overfetch = Some(exclude_overfetch);
+        self
+    }
+
+    /// See [QueryOptions::timeout].
+    pub fn timeout(mut self, timeout: Duration) -> Self {
+        self.options.timeout = Some(timeout);
+        self
+    }
+
+    /// Build the [QueryOptions].
+    ///
+    /// # Errors
+    ///
+    /// * If neither or both of query_embeddings and query_texts are set
+    pub fn build(self) -> std::result::Result<QueryOptions<'a>, ChromaError> {
+        match (&self.options.query_embeddings, &self.options.query_texts) {
+            (None, None) => Err(ChromaError::invalid_argument(
+                "You must provide either query_embeddings or query_texts",
+            )),
+            (Some(_), Some(_)) => Err(ChromaError::invalid_argument(
+                "You can only provide query_embeddings or query_texts, not both",
+            )),
+            _ => Ok(self.options),
+        }
+    }
+}
+
+#[derive(Deserialize, Debug)]
+pub struct QueryResult {
+    pub ids: Vec<Vec<String>>,
+    /// Only populated when [Include::Metadatas] is requested.
+    pub metadatas: Option<Vec<Vec<Option<Metadata>>>>,
+    /// Only populated when [Include::Documents] is requested.
+    pub documents: Option<Vec<Vec<String>>>,
+    /// Only populated when [Include::Embeddings] is requested.
+    /// `None` if the server left any of them out, see [ChromaCollection::with_strict_embeddings].
+    #[serde(default, deserialize_with = "deserialize_query_embeddings")]
+    pub embeddings: Option<Vec<Vec<Embedding>>>,
+    /// Only populated when [Include::Distances] is requested.
+    pub distances: Option<Vec<Vec<f32>>>,
+}
+
+/// Deserialize the embeddings of a query result as `None` if the server returned `null` for any of them,
+/// as some managed deployments do for large results, instead of failing.
+fn deserialize_query_embeddings<'de, D: serde::Deserializer<'de>>(
+    deserializer: D,
+) -> std::result::Result<Option<Vec<Vec<Embedding>>>, D::Error> {
+    let embeddings: Option<Vec<Option<Vec<Option<Embedding>>>>> =
+        Option::deserialize(deserializer)?;
+    Ok(embeddings.and_then(|queries| {
+        queries
+            .into_iter()
+            .map(|query| query?.into_iter().collect())
+            .collect()
+    }))
+}
+
+/// The result of [query_batched](ChromaCollection::query_batched).
+#[derive(Debug)]
+pub struct BatchedQueryResult {
+    /// The hits of every query embedding, in order. The queries of failed batches have no hits.
+    pub result: QueryResult,
+    /// A [ChromaError::ChunkFailed] for every batch that failed, in order. Only populated with `allow_partial`.
+    pub failed: Vec<ChromaError>,
+}
+
+impl QueryResult {
+    /// The number of hits returned without an embedding.
+    fn missing_embeddings(&self) -> usize {
+        let total: usize = self.ids.iter().map(Vec::len).sum();
+        match &self.embeddings {
+            Some(embeddings) => {
+                let returned: usize = embeddings.iter().map(Vec::len).sum();
+                total.saturating_sub(returned)
+            }
+            None => total,
+        }
+    }
+
+    /// A result with no hits for `queries` queries.
+    fn empty(queries: usize) -> Self {
+        Self {
+            ids: vec![Vec::new(); queries],
+            metadatas: None,
+            documents: None,
+            embeddings: None,
+            distances: None,
+        }
+    }
+
+    /// Append the queries of another result. A column missing from one side is filled with empty lists for its queries.
+    fn extend(&mut self, other: QueryResult) {
+        fn extend_column<T>(
+            column: &mut Option<Vec<Vec<T>>>,
+            other: Option<Vec<Vec<T>>>,
+            len: usize,
+            other_len: usize,
+        ) {
+            match (column.as_mut(), other) {
+                (Some(column), Some(other)) => column.extend(other),
+                (Some(column), None) => column.extend((0..other_len).map(|_| Vec::new())),
+                (None, Some(other)) => {
+                    let mut filled: Vec<Vec<T>> = (0..len).map(|_| Vec::new()).collect();
+                    filled.extend(other);
+                    *column = Some(filled);
+                }
+                (None, None) => {}
+            }
+        }
+        let len = self.ids.len();
+        let other_len = other.ids.len();
+        self.ids.extend(other.ids);
+        extend_column(&mut self.metadatas, other.metadatas, len, other_len);
+        extend_column(&mut self.documents, other.documents, len, other_len);
+        extend_column(&mut self.embeddings, other.embeddings, len, other_len);
+        extend_column(&mut self.distances, other.distances, len, other_len);
+    }
+
+    /// Drop the hits whose id is in `excluded` and keep at most `n_results` of the rest, for each query.
+    fn exclude(&mut self, excluded: &HashSet<&str>, n_results: usize) {
+        fn retain<T>(column: &mut Vec<T>, keep: &[bool]) {
+            let mut keep = keep.iter();
+            column.retain(|_| keep.next().copied().unwrap_or(false));
+        }
+        for (i, ids) in self.ids.iter_mut().enumerate() {
+            let mut kept = 0;
+            let keep: Vec<bool> = ids
+                .iter()
+                .map(|id| {
+                    let keep = kept < n_results && !excluded.contains(id.as_str());
+                    kept += keep as usize;
+                    keep
+                })
+                .collect();
+            retain(ids, &keep);
+            if let Some(metadatas) = self.metadatas.as_mut().and_then(|m| m.get_mut(i)) {
+                retain(metadatas, &keep);
+            }
+            if let Some(documents) = self.documents.as_mut().and_then(|d| d.get_mut(i)) {
+                retain(documents, &keep);
+            }
+            if let Some(embeddings) = self.embeddings.as_mut().and_then(|e| e.get_mut(i)) {
+                retain(embeddings, &keep);
+            }
+            if let Some(distances) = self.distances.as_mut().and_then(|d| d.get_mut(i)) {
+                retain(distances, &keep);
+            }
+        }
+    }
+
+    /// Flatten the result into one list of [Hit]s per query.
+    pub fn into_hits(self) -> Vec<Vec<Hit>> {
+        let QueryResult {
+            ids,
+            metadatas,
+            documents,
+            embeddings: _,
+            distances,
+        } = self;
+        let mut metadatas = metadatas.map(|m| m.into_iter());
+        let mut documents = documents.map(|d| d.into_iter());
+        let mut distances = distances.map(|d| d.into_iter());
+        ids.into_iter()
+            .map(|ids| {
+                let mut metadatas = metadatas
+                    .as_mut()
+                    .and_then(|m| m.next())
+                    .map(|m| m.into_iter());
+                let mut documents = documents
+                    .as_mut()
+                    .and_then(|d| d.next())
+                    .map(|d| d.into_iter());
+                let mut distances = distances
+                    .as_mut()
+                    .and_then(|d| d.next())
+                    .map(|d| d.into_iter());
+                ids.into_iter()
+                    .map(|id| Hit {
+                        id,
+                        document: documents.as_mut().and_then(|d| d.next()),
+                        metadata: metadatas.as_mut().and_then(|m| m.next()).flatten(),
+                        distance: distances.as_mut().and_then(|d| d.next()),
+                    })
+                    .collect()
+            })
+            .collect()
+    }
+}
+
+/// A single entry returned by a similarity search.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Hit {
+    pub id: String,
+    pub document: Option<String>,
+    pub metadata: Option<Metadata>,
+    pub distance: Option<f32>,
+}
+
+/// The hits of a single query, ranked from nearest to farthest. Derefs to a slice of [Hit].
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct QueryHits {
+    pub hits: Vec<Hit>,
+    pub warnings: Warnings,
+}
+
+impl std::ops::Deref for QueryHits {
+    type Target = [Hit];
+
+    fn deref(&self) -> &[Hit] {
+        &self.hits
+    }
+}
+
+impl IntoIterator for QueryHits {
+    type Item = Hit;
+    type IntoIter = vec::IntoIter<Hit>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.hits.into_iter()
+    }
+}
+
+/// Serves successive pages of query hits. See [ChromaCollection::query_paged].
+pub struct QueryPager<'c> {
+    collection: &'c ChromaCollection,
+    query_embedding: Embedding,
+    where_metadata: Option<Value>,
+    where_document: Option<Value>,
+    include: Vec<Include>,
+    exclude_ids: Option<Vec<String>>,
+    exclude_overfetch: Option<f32>,
+    timeout: Option<Duration>,
+    page_size: usize,
+    hits: Vec<Hit>,
+    fetched: usize,
+    position: usize,
+    exhausted: bool,
+}
+
+impl QueryPager<'_> {
+    /// Get the next page of hits, or `None` once all hits have been served.
+    /// Only queries the server when the page runs past the hits fetched so far.
+    pub async fn next_page(&mut self) -> Result<Option<Vec<Hit>>> {
+        let end = self.position + self.page_size;
+        if let Some(n_results) = next_fetch_size(self.fetched, end, self.exhausted) {
+            let query = QueryOptions {
+                query_embeddings: Some(vec![self.query_embedding.clone()]),
+                query_texts: None,
+                n_results: Some(n_results),
+                where_metadata: self.where_metadata.clone(),
+                where_document: self.where_document.clone(),
+                include: Some(self.include.clone()),
+                exclude_ids: self.exclude_ids.clone(),
+                exclude_overfetch: self.exclude_overfetch,
+                timeout: self.timeout,
+            };
+            let query_result = self.collection.query(query, None).await?;
+            self.hits = query_result
+                .into_hits()
+                .into_iter()
+                .next()
+                .unwrap_or_default();
+            self.fetched = n_results;
+            self.exhausted = self.hits.len() < n_results;
+        }
+
+        if self.position >= self.hits.len() {
+            return Ok(None);
+        }
+        let page = self.hits[self.position..end.min(self.hits.len())].to_vec();
+        self.position = end;
+        Ok(Some(page))
+    }
+}
+
+/// How many results to query for so that the hits cover `end`, doubling what was fetched before,
+/// or `None` if the hits fetched so far already suffice.
+fn next_fetch_size(fetched: usize, end: usize, exhausted: bool) -> Option<usize> {
+    if exhausted || end <= fetched {
+        None
+    } else {
+        Some(end.max(fetched * 2))
+    }
+}
+
+#[derive(Serialize, Debug, Default)]
+pub struct CollectionEntries<'a> {
+    pub ids: Vec<&'a str>,
+    /// Integer values are sent and read back exactly, but Chroma stores them as 64-bit signed integers:
+    /// values above `i64::MAX`, like large `u64` ids, are rejected with [ValidationRule::IntegerOutOfRange]. Store them as strings instead.
+    pub metadatas: Option<Metadatas>,
+    pub documents: Option<Documents<'a>>,
+    pub embeddings: Option<Embeddings>,
+}
+
+impl CollectionEntries<'_> {
+    /// The size in bytes of the JSON body sent to add or upsert these entries, computed without building it.
+    pub fn estimated_body_size(&self) -> usize {
+        self.estimated_body_size_with(None)
+    }
+
+    /// The body size once embedding components are rounded to `float_precision` decimal places,
+    /// see [ChromaCollection::with_float_precision].
+    pub fn estimated_body_size_with(&self, float_precision: Option<u8>) -> usize {
+        let record_sizes: usize = self.record_sizes(float_precision).iter().sum();
+        // Every record carries a trailing comma per field, except the last one.
+        let trailing_commas = if self.ids.is_empty() {
+            0
+        } else {
+            self.field_count()
+        };
+        self.body_overhead() + record_sizes - trailing_commas
+    }
+
+    /// The number of array fields in the body: the ids, and the other fields that are set.
+    fn field_count(&self) -> usize {
+        1 + [
+            self.metadatas.is_some(),
+            self.documents.is_some(),
+            self.embeddings.is_some(),
+        ]
+        .into_iter()
+        .filter(|&set| set)
+        .count()
+    }
+
+    /// The bytes of the body that do not depend on the records: braces, keys, brackets and nulls.
+    fn body_overhead(&self) -> usize {
+        let field = |key: &str, set: bool| key.len() + 3 + if set { 2 } else { 4 };
+        2 + 3
+            + field("ids", true)
+            + field("metadatas", self.metadatas.is_some())
+            + field("documents", self.documents.is_some())
+            + field("embeddings", self.embeddings.is_some())
+    }
+
+    /// The bytes of every record across all fields, each including a trailing comma per field.
+    fn record_sizes(&self, float_precision: Option<u8>) -> Vec<usize> {
+        (0..self.ids.len())
+            .map(|i| {
+                let metadata = self.metadatas.as_ref().and_then(|m| m.get(i));
+                let document = self.documents.as_ref().and_then(|d| d.get(i));
+                let embedding = self.embeddings.as_ref().and_then(|e| e.get(i));
+                json_len(self.ids[i])
+                    + metadata.map_or(0, json_len)
+                    + document.map_or(0, json_len)
+                    + embedding.map_or(0, |e| embedding_json_len(e, float_precision))
+                    + self.field_count()
+            })
+            .collect()
+    }
+}
+
+impl<'a> CollectionEntries<'a> {
+    /// Build entries one record at a time, see [CollectionEntriesBuilder].
+    pub fn builder() -> CollectionEntriesBuilder<'a> {
+        CollectionEntriesBuilder::default()
+    }
+
+    /// Entries with a freshly generated UUID id per record, for records without a meaningful id.
+    ///
+    /// The entries borrow their ids, so they are generated into `ids`, replacing its contents. Keep it to store the ids
+    /// once the entries are written. The number of records is taken from the documents, then the embeddings, then the metadatas.
+    ///
+    /// ```
+    /// use chromadb::collection::CollectionEntries;
+    ///
+    /// let mut ids = vec![];
+    /// let collection_entries = CollectionEntries::with_generated_ids(
+    ///     &mut ids,
+    ///     None,
+    ///     None,
+    ///     Some(vec!["9 octopus recipes", "Superman vs Superman"]),
+    /// );
+    /// assert_eq!(collection_entries.ids.len(), 2);
+    /// ```
+    pub fn with_generated_ids(
+        ids: &'a mut Vec<String>,
+        embeddings: Option<Embeddings>,
+        metadatas: Option<Metadatas>,
+        documents: Option<Documents<'a>>,
+    ) -> Self {
+        let len = documents
+            .as_ref()
+            .map(Vec::len)
+            .or(embeddings.as_ref().map(Vec::len))
+            .or(metadatas.as_ref().map(Vec::len))
+            .unwrap_or(0);
+        *ids = generate_ids(len);
+        let ids: &'a Vec<String> = ids;
+        CollectionEntries {
+            ids: ids.iter().map(String::as_str).collect(),
+            metadatas,
+            documents,
+            embeddings,
+        }
+    }
+}
+
+/// `n` random UUIDs, as ids for records without a meaningful one.
+pub(crate) fn generate_ids(n: usize) -> Vec<String> {
+    (0..n).map(|_| uuid::Uuid::new_v4().to_string()).collect()
+}
+
+/// Collects `(id, embedding, metadata, document)` records into entries.
+/// Metadatas and documents are set if any record has one: records without get an empty metadata or an empty document.
+/// Use [CollectionEntriesBuilder] to reject such records, and empty or duplicate ids, instead.
+impl<'a> FromIterator<(&'a str, Embedding, Option<Metadata>, Option<&'a str>)>
+    for CollectionEntries<'a>
+{
+    fn from_iter<
+        I: IntoIterator<Item = (&'a str, Embedding, Option<Metadata>, Option<&'a str>)>,
+    >(
+        iter: I,
+    ) -> Self {
+        let mut ids = vec![];
+        let mut embeddings = vec![];
+        let mut metadatas = vec![];
+        let mut documents = vec![];
+        for (id, embedding, metadata, document) in iter {
+            ids.push(id);
+            embeddings.push(embedding);
+            metadatas.push(metadata);
+            documents.push(document);
+        }
+        let metadatas = metadatas.iter().any(Option::is_some).then(|| {
+            metadatas
+                .into_iter()
+                .map(Option::unwrap_or_default)
+                .collect()
+        });
+        let documents = documents.iter().any(Option::is_some).then(|| {
+            documents
+                .into_iter()
+                .map(Option::unwrap_or_default)
+                .collect()
+        });
+        CollectionEntries {
+            ids,
+            metadatas,
+            documents,
+            embeddings: Some(embeddings),
+        }
+    }
+}
+
+/// Iterates over the entries record by record, as `(id, embedding, metadata, document)`.
+impl<'a> IntoIterator for CollectionEntries<'a> {
+    type Item = (
+        &'a str,
+        Option<Embedding>,
+        Option<Metadata>,
+        Option<&'a str>,
+    );
+    type IntoIter = vec::IntoIter<Self::Item>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        fn column<T>(values: Option<Vec<T>>) -> impl Iterator<Item = Option<T>> {
+            values
+                .into_iter()
+                .flatten()
+                .map(Some)
+                .chain(std::iter::repeat_with(|| None))
+        }
+        let records: Vec<_> = self
+            .ids
+            .into_iter()
+            .zip(column(self.embeddings))
+            .zip(column(self.metadatas))
+            .zip(column(self.documents))
+            .map(|(((id, embedding), metadata), document)| (id, embedding, metadata, document))
+            .collect();
+        records.into_iter()
+    }
+}
+
+/// Builds [CollectionEntries] one record at a time, keeping ids, embeddings, metadatas and documents in sync.
+///
+/// ```
+/// use chromadb::collection::CollectionEntries;
+///
+/// let collection_entries = CollectionEntries::builder()
+///     .add_entry("a", Some(vec![0.5, 0.25]), None, Some("first"))
+///     .add_entry("b", Some(vec![0.25, 0.5]), None, Some("second"))
+///     .build()
+///     .unwrap();
+/// assert_eq!(collection_entries.ids, ["a", "b"]);
+/// assert!(collection_entries.metadatas.is_none());
+/// ```
+#[derive(Debug, Default)]
+pub struct CollectionEntriesBuilder<'a> {
+    /// `None` for the records added with [add_generated_entry](Self::add_generated_entry).
+    ids: Vec<Option<&'a str>>,
+    embeddings: Vec<Option<Embedding>>,
+    metadatas: Vec<Option<Metadata>>,
+    documents: Vec<Option<&'a str>>,
+    generated_ids: Option<&'a mut Vec<String>>,
+}
+
+impl<'a> CollectionEntriesBuilder<'a> {
+    /// Generate a UUID id for every record added with [add_generated_entry](Self::add_generated_entry).
+    ///
+    /// The entries borrow their ids, so they are generated into `ids` by [build](Self::build), replacing its contents.
+    /// Keep it to store the ids once the entries are written.
+    ///
+    /// ```
+    /// use chromadb::collection::CollectionEntries;
+    ///
+    /// let mut ids = vec![];
+    /// let collection_entries = CollectionEntries::builder()
+    ///     .generate_ids(&mut ids)
+    ///     .add_generated_entry(Some(vec![0.5, 0.25]), None, Some("first"))
+    ///     .add_generated_entry(Some(vec![0.25, 0.5]), None, Some("second"))
+    ///     .build()
+    ///     .unwrap();
+    /// assert_eq!(collection_entries.ids.len(), 2);
+    /// ```
+    pub fn generate_ids(mut self, ids: &'a mut Vec<String>) -> Self {
+        self.generated_ids = Some(ids);
+        self
+    }
+
+    /// Add a record under a generated id. Requires [generate_ids](Self::generate_ids).
+    pub fn add_generated_entry(
+        mut self,
+        embedding: Option<Embedding>,
+        metadata: Option<Metadata>,
+        document: Option<&'a str>,
+    ) -> Self {
+        self.ids.push(None);
+        self.embeddings.push(embedding);
+        self.metadatas.push(metadata);
+        self.documents.push(document);
+        self
+    }
+
+    /// Add a record. Leave out the embedding to compute it from the document with an embedding function.
+    pub fn add_entry(
+        mut self,
+        id: &'a str,
+        embedding: Option<Embedding>,
+        metadata: Option<Metadata>,
+        document: Option<&'a str>,
+    ) -> Self {
+        self.ids.push(Some(id));
+        self.embeddings.push(embedding);
+        self.metadatas.push(metadata);
+        self.documents.push(document);
+        self
+    }
+
+    /// Assemble the entries. A field is set if any record has it, in which case every record must have it.
+    ///
+    /// # Errors
+    ///
+    /// * [ChromaError::Validation] listing the empty and duplicate ids, and the records missing a field that others have
+    /// * If records were added with [add_generated_entry](Self::add_generated_entry) without [generate_ids](Self::generate_ids)
+    pub fn build(self) -> Result<CollectionEntries<'a>> {
+        let ids: Vec<&'a str> = if self.ids.iter().any(Option::is_none) {
+            let Some(generated_ids) = self.generated_ids else {
+                return Err(ChromaError::invalid_argument(
+                    "add_generated_entry requires generate_ids to be called first",
+                ));
+            };
+            *generated_ids = generate_ids(self.ids.iter().filter(|id| id.is_none()).count());
+            let generated_ids: &'a Vec<String> = generated_ids;
+            let mut generated_ids = generated_ids.iter().map(String::as_str);
+            self.ids
+                .into_iter()
+                .map(|id| id.or_else(|| generated_ids.next()).unwrap_or_default())
+                .collect()
+        } else {
+            self.ids.into_iter().flatten().collect()
+        };
+        let max_violations = DEFAULT_MAX_VALIDATION_VIOLATIONS;
+        let mut error = ValidationError::new(max_violations);
+        check_ids(&mut error, max_violations, &ids);
+        let embeddings = assemble_column(&mut error, &ids, "embedding", self.embeddings);
+        let metadatas = assemble_column(&mut error, &ids, "metadata", self.metadatas);
+        let documents = assemble_column(&mut error, &ids, "document", self.documents);
+        error.into_result()?;
+        Ok(CollectionEntries {
+            ids,
+            metadatas,
+            documents,
+            embeddings,
+        })
+    }
+}
+
+/// Turn a column of optional values into the field of [CollectionEntries], reporting the records missing a value if any record has one.
+fn assemble_column<T>(
+    error: &mut ValidationError,
+    ids: &[&str],
+    field: &str,
+    values: Vec<Option<T>>,
+) -> Option<Vec<T>> {
+    if values.iter().all(Option::is_none) {
+        return None;
+    }
+    for (index, value) in values.iter().enumerate() {
+        if value.is_none() {
+            error.push(
+                DEFAULT_MAX_VALIDATION_VIOLATIONS,
+                Some(index),
+                Some(ids[index]),
+                ValidationRule::LengthMismatch,
+                format!("Missing {field}, which other records have"),
+            );
+        }
+    }
+    values.into_iter().collect()
+}
+
+/// The length of a value's JSON serialization, counted without allocating it.
+fn json_len<T: Serialize + ?Sized>(value: &T) -> usize {
+    struct Counter(usize);
+    impl std::io::Write for Counter {
+        fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+            self.0 += buf.len();
+            Ok(buf.len())
+        }
+        fn flush(&mut self) -> std::io::Result<()> {
+            Ok(())
+        }
+    }
+    let mut counter = Counter(0);
+    match serde_json::to_writer(&mut counter, value) {
+        Ok(()) => counter.0,
+        Err(_) => 0,
+    }
+}
+
+fn embedding_json_len(embedding: &Embedding, float_precision: Option<u8>) -> usize {
+    let components: usize = embedding
+        .iter()
+        .map(|&x| json_len(&component_json(x, float_precision)))
+        .sum();
+    2 + components + embedding.len().saturating_sub(1)
+}
+
+/// Embedding components are sent as JSON numbers holding the f32 widened to f64, or rounded to `float_precision` decimal places.
+fn component_json(x: f32, float_precision: Option<u8>) -> f64 {
+    match float_precision {
+        Some(precision) => {
+            let scale = 10f64.powi(precision.into());
+            (x as f64 * scale).round() / scale
+        }
+        None => x as f64,
+    }
+}
+
+/// The body of an add, upsert or update, borrowing the entries instead of copying them into a [Value].
+///
+/// The fields are in alphabetical order, the order of the keys of a [Value] object, so that the body is the same
+/// as if the entries were serialized through one.
+#[derive(Serialize)]
+struct WriteBody<'a> {
+    documents: Option<&'a [&'a str]>,
+    embeddings: Option<EmbeddingsJson<'a>>,
+    ids: &'a [&'a str],
+    metadatas: Option<&'a [Metadata]>,
+}
+
+impl<'a> WriteBody<'a> {
+    fn new(collection_entries: &'a CollectionEntries<'a>, float_precision: Option<u8>) -> Self {
+        Self {
+            documents: collection_entries.documents.as_deref(),
+            embeddings: collection_entries
+                .embeddings
+                .as_deref()
+                .map(|embeddings| EmbeddingsJson {
+                    embeddings,
+                    float_precision,
+                }),
+            ids: &collection_entries.ids,
+            metadatas: collection_entries.metadatas.as_deref(),
+        }
+    }
+}
+
+/// Embeddings serialized component by component with [component_json].
+struct EmbeddingsJson<'a> {
+    embeddings: &'a [Embedding],
+    float_precision: Option<u8>,
+}
+
+impl Serialize for EmbeddingsJson<'_> {
+    fn serialize<S: serde::Serializer>(
+        &self,
+        serializer: S,
+    ) -> std::result::Result<S::Ok, S::Error> {
+        struct EmbeddingJson<'a>(&'a Embedding, Option<u8>);
+
+        impl Serialize for EmbeddingJson<'_> {
+            fn serialize<S: serde::Serializer>(
+                &self,
+                serializer: S,
+            ) -> std::result::Result<S::Ok, S::Error> {
+                serializer.collect_seq(self.0.iter().map(|&x| component_json(x, self.1)))
+            }
+        }
+
+        serializer.collect_seq(
+            self.embeddings
+                .iter()
+                .map(|embedding| EmbeddingJson(embedding, self.float_precision)),
+        )
+    }
+}
+
+/// The result of a chunked write.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct AddResult {
+    /// The server's response to every chunk, in order.
+    pub responses: Vec<Value>,
+    pub warnings: Warnings,
+}
+
+/// Options for the chunked writes, [add_chunked](ChromaCollection::add_chunked) and [upsert_chunked](ChromaCollection::upsert_chunked).
+pub struct AddOptions {
+    /// How many chunks to write at once. Defaults to 1, writing the chunks sequentially.
+    pub concurrency: usize,
+    /// Run the embedding function's [health_check](EmbeddingFunction::health_check) before writing anything.
+    pub preflight_embeddings: bool,
+    /// Called with the number of entries written so far and the total after every chunk.
+    pub progress: Option<Box<dyn Fn(usize, usize) + Send + Sync>>,
+    /// Also cap each chunk's request body at roughly this many bytes, see [CollectionEntries::estimated_body_size].
+    /// Embeddings computed from documents are not part of the estimate, so leave room for them. A single entry
+    /// larger than the cap is sent on its own.
+    pub max_chunk_bytes: Option<usize>,
+}
+
+impl Default for AddOptions {
+    fn default() -> Self {
+        Self {
+            concurrency: 1,
+            preflight_embeddings: false,
+            progress: None,
+            max_chunk_bytes: None,
+        }
+    }
+}
+
+impl std::fmt::Debug for AddOptions {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("AddOptions")
+            .field("concurrency", &self.concurrency)
+            .field("preflight_embeddings", &self.preflight_embeddings)
+            .field("progress", &self.progress.is_some())
+            .field("max_chunk_bytes", &self.max_chunk_bytes)
+            .finish()
+    }
+}
+
+/// Options for [add_with_options](ChromaCollection::add_with_options) and [upsert_with_options](ChromaCollection::upsert_with_options).
+#[derive(Debug, Clone, Default)]
+pub struct UpsertOptions {
+    /// How many calls to the embedding function to run at once, see [EmbeddingFunction::embed_parallel].
+    /// Defaults to 1, embedding all the documents in a single call.
+    pub concurrency: Option<usize>,
+}
+
+/// Split the entries into chunks of at most `chunk_size` entries and, if given, about `max_chunk_bytes` bytes,
+/// keeping ids, metadatas, documents and embeddings in sync.
+fn split_entries(
+    collection_entries: CollectionEntries<'_>,
+    chunk_size: usize,
+    max_chunk_bytes: Option<usize>,
+    float_precision: Option<u8>,
+) -> Vec<CollectionEntries<'_>> {
+    fn split<T>(items: Vec<T>, chunk_lens: &[usize]) -> Vec<Vec<T>> {
+        let mut items = items.into_iter();
+        chunk_lens
+            .iter()
+            .map(|&len| items.by_ref().take(len).collect())
+            .collect()
+    }
+    fn split_optional<T>(items: Option<Vec<T>>, chunk_lens: &[usize]) -> Vec<Option<Vec<T>>> {
+        match items {
+            Some(items) => split(items, chunk_lens).into_iter().map(Some).collect(),
+            None => vec![],
+        }
+    }
+
+    let mut chunk_lens = vec![];
+    match max_chunk_bytes {
+        Some(max_chunk_bytes) => {
+            let overhead = collection_entries.body_overhead();
+            let (mut len, mut bytes) = (0, overhead);
+            for record_bytes in collection_entries.record_sizes(float_precision) {
+                if len > 0 && (len == chunk_size || bytes + record_bytes > max_chunk_bytes) {
+                    chunk_lens.push(len);
+                    (len, bytes) = (0, overhead);
+                }
+                len += 1;
+                bytes += record_bytes;
+            }
+            if len > 0 {
+                chunk_lens.push(len);
+            }
+        }
+        None => {
+            let mut remaining = collection_entries.ids.len();
+            while remaining > 0 {
+                chunk_lens.push(remaining.min(chunk_size));
+                remaining -= remaining.min(chunk_size);
+            }
+        }
+    }
+
+    let CollectionEntries {
+        ids,
+        metadatas,
+        documents,
+        embeddings,
+    } = collection_entries;
+    let mut metadatas = split_optional(metadatas, &chunk_lens).into_iter();
+    let mut documents = split_optional(documents, &chunk_lens).into_iter();
+    let mut embeddings = split_optional(embeddings, &chunk_lens).into_iter();
+    split(ids, &chunk_lens)
+        .into_iter()
+        .map(|ids| CollectionEntries {
+            ids,
+            metadatas: metadatas.next().flatten(),
+            documents: documents.next().flatten(),
+            embeddings: embeddings.next().flatten(),
+        })
+        .collect()
+}
+
+/// Reject empty filter objects, which servers interpret inconsistently,
+/// and metadata filters on integers Chroma cannot store, which would never match.
+fn check_filters(where_metadata: &Option<Value>, where_document: &Option<Value>) -> Result<()> {
+    for (name, filter) in [
+        ("where_metadata", where_metadata),
+        ("where_document", where_document),
+    ] {
+        if matches!(filter, Some(Value::Object(filter)) if filter.is_empty()) {
+            return Err(ChromaError::invalid_argument(format!(
+                "{name} cannot be an empty object, use None to not filter"
+            )));
+        }
+    }
+    if let Some(n) = where_metadata.as_ref().and_then(find_out_of_range_integer) {
+        return Err(ChromaError::invalid_argument(format!(
+            "where_metadata compares with {n}, which is above the largest integer Chroma stores ({})",
+            i64::MAX
+        )));
+    }
+    Ok(())
+}
+
+/// Drop the hits whose id is in `excluded` and keep at most `n_results` of the rest.
+fn exclude_hits(hits: Vec<Hit>, excluded: &[&str], n_results: usize) -> Vec<Hit> {
+    hits.into_iter()
+        .filter(|hit| !excluded.contains(&hit.id.as_str()))
+        .take(n_results)
+        .collect()
+}
+
+/// Merge one hit list per query embedding into a single list ranked by weighted distance.
+/// Ties are broken by id so the ranking is deterministic.
+fn combine_weighted_hits(
+    hit_lists: Vec<Vec<Hit>>,
+    weights: &[f32],
+    missing_distance: Option<f32>,
+    n_results: usize,
+) -> Vec<Hit> {
+    let mut hits: BTreeMap<String, Hit> = BTreeMap::new();
+    let mut distances: Vec<(HashMap<String, f32>, f32)> = Vec::with_capacity(hit_lists.len());
+    for hit_list in hit_lists {
+        let mut list_distances = HashMap::with_capacity(hit_list.len());
+        // Seeded from the hits rather than 0, as inner product distances can be negative.
+        let mut max_distance: Option<f32> = None;
+        for hit in hit_list {
+            let distance = hit.distance.unwrap_or(0.0);
+            max_distance = Some(max_distance.map_or(distance, |max| max.max(distance)));
+            list_distances.insert(hit.id.clone(), distance);
+            hits.entry(hit.id.clone()).or_insert(hit);
+        }
+        let missing = missing_distance.or(max_distance).unwrap_or(0.0);
+        distances.push((list_distances, missing));
+    }
+
+    let mut ranked: Vec<Hit> = hits
+        .into_values()
+        .map(|mut hit| {
+            let combined = distances
+                .iter()
+                .zip(weights)
+                .map(|((list_distances, missing), weight)| {
+                    weight * list_distances.get(&hit.id).copied().unwrap_or(*missing)
+                })
+                .sum();
+            hit.distance = Some(combined);
+            hit
+        })
+        .collect();
+    // The sort is stable and `hits` is ordered by id, so ties keep id order.
+    ranked.sort_by(|a, b| a.distance.unwrap().total_cmp(&b.distance.unwrap()));
+    ranked.truncate(n_results);
+    ranked
+}
+
+/// The rule a [Violation] broke.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ValidationRule {
+    MissingEmbeddingsAndDocuments,
+    MissingEmbeddingFunction,
+    UnexpectedEmbeddingFunction,
+    LengthMismatch,
+    EmptyId,
+    DuplicateId,
+    /// The record's metadata does not match the [MetadataSchema] of the collection handle.
+    MetadataSchema,
+    /// The record's metadata holds an integer above `i64::MAX`, which Chroma cannot store faithfully.
+    IntegerOutOfRange,
+}
+
+/// A single problem found while validating [CollectionEntries].
+/// `index` and `id` are `None` for problems that concern the whole batch.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Violation {
+    pub index: Option<usize>,
+    pub id: Option<String>,
+    pub rule: ValidationRule,
+    pub detail: String,
+}
+
+impl std::fmt::Display for Violation {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match (self.index, &self.id) {
+            (Some(index), Some(id)) => write!(f, "record {index} (id {id:?}): {}", self.detail),
+            (Some(index), None) => write!(f, "record {index}: {}", self.detail),
+            _ => write!(f, "{}", self.detail),
+        }
+    }
+}
+
+/// The error returned when [CollectionEntries] fail validation, listing every violation found up to a cap.
+/// See [ChromaCollection::with_max_validation_violations].
+#[derive(Debug, Clone, PartialEq)]
+pub struct ValidationError {
+    pub violations: Vec<Violation>,
+    /// The number of violations found, including those beyond the cap.
+    pub total: usize,
+}
+
+impl ValidationError {
+    fn new(max_violations: usize) -> Self {
+        Self {
+            violations: Vec::with_capacity(max_violations.min(16)),
+            total: 0,
+        }
+    }
+
+    fn push(
+        &mut self,
+        max_violations: usize,
+        index: Option<usize>,
+        id: Option<&str>,
+        rule: ValidationRule,
+        detail: impl Into<String>,
+    ) {
+        self.total += 1;
+        if self.violations.len() < max_violations {
+            self.violations.push(Violation {
+                index,
+                id: id.map(str::to_string),
+                rule,
+                detail: detail.into(),
+            });
+        }
+    }
+
+    fn into_result(self) -> std::result::Result<(), Self> {
+        if self.total == 0 {
+            Ok(())
+        } else {
+            Err(self)
+        }
+    }
+}
+
+impl std::fmt::Display for ValidationError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} validation error(s)", self.total)?;
+        for violation in &self.violations {
+            write!(f, "\n  - {violation}")?;
+        }
+        let omitted = self.total - self.violations.len();
+        if omitted > 0 {
+            write!(f, "\n  ... and {omitted} more")?;
+        }
+        Ok(())
+    }
+}
+
+impl std::error::Error for ValidationError {}
+
+async fn validate(
+    require_embeddings_or_documents: bool,
+    collection_entries: CollectionEntries<'_>,
+    embedding_function: Option<Box<dyn EmbeddingFunction>>,
+    embedding_concurrency: usize,
+    max_violations: usize,
+) -> Result<CollectionEntries<'_>> {
+    check_entries(
+        require_embeddings_or_documents,
+        &collection_entries,
+        embedding_function.is_some(),
+        max_violations,
+    )?;
+
+    let CollectionEntries {
+        ids,
+        mut embeddings,
+        metadatas,
+        documents,
+    } = collection_entries;
+
+    if let (None, Some(docs), Some(embedding_function)) =
+        (&embeddings, &documents, embedding_function)
+    {
+        let computed = embedding_function
+            .embed_parallel(docs, embedding_concurrency)
+            .await
+            .map_err(ChromaError::EmbeddingError)?;
+        let mut error = ValidationError::new(max_violations);
+        check_length(
+            &mut error,
+            max_violations,
+            "computed embeddings",
+            Some(computed.len()),
+            ids.len(),
+        );
+        error.into_result()?;
+        embeddings = Some(computed);
+    }
+
+    Ok(CollectionEntries {
+        ids,
+        metadatas,
+        documents,
+        embeddings,
+    })
+}
+
+/// Check the entries' structure, lengths and ids, reporting every violation at once.
+fn check_entries(
+    require_embeddings_or_documents: bool,
+    collection_entries: &CollectionEntries<'_>,
+    has_embedding_function: bool,
+    max_violations: usize,
+) -> Result<()> {
+    let CollectionEntries {
+        ids,
+        embeddings,
+        metadatas,
+        documents,
+    } = collection_entries;
+    let mut error = ValidationError::new(max_violations);
+
+    if require_embeddings_or_documents && embeddings.is_none() && documents.is_none() {
+        error.push(
+            max_violations,
+            None,
+            None,
+            ValidationRule::MissingEmbeddingsAndDocuments,
+            "Embeddings and documents cannot both be None",
+        );
+    }
+
+    if embeddings.is_none() && documents.is_some() && !has_embedding_function {
+        error.push(
+            max_violations,
+            None,
+            None,
+            ValidationRule::MissingEmbeddingFunction,
+            "embedding_function cannot be None if documents are provided and embeddings are None",
+        );
+    }
+
+    if embeddings.is_some() && has_embedding_function {
+        error.push(
+            max_violations,
+            None,
+            None,
+            ValidationRule::UnexpectedEmbeddingFunction,
+            "embedding_function should be None if embeddings are provided",
+        );
+    }
+
+    check_length(
+        &mut error,
+        max_violations,
+        "metadatas",
+        metadatas.as_ref().map(Vec::len),
+        ids.len(),
+    );
+    check_length(
+        &mut error,
+        max_violations,
+        "documents",
+        documents.as_ref().map(Vec::len),
+        ids.len(),
+    );
+    check_length(
+        &mut error,
+        max_violations,
+        "embeddings",
+        embeddings.as_ref().map(Vec::len),
+        ids.len(),
+    );
+
+    check_ids(&mut error, max_violations, ids);
+    if let Some(metadatas) = metadatas {
+        check_metadata_integers(&mut error, max_violations, ids, metadatas);
+    }
+    Ok(error.into_result()?)
+}
+
+/// Report the metadata values Chroma would not store faithfully, see [ValidationRule::IntegerOutOfRange].
+fn check_metadata_integers(
+    error: &mut ValidationError,
+    max_violations: usize,
+    ids: &[&str],
+    metadatas: &[Metadata],
+) {
+    for (index, metadata) in metadatas.iter().enumerate() {
+        for (key, value) in metadata {
+            if let Some(n) = find_out_of_range_integer(value) {
+                error.push(
+                    max_violations,
+                    Some(index),
+                    ids.get(index).copied(),
+                    ValidationRule::IntegerOutOfRange,
+                    format!("Metadata key {key:?} holds {n}, which is above the largest integer Chroma stores ({})", i64::MAX),
+                );
+            }
+        }
+    }
+}
+
+/// The first integer in the value, or nested in its arrays and objects, that does not fit the 64-bit signed integers Chroma stores.
+fn find_out_of_range_integer(value: &Value) -> Option<u64> {
+    match value {
+        Value::Number(n) => n.as_u64().filter(|&n| n > i64::MAX as u64),
+        Value::Array(values) => values.iter().find_map(find_out_of_range_integer),
+        Value::Object(map) => map.values().find_map(find_out_of_range_integer),
+        _ => None,
+    }
+}
+
+/// Report the empty and duplicate ids.
+fn check_ids(error: &mut ValidationError, max_violations: usize, ids: &[&str]) {
+    let mut first_seen: HashMap<&str, usize> = HashMap::with_capacity(ids.len());
+    for (index, id) in ids.iter().enumerate() {
+        if id.is_empty() {
+            error.push(
+                max_violations,
+                Some(index),
+                Some(id),
+                ValidationRule::EmptyId,
+                "Found empty string in IDs",
+            );
+        } else if let Some(first) = first_seen.get(id) {
+            error.push(
+                max_violations,
+                Some(index),
+                Some(id),
+                ValidationRule::DuplicateId,
+                format!("Expected IDs to be unique, duplicate of record {first}"),
+            );
+        } else {
+            first_seen.insert(id, index);
+        }
+    }
+}
+
+fn check_length(
+    error: &mut ValidationError,
+    max_violations: usize,
+    field: &str,
+    len: Option<usize>,
+    ids_len: usize,
+) {
+    match len {
+        Some(len) if len != ids_len => error.push(
+            max_violations,
+            None,
+            None,
+            ValidationRule::LengthMismatch,
+            format!("IDs, embeddings, metadatas, and documents must all be the same length: found {ids_len} IDs and {len} {field}"),
+        ),
+        _ => {}
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::{json, Value};
+
+    use std::sync::Arc;
+
+    use crate::{
+        collection::{
+            combine_weighted_hits, component_json, exclude_hits, next_fetch_size, split_entries,
+            validate, AddOptions, ChromaCollection, CollectionConfiguration, CollectionEntries,
+            Filter, GetOptions, GetResult, Hit, HnswConfiguration, Include, QueryOptions,
+            QueryResult, SortBy, UpsertOptions, ValidationRule, WhereDocumentFilter, WhereFilter,
+            DEFAULT_N_RESULTS,
+        },
+        commons::Metadata,
+        embeddings::MockEmbeddingProvider,
+        trace::{QueryLogger, QueryTrace},
+        warnings::{Warning, WarningCallback},
+        ChromaClient, ChromaError,
+    };
+
+    const TEST_COLLECTION: &str = "21-recipies-for-octopus";
+
+    #[tokio::test]
+    async fn test_modify_collection() {
+        let client = ChromaClient::new(Default::default());
+
+        let collection = client
+            .await
+            .unwrap()
+            .get_or_create_collection(TEST_COLLECTION, None)
+            .await
+            .unwrap();
+
+        //Test for setting invalid collection name. Should fail.
+        assert!(collection
+            .modify(Some("new name for test collection"), None)
+            .await
+            .is_err());
+
+        //Test for setting new metadata. Should pass.
+        assert!(collection
+            .modify(
+                None,
+                Some(
+                    json!({
+                        "test": "test"
+                    })
+                    .as_object()
+                    .unwrap()
+                )
+            )
+            .await
+            .is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_add_to_collection() {
+        let client = ChromaClient::new(Default::default());
+
+        let collection = client
+            .await
+            .unwrap()
+            .get_or_create_collection(TEST_COLLECTION, None)
+            .await
+            .unwrap();
+
+        let invalid_collection_entries = CollectionEntries {
+            ids: vec!["test1"],
+            metadatas: None,
+            documents: None,
+            embeddings: None,
+        };
+
+        let response = collection.add(
+            invalid_collection_entries,
+            Some(Box::new(MockEmbeddingProvider)),
+        );
+        assert!(
+            response.await.is_err(),
+            "Embeddings and documents cannot both be None"
+        );
+
+        let invalid_collection_entries = CollectionEntries {
+            ids: vec!["test"],
+            metadatas: None,
+            documents: Some(vec!["Document content 1", "Document content 2"]),
+            embeddings: None,
+        };
+        let response = collection.add(
+            invalid_collection_entries,
+            Some(Box::new(MockEmbeddingProvider)),
+        );
+        assert!(
+            response.await.is_err(),
+            "IDs, embeddings, metadatas, and documents must all be the same length"
+        );
+
+        let valid_collection_entries = CollectionEntries {
+            ids: vec!["test1", "test2"],
+            metadatas: None,
+            documents: Some(vec!["Document content 1", "Document content 2"]),
+            embeddings: None,
+        };
+        let response = collection.add(
+            valid_collection_entries,
+            Some(Box::new(MockEmbeddingProvider)),
+        );
+        assert!(
+            response.await.is_ok(),
+            "IDs, embeddings, metadatas, and documents must all be the same length"
+        );
+
+        let invalid_collection_entries = CollectionEntries {
+            ids: vec!["test1", ""],
+            metadatas: None,
+            documents: Some(vec!["Document content 1", "Document content 2"]),
+            embeddings: None,
+        };
+        let response = collection.add(
+            invalid_collection_entries,
+            Some(Box::new(MockEmbeddingProvider)),
+        );
+        assert!(response.await.is_err(), "Empty IDs not allowed");
+
+        let invalid_collection_entries = CollectionEntries {
+            ids: vec!["test", "test"],
+            metadatas: None,
+            documents: Some(vec!["Document content 1", "Document content 2"]),
+            embeddings: Some(vec![vec![1.0, 2.0], vec![3.0, 4.0]]),
+        };
+        let response = collection.add(invalid_collection_entries, None);
+        assert!(
+            response.await.is_err(),
+            "Expected IDs to be unique. Duplicates not allowed"
+        );
+
+        let collection_entries = CollectionEntries {
+            ids: vec!["test1", "test2"],
+            metadatas: None,
+            documents: Some(vec!["Document content 1", "Document content 2"]),
+            embeddings: None,
+        };
+        let response = collection.add(collection_entries, None);
+        assert!(
+            response.await.is_err(),
+            "embedding_function cannot be None if documents are provided and embeddings are None"
+        );
+
+        let collection_entries = CollectionEntries {
+            ids: vec!["test1", "test2"],
+            metadatas: None,
+            documents: Some(vec!["Document content 1", "Document content 2"]),
+            embeddings: None,
+        };
+        let response = collection.add(collection_entries, Some(Box::new(MockEmbeddingProvider)));
+        assert!(
+            response.await.is_ok(),
+            "Embeddings are computed by the embedding_function if embeddings are None and documents are provided"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_upsert_collection() {
+        let client = ChromaClient::new(Default::default());
+
+        let collection = client
+            .await
+            .unwrap()
+            .get_or_create_collection(TEST_COLLECTION, None)
+            .await
+            .unwrap();
+
+        let invalid_collection_entries = CollectionEntries {
+            ids: vec!["test1"],
+            metadatas: None,
+            documents: None,
+            embeddings: None,
+        };
+
+        let response = collection.upsert(
+            invalid_collection_entries,
+            Some(Box::new(MockEmbeddingProvider)),
+        );
+        assert!(
+            response.await.is_err(),
+            "Embeddings and documents cannot both be None"
+        );
+
+        let invalid_collection_entries = CollectionEntries {
+            ids: vec!["test"],
+            metadatas: None,
+            documents: Some(vec!["Document content 1", "Document content 2"]),
+            embeddings: None,
+        };
+        let response = collection.upsert(
+            invalid_collection_entries,
+            Some(Box::new(MockEmbeddingProvider)),
+        );
+        assert!(
+            response.await.is_err(),
+            "IDs, embeddings, metadatas, and documents must all be the same length"
+        );
+
+        let valid_collection_entries = CollectionEntries {
+            ids: vec!["test1", "test2"],
+            metadatas: None,
+            documents: Some(vec!["Document content 1", "Document content 2"]),
+            embeddings: None,
+        };
+        let response = collection.upsert(
+            valid_collection_entries,
+            Some(Box::new(MockEmbeddingProvider)),
+        );
+        assert!(
+            response.await.is_ok(),
+            "IDs, embeddings, metadatas, and documents must all be the same length"
+        );
+
+        let invalid_collection_entries = CollectionEntries {
+            ids: vec!["test1", ""],
+            metadatas: None,
+            documents: Some(vec!["Document content 1", "Document content 2"]),
+            embeddings: None,
+        };
+        let response = collection.upsert(
+            invalid_collection_entries,
+            Some(Box::new(MockEmbeddingProvider)),
+        );
+        assert!(response.await.is_err(), "Empty IDs not allowed");
+
+        let invalid_collection_entries = CollectionEntries {
+            ids: vec!["test", "test"],
+            metadatas: None,
+            documents: Some(vec!["Document content 1", "Document content 2"]),
+            embeddings: Some(vec![vec![1.0, 2.0], vec![3.0, 4.0]]),
+        };
+        let response = collection.upsert(invalid_collection_entries, None);
+        assert!(
+            response.await.is_err(),
+            "Expected IDs to be unique. Duplicates not allowed"
+        );
+
+        let collection_entries = CollectionEntries {
+            ids: vec!["test1", "test2"],
+            metadatas: None,
+            documents: Some(vec!["Document content 1", "Document content 2"]),
+            embeddings: None,
+        };
+        let response = collection.upsert(collection_entries, None);
+        assert!(
+            response.await.is_err(),
+            "embedding_function cannot be None if documents are provided and embeddings are None"
+        );
+
+        let collection_entries = CollectionEntries {
+            ids: vec!["test1", "test2"],
+            metadatas: None,
+            documents: Some(vec!["Document content 1", "Document content 2"]),
+            embeddings: None,
+        };
+        let response = collection.upsert(collection_entries, Some(Box::new(MockEmbeddingProvider)));
+        assert!(
+            response.await.is_ok(),
+            "Embeddings are computed by the embedding_function if embeddings are None and documents are provided"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_get_all_embeddings_from_collection() {
+        let client = ChromaClient::new(Default::default());
+
+        let collection = client
+            .await
+            .unwrap()
+            .get_or_create_collection(TEST_COLLECTION, None)
+            .await
+            .unwrap();
+
+        let get_all_query = GetOptions {
+            ids: vec![],
+            where_metadata: None,
+            limit: None,
+            offset: None,
+            where_document: None,
+            include: None,
+            sort: None,
+        };
+        let get_all_result = collection.get(get_all_query).await.unwrap();
+
+        assert_eq!(get_all_result.ids.len(), collection.count().await.unwrap());
+    }
+
+    #[tokio::test]
+    async fn test_update_collection() {
+        let client = ChromaClient::new(Default::default());
+
+        let collection = client
+            .await
+            .unwrap()
+            .get_or_create_collection(TEST_COLLECTION, None)
+            .await
+            .unwrap();
+
+        let valid_collection_entries = CollectionEntries {
+            ids: vec!["test1"],
+            metadatas: None,
+            documents: None,
+            embeddings: None,
+        };
+
+        let response = collection
+            .update(
+                valid_collection_entries,
+                Some(Box::new(MockEmbeddingProvider)),
+            )
+            .await;
+
+        println!("{:?}", response);
+
+        assert!(
+            response.is_ok(),
+            "Embeddings and documents can both be None"
+        );
+
+        let invalid_collection_entries = CollectionEntries {
+            ids: vec!["test"],
+            metadatas: None,
+            documents: Some(vec!["Document content 1", "Document content 2"]),
+            embeddings: None,
+        };
+        let response = collection.update(
+            invalid_collection_entries,
+            Some(Box::new(MockEmbeddingProvider)),
+        );
+        assert!(
+            response.await.is_err(),
+            "IDs, embeddings, metadatas, and documents must all be the same length"
+        );
+
+        let valid_collection_entries = CollectionEntries {
+            ids: vec!["test1", "test2"],
+            metadatas: None,
+            documents: Some(vec!["Document content 1", "Document content 2"]),
+            embeddings: None,
+        };
+        let response = collection.update(
+            valid_collection_entries,
+            Some(Box::new(MockEmbeddingProvider)),
+        );
+        assert!(
+            response.await.is_ok(),
+            "IDs, embeddings, metadatas, and documents must all be the same length"
+        );
+
+        let invalid_collection_entries = CollectionEntries {
+            ids: vec!["test1", ""],
+            metadatas: None,
+            documents: Some(vec!["Document content 1", "Document content 2"]),
+            embeddings: None,
+        };
+        let response = collection.update(
+            invalid_collection_entries,
+            Some(Box::new(MockEmbeddingProvider)),
+        );
+        assert!(response.await.is_err(), "Empty IDs not allowed");
+
+        let invalid_collection_entries = CollectionEntries {
+            ids: vec!["test", "test"],
+            metadatas: None,
+            documents: Some(vec!["Document content 1", "Document content 2"]),
+            embeddings: Some(vec![vec![1.0, 2.0], vec![3.0, 4.0]]),
+        };
+        let response = collection.update(invalid_collection_entries, None);
+        assert!(
+            response.await.is_err(),
+            "Expected IDs to be unique. Duplicates not allowed"
+        );
+
+        let collection_entries = CollectionEntries {
+            ids: vec!["test1", "test2"],
+            metadatas: None,
+            documents: Some(vec!["Document content 1", "Document content 2"]),
+            embeddings: None,
+        };
+        let response = collection.update(collection_entries, None);
+        assert!(
+            response.await.is_err(),
+            "embedding_function cannot be None if documents are provided and embeddings are None"
+        );
+
+        let collection_entries = CollectionEntries {
+            ids: vec!["test1", "test2"],
+            metadatas: None,
+            documents: Some(vec!["Document content 1", "Document content 2"]),
+            embeddings: None,
+        };
+        let response = collection.update(collection_entries, Some(Box::new(MockEmbeddingProvider)));
+        assert!(
+            response.await.is_ok(),
+            "Embeddings are computed by the embedding_function if embeddings are None and documents are provided"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_query_collection() {
+        let client = ChromaClient::new(Default::default());
+
+        let collection = client
+            .await
+            .unwrap()
+            .get_or_create_collection(TEST_COLLECTION, None)
+            .await
+            .unwrap();
+        assert!(collection.count().await.is_ok());
+
+        let query = QueryOptions {
+            query_texts: None,
+            query_embeddings: None,
+            where_metadata: None,
+            where_document: None,
+            n_results: None,
+            include: None,
+            exclude_ids: None,
+            exclude_overfetch: None,
+            timeout: None,
+        };
+        let query_result = collection.query(query, None);
+        assert!(
+            query_result.await.is_err(),
+            "query_texts and query_embeddings cannot both be None"
+        );
+
+        let query = QueryOptions {
+            query_texts: Some(vec![
+                "Writing tests help me find bugs",
+                "Running them does not",
+            ]),
+            query_embeddings: None,
+            where_metadata: None,
+            where_document: None,
+            n_results: None,
+            include: None,
+            exclude_ids: None,
+            exclude_overfetch: None,
+            timeout: None,
+        };
+        let query_result = collection.query(query, Some(Box::new(MockEmbeddingProvider)));
+        assert!(
+            query_result.await.is_ok(),
+            "query_embeddings will be computed from query_texts if embedding_function is provided"
+        );
+
+        let query = QueryOptions {
+            query_texts: Some(vec![
+                "Writing tests help me find bugs",
+                "Running them does not",
+            ]),
+            query_embeddings: Some(vec![vec![0.0_f32; 768], vec![0.0_f32; 768]]),
+            where_metadata: None,
+            where_document: None,
+            n_results: None,
+            include: None,
+            exclude_ids: None,
+            exclude_overfetch: None,
+            timeout: None,
+        };
+        let query_result = collection.query(query, Some(Box::new(MockEmbeddingProvider)));
+        assert!(
+            query_result.await.is_err(),
+            "Both query_embeddings and query_texts cannot be provided"
+        );
+
+        let query = QueryOptions {
+            query_texts: None,
+            query_embeddings: Some(vec![vec![0.0_f32; 768], vec![0.0_f32; 768]]),
+            where_metadata: None,
+            where_document: None,
+            n_results: None,
+            include: None,
+            exclude_ids: None,
+            exclude_overfetch: None,
+            timeout: None,
+        };
+        let query_result = collection.query(query, None);
+        assert!(
+            query_result.await.is_ok(),
+            "Use provided query_embeddings if embedding_function is None"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_delete_from_collection() {
+        let client = ChromaClient::new(Default::default());
+
+        let collection = client
+            .await
+            .unwrap()
+            .get_or_create_collection(TEST_COLLECTION, None)
+            .await
+            .unwrap();
+
+        let valid_collection_entries = CollectionEntries {
+            ids: vec!["123ABC"],
+            metadatas: None,
+            documents: Some(vec!["Document content 1"]),
+            embeddings: None,
+        };
+
+        let response = collection.add(
+            valid_collection_entries,
+            Some(Box::new(MockEmbeddingProvider)),
+        );
+        assert!(response.await.is_ok());
+
+        let response = collection.delete(Some(vec!["123ABC"]), None, None).await;
+
+        assert!(response.is_ok(),);
+    }
+
+    #[tokio::test]
+    async fn test_similarity_search() {
+        let client = ChromaClient::new(Default::default());
+
+        let collection = client
+            .await
+            .unwrap()
+            .get_or_create_collection(TEST_COLLECTION, None)
+            .await
+            .unwrap();
+
+        let response = collection.similarity_search("octopus", 1, None, None).await;
+        assert!(
+            response.is_err(),
+            "An embedding function must be attached to use similarity_search"
+        );
+
+        let collection = collection.with_embedding_function(Arc::new(MockEmbeddingProvider));
+        let collection_entries = CollectionEntries {
+            ids: vec!["test1", "test2"],
+            metadatas: None,
+            documents: Some(vec!["Document content 1", "Document content 2"]),
+            embeddings: None,
+        };
+        collection
+            .upsert(collection_entries, Some(Box::new(MockEmbeddingProvider)))
+            .await
+            .unwrap();
+
+        let hits = collection
+            .similarity_search("Document content", 2, None, None)
+            .await
+            .unwrap();
+        assert_eq!(hits.len(), 2);
+        assert!(hits
+            .iter()
+            .all(|hit| hit.document.is_some() && hit.distance.is_some()));
+    }
+
+    #[test]
+    fn test_query_result_into_hits() {
+        let query_result = QueryResult {
+            ids: vec![vec!["a".into(), "b".into()], vec!["c".into()]],
+            metadatas: Some(vec![
+                vec![Some(json!({"k": 1}).as_object().unwrap().clone()), None],
+                vec![None],
+            ]),
+            documents: None,
+            embeddings: None,
+            distances: Some(vec![vec![0.1, 0.2], vec![0.3]]),
+        };
+
+        let hits = query_result.into_hits();
+        assert_eq!(
+            hits,
+            vec![
+                vec![
+                    Hit {
+                        id: "a".into(),
+                        document: None,
+                        metadata: Some(json!({"k": 1}).as_object().unwrap().clone()),
+                        distance: Some(0.1),
+                    },
+                    Hit {
+                        id: "b".into(),
+                        document: None,
+                        metadata: None,
+                        distance: Some(0.2),
+                    },
+                ],
+                vec![Hit {
+                    id: "c".into(),
+                    document: None,
+                    metadata: None,
+                    distance: Some(0.3),
+                }],
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_add_texts() {
+        let client = ChromaClient::new(Default::default());
+
+        let collection = client
+            .await
+            .unwrap()
+            .get_or_create_collection(TEST_COLLECTION, None)
+            .await
+            .unwrap();
+
+        let response = collection
+            .add_texts(vec!["Document content 1"], None, None)
+            .await;
+        assert!(
+            response.is_err(),
+            "An embedding function must be provided or attached"
+        );
+
+        let ids = collection
+            .add_texts(
+                vec!["Document content 1", "Document content 2"],
+                None,
+                Some(Box::new(MockEmbeddingProvider)),
+            )
+            .await
+            .unwrap();
+        assert_eq!(ids.len(), 2);
+        assert_ne!(ids[0], ids[1]);
+
+        let get_result = collection
+            .get(GetOptions {
+                ids: ids.clone(),
+                ..Default::default()
+            })
+            .await
+            .unwrap();
+        assert_eq!(get_result.ids.len(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_add_texts_chunked() {
+        use wiremock::matchers::{method, path_regex};
+        use wiremock::{Mock, MockServer, ResponseTemplate};
+
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path_regex("/upsert$"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!(true)))
+            .expect(3)
+            .mount(&server)
+            .await;
+        let collection = mock_collection(&server, None);
+
+        let ids = collection
+            .add_texts(
+                vec!["text"; 250],
+                None,
+                Some(Box::new(MockEmbeddingProvider)),
+            )
+            .await
+            .unwrap();
+        assert_eq!(ids.len(), 250);
+        let sizes: Vec<usize> = server
+            .received_requests()
+            .await
+            .unwrap()
+            .iter()
+            .map(|request| {
+                request.body_json::<Value>().unwrap()["ids"]
+                    .as_array()
+                    .unwrap()
+                    .len()
+            })
+            .collect();
+        assert_eq!(sizes, [100, 100, 50]);
+    }
+
+    fn weighted_test_hit(id: &str, distance: f32) -> Hit {
+        Hit {
+            id: id.into(),
+            document: Some(format!("document {id}")),
+            metadata: None,
+            distance: Some(distance),
+        }
+    }
+
+    fn assert_ranking(hits: &[Hit], expected: &[(&str, f32)]) {
+        assert_eq!(hits.len(), expected.len());
+        for (hit, (id, distance)) in hits.iter().zip(expected) {
+            assert_eq!(hit.id, *id);
+            assert!((hit.distance.unwrap() - distance).abs() < 1e-6);
+            assert_eq!(hit.document, Some(format!("document {id}")));
+        }
+    }
+
+    #[test]
+    fn test_combine_weighted_hits() {
+        let hit_lists = || {
+            vec![
+                vec![weighted_test_hit("a", 0.1), weighted_test_hit("b", 0.4)],
+                vec![weighted_test_hit("b", 0.2), weighted_test_hit("c", 0.5)],
+            ]
+        };
+
+        // a = 0.7 * 0.1 + 0.3 * 1.0, b = 0.7 * 0.4 + 0.3 * 0.2, c = 0.7 * 1.0 + 0.3 * 0.5
+        let hits = combine_weighted_hits(hit_lists(), &[0.7, 0.3], Some(1.0), 3);
+        assert_ranking(&hits, &[("b", 0.34), ("a", 0.37), ("c", 0.85)]);
+
+        // Missing distances default to the furthest hit of each list: 0.4 and 0.5.
+        // a = 0.7 * 0.1 + 0.3 * 0.5, b = 0.7 * 0.4 + 0.3 * 0.2, c = 0.7 * 0.4 + 0.3 * 0.5
+        let hits = combine_weighted_hits(hit_lists(), &[0.7, 0.3], None, 2);
+        assert_ranking(&hits, &[("a", 0.22), ("b", 0.34)]);
+
+        // Ties are ranked by id.
+        let hits = combine_weighted_hits(
+            vec![vec![
+                weighted_test_hit("z", 0.5),
+                weighted_test_hit("y", 0.5),
+            ]],
+            &[1.0],
+            None,
+            2,
+        );
+        assert_ranking(&hits, &[("y", 0.5), ("z", 0.5)]);
+
+        // With inner product distances, all negative, missing distances default to the furthest hits: -0.4 and -0.3.
+        // a = 0.5 * -0.9 + 0.5 * -0.3, b = 0.5 * -0.4 + 0.5 * -0.7, c = 0.5 * -0.4 + 0.5 * -0.3
+        let hits = combine_weighted_hits(
+            vec![
+                vec![weighted_test_hit("a", -0.9), weighted_test_hit("b", -0.4)],
+                vec![weighted_test_hit("b", -0.7), weighted_test_hit("c", -0.3)],
+            ],
+            &[0.5, 0.5],
+            None,
+            3,
+        );
+        assert_ranking(&hits, &[("a", -0.6), ("b", -0.55), ("c", -0.35)]);
+    }
+
+    #[tokio::test]
+    async fn test_validate_reports_all_violations() {
+        let collection_entries = CollectionEntries {
+            ids: vec!["a", "", "b", "a", "b"],
+            metadatas: Some(vec![Default::default(); 4]),
+            documents: None,
+            embeddings: Some(vec![vec![1.0]; 5]),
+        };
+        let error = validate(
+            true,
+            collection_entries,
+            Some(Box::new(MockEmbeddingProvider)),
+            1,
+            100,
+        )
+        .await
+        .unwrap_err();
+        let ChromaError::Validation(error) = error else {
+            panic!("Expected a validation error, got {error:?}");
+        };
+
+        let rules: Vec<_> = error.violations.iter().map(|v| (v.index, v.rule)).collect();
+        assert_eq!(
+            rules,
+            vec![
+                (None, ValidationRule::UnexpectedEmbeddingFunction),
+                (None, ValidationRule::LengthMismatch),
+                (Some(1), ValidationRule::EmptyId),
+                (Some(3), ValidationRule::DuplicateId),
+                (Some(4), ValidationRule::DuplicateId),
+            ]
+        );
+        assert_eq!(error.total, 5);
+        assert_eq!(error.violations[3].id.as_deref(), Some("a"));
+
+        let message = error.to_string();
+        assert!(message.starts_with("5 validation error(s)"));
+        assert!(message.contains("record 3 (id \"a\")"));
+    }
+
+    #[test]
+    fn test_filter_sections() {
+        let filter = Filter::new()
+            .metadata(WhereFilter::and([
+                WhereFilter::field("year").gte(2020),
+                WhereFilter::field("kind").in_(["recipe", "story"]),
+            ]))
+            .unwrap()
+            .document(WhereDocumentFilter::or([
+                WhereDocumentFilter::contains("octopus"),
+                WhereDocumentFilter::regex("squid+"),
+            ]))
+            .unwrap();
+        let get_options = GetOptions::builder().filter(filter.clone()).build();
+        assert_eq!(get_options.where_metadata.as_ref(), filter.where_metadata());
+        assert_eq!(get_options.where_document.as_ref(), filter.where_document());
+
+        for misplaced in [
+            json!({"$contains": "octopus"}),
+            json!({"title": {"$contains": "octopus"}}),
+            json!({"$and": [{"year": {"$eq": 2020}}, {"$not_contains": "squid"}]}),
+            json!({"year": {"$like": 2020}}),
+            json!({"$and": {"year": 2020}}),
+            json!("octopus"),
+        ] {
+            assert!(
+                matches!(
+                    Filter::new().metadata(misplaced.clone()),
+                    Err(ChromaError::InvalidArgument { .. })
+                ),
+                "{misplaced}"
+            );
+        }
+        for misplaced in [
+            json!({"year": {"$eq": 2020}}),
+            json!({"$eq": "octopus"}),
+            json!({"$or": [{"$contains": "octopus"}, {"$gte": 2020}]}),
+        ] {
+            assert!(
+                matches!(
+                    Filter::new().document(misplaced.clone()),
+                    Err(ChromaError::InvalidArgument { .. })
+                ),
+                "{misplaced}"
+            );
+        }
+        let Err(ChromaError::InvalidArgument { message }) =
+            Filter::new().metadata(json!({"$contains": "octopus"}))
+        else {
+            panic!("Expected an invalid argument error");
+        };
+        assert!(
+            message.contains("belongs in the document section"),
+            "{message}"
+        );
+    }
+
+    #[test]
+    fn test_collection_configuration() {
+        let parse = |configuration_json: Value| {
+            CollectionConfiguration::try_from(configuration_json.as_object().unwrap().clone())
+        };
+        let expected = HnswConfiguration {
+            space: Some("cosine".to_string()),
+            ef_construction: Some(100),
+            max_neighbors: Some(16),
+            ..Default::default()
+        };
+
+        let configuration = parse(json!({
+            "hnsw": {"space": "cosine", "ef_construction": 100, "max_neighbors": 16},
+            "spann": null,
+        }))
+        .unwrap();
+        assert_eq!(configuration.hnsw.as_ref(), Some(&expected));
+        let legacy = parse(json!({
+            "hnsw_configuration": {"space": "cosine", "ef_construction": 100, "M": 16, "_type": "HNSWConfigurationInternal"},
+        }))
+        .unwrap();
+        assert_eq!(legacy.hnsw.as_ref(), Some(&expected));
+        let flat =
+            parse(json!({"hnsw:space": "cosine", "hnsw:construction_ef": 100, "hnsw:M": 16}))
+                .unwrap();
+        assert_eq!(flat.hnsw.as_ref(), Some(&expected));
+
+        assert_eq!(parse(json!({"hnsw": null})).unwrap().hnsw, None);
+        assert!(parse(json!({"hnsw": {"ef_search": "high"}})).is_err());
+        assert_eq!(
+            json!(configuration),
+            json!({"hnsw": {"space": "cosine", "ef_construction": 100, "max_neighbors": 16}})
+        );
+    }
+
+    #[test]
+    fn test_collection_entries_builder() {
+        let metadata: Metadata = json!({"kind": "recipe"}).as_object().unwrap().clone();
+        let collection_entries = CollectionEntries::builder()
+            .add_entry("a", None, Some(metadata.clone()), Some("first"))
+            .add_entry("b", None, Some(metadata.clone()), Some("second"))
+            .build()
+            .unwrap();
+        assert_eq!(collection_entries.ids, ["a", "b"]);
+        assert!(collection_entries.embeddings.is_none());
+        assert_eq!(collection_entries.metadatas.unwrap().len(), 2);
+        assert_eq!(collection_entries.documents.unwrap(), ["first", "second"]);
+
+        let error = CollectionEntries::builder()
+            .add_entry("a", Some(vec![1.0]), None, None)
+            .add_entry("", Some(vec![1.0]), None, None)
+            .add_entry("a", None, Some(metadata), None)
+            .build()
+            .unwrap_err();
+        let ChromaError::Validation(error) = error else {
+            panic!("Expected a validation error, got {error:?}");
+        };
+        let rules: Vec<_> = error.violations.iter().map(|v| (v.index, v.rule)).collect();
+        assert_eq!(
+            rules,
+            vec![
+                (Some(1), ValidationRule::EmptyId),
+                (Some(2), ValidationRule::DuplicateId),
+                (Some(2), ValidationRule::LengthMismatch),
+                (Some(0), ValidationRule::LengthMismatch),
+                (Some(1), ValidationRule::LengthMismatch),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_collection_entries_generated_ids() {
+        let mut ids = vec!["stale".to_string()];
+        let collection_entries = CollectionEntries::with_generated_ids(
+            &mut ids,
+            Some(vec![vec![1.0], vec![2.0]]),
+            None,
+            None,
+        );
+        assert_eq!(collection_entries.ids.len(), 2);
+        assert_ne!(collection_entries.ids[0], collection_entries.ids[1]);
+        assert!(uuid::Uuid::parse_str(collection_entries.ids[0]).is_ok());
+        drop(collection_entries);
+        assert_eq!(ids.len(), 2);
+
+        let mut ids = vec![];
+        let collection_entries = CollectionEntries::builder()
+            .generate_ids(&mut ids)
+            .add_entry("a", None, None, Some("first"))
+            .add_generated_entry(None, None, Some("second"))
+            .build()
+            .unwrap();
+        assert_eq!(collection_entries.ids[0], "a");
+        assert!(uuid::Uuid::parse_str(collection_entries.ids[1]).is_ok());
+        drop(collection_entries);
+        assert_eq!(ids.len(), 1);
+
+        let error = CollectionEntries::builder()
+            .add_generated_entry(None, None, Some("first"))
+            .build()
+            .unwrap_err();
+        assert!(matches!(error, ChromaError::InvalidArgument { .. }));
+    }
+
+    #[test]
+    fn test_collection_entries_iter() {
+        let metadata: Metadata = json!({"kind": "recipe"}).as_object().unwrap().clone();
+        let collection_entries: CollectionEntries = vec![
+            ("a", vec![1.0], Some(metadata.clone()), None),
+            ("b", vec![2.0], None, Some("second")),
+        ]
+        .into_iter()
+        .collect();
+        assert_eq!(collection_entries.ids, ["a", "b"]);
+        assert_eq!(
+            collection_entries.embeddings.as_deref(),
+            Some(&[vec![1.0], vec![2.0]][..])
+        );
+        assert_eq!(
+            collection_entries.metadatas.as_deref(),
+            Some(&[metadata.clone(), Metadata::new()][..])
+        );
+        assert_eq!(
+            collection_entries.documents.as_deref(),
+            Some(&["", "second"][..])
+        );
+
+        let records: Vec<_> = CollectionEntries {
+            ids: vec!["a", "b"],
+            embeddings: None,
+            metadatas: Some(vec![metadata.clone(), Metadata::new()]),
+            documents: Some(vec!["first", "second"]),
+        }
+        .into_iter()
+        .collect();
+        assert_eq!(
+            records,
+            vec![
+                ("a", None, Some(metadata), Some("first")),
+                ("b", None, Some(Metadata::new()), Some("second")),
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_validate_caps_violations() {
+        let collection_entries = CollectionEntries {
+            ids: vec![""; 10],
+            metadatas: None,
+            documents: None,
+            embeddings: Some(vec![vec![1.0]; 10]),
+        };
+        let error = validate(true, collection_entries, None, 1, 3)
+            .await
+            .unwrap_err();
+        let ChromaError::Validation(error) = error else {
+            panic!("Expected a validation error, got {error:?}");
+        };
+
+        assert_eq!(error.violations.len(), 3);
+        assert_eq!(error.total, 10);
+        assert!(error.to_string().ends_with("... and 7 more"));
+    }
+
+    #[test]
+    fn test_exclude_hits() {
+        let hits = vec![
+            weighted_test_hit("a", 0.0),
+            weighted_test_hit("b", 0.1),
+            weighted_test_hit("c", 0.2),
+        ];
+        assert_ranking(
+            &exclude_hits(hits.clone(), &["a"], 2),
+            &[("b", 0.1), ("c", 0.2)],
+        );
+        assert_ranking(&exclude_hits(hits.clone(), &["b"], 1), &[("a", 0.0)]);
+        assert_ranking(
+            &exclude_hits(hits, &["z"], 5),
+            &[("a", 0.0), ("b", 0.1), ("c", 0.2)],
+        );
+    }
+
+    #[tokio::test]
+    async fn test_query_similar_to() {
+        let client = ChromaClient::new(Default::default());
+
+        let collection = client
+            .await
+            .unwrap()
+            .get_or_create_collection(TEST_COLLECTION, None)
+            .await
+            .unwrap();
+
+        let collection_entries = CollectionEntries {
+            ids: vec!["test1", "test2", "test3"],
+            metadatas: None,
+            documents: None,
+            embeddings: Some(vec![vec![0.0_f32; 768]; 3]),
+        };
+        collection.upsert(collection_entries, None).await.unwrap();
+
+        let hits = collection.query_similar_to("test1", 2, None).await.unwrap();
+        assert_eq!(hits.len(), 2);
+        assert!(hits.iter().all(|hit| hit.id != "test1"));
+
+        assert!(collection
+            .query_similar_to("missing-id", 2, None)
+            .await
+            .is_err());
+    }
+
+    #[test]
+    fn test_query_options_builder() {
+        let query = QueryOptions::builder()
+            .query_embeddings(vec![vec![0.0_f32; 768]])
+            .n_results(5)
+            .build()
+            .unwrap();
+        assert_eq!(query.query_embeddings, Some(vec![vec![0.0_f32; 768]]));
+        assert_eq!(query.n_results, Some(5));
+        assert!(query.query_texts.is_none());
+        assert!(query.where_metadata.is_none());
+
+        let query = QueryOptions::builder()
+            .query_texts(vec!["octopus"])
+            .where_document(json!({"$contains": "octopus"}))
+            .include(vec![Include::Documents])
+            .build()
+            .unwrap();
+        assert_eq!(query.query_texts, Some(vec!["octopus"]));
+        assert_eq!(query.include, Some(vec![Include::Documents]));
+
+        assert!(matches!(
+            QueryOptions::builder().n_results(5).build(),
+            Err(ChromaError::InvalidArgument { .. })
+        ));
+        assert!(matches!(
+            QueryOptions::builder()
+                .query_texts(vec!["octopus"])
+                .query_embeddings(vec![vec![0.0_f32; 768]])
+                .build(),
+            Err(ChromaError::InvalidArgument { .. })
+        ));
+    }
+
+    #[test]
+    fn test_include_serialization() {
+        assert_eq!(
+            serde_json::to_value(vec![
+                Include::Documents,
+                Include::Embeddings,
+                Include::Metadatas,
+                Include::Distances,
+                Include::Uris,
+            ])
+            .unwrap(),
+            json!(["documents", "embeddings", "metadatas", "distances", "uris"])
+        );
+        let options = GetOptions {
+            include: Some(vec![Include::Metadatas]),
+            ..Default::default()
+        };
+        assert_eq!(
+            serde_json::to_value(&options).unwrap()["include"],
+            json!(["metadatas"])
+        );
+    }
+
+    #[test]
+    fn test_get_options_builder() {
+        let get_query = GetOptions::builder()
+            .ids(vec!["test1".into()])
+            .limit(1)
+            .offset(2)
+            .where_metadata(json!({"color": "red"}))
+            .build();
+        assert_eq!(get_query.ids, vec!["test1".to_string()]);
+        assert_eq!(get_query.limit, Some(1));
+        assert_eq!(get_query.offset, Some(2));
+        assert_eq!(get_query.where_metadata, Some(json!({"color": "red"})));
+        assert!(get_query.include.is_none());
+    }
+
+    #[test]
+    fn test_next_fetch_size() {
+        // The first page triggers a fetch of exactly one page.
+        assert_eq!(next_fetch_size(0, 10, false), Some(10));
+        // Later pages double what was fetched.
+        assert_eq!(next_fetch_size(10, 20, false), Some(20));
+        assert_eq!(next_fetch_size(20, 30, false), Some(40));
+        // Pages within what was fetched are served from the cache.
+        assert_eq!(next_fetch_size(40, 40, false), None);
+        assert_eq!(next_fetch_size(40, 30, false), None);
+        // Large pages fetch at least up to their end.
+        assert_eq!(next_fetch_size(10, 50, false), Some(50));
+        // Once the server returned fewer hits than asked for, never fetch again.
+        assert_eq!(next_fetch_size(40, 50, true), None);
     }
 
-    let unique_ids: HashSet<_> = ids.iter().collect();
-    if unique_ids.len() != ids.len() {
-        let duplicate_ids: Vec<_> = ids
-            .iter()
-            .filter(|id| ids.iter().filter(|x| x == id).count() > 1)
-            .collect();
-        bail!(
-            "Expected IDs to be unique, found duplicates for: {:?}",
-            duplicate_ids
+    #[tokio::test]
+    async fn test_query_paged() {
+        let client = ChromaClient::new(Default::default());
+
+        let collection = client
+            .await
+            .unwrap()
+            .get_or_create_collection("paged-recipes-for-octopus", None)
+            .await
+            .unwrap();
+
+        let ids: Vec<String> = (0..7).map(|i| format!("paged{i}")).collect();
+        let collection_entries = CollectionEntries {
+            ids: ids.iter().map(String::as_str).collect(),
+            metadatas: None,
+            documents: None,
+            embeddings: Some((0..7).map(|i| vec![i as f32; 768]).collect()),
+        };
+        collection.upsert(collection_entries, None).await.unwrap();
+
+        let query = QueryOptions::builder()
+            .query_embeddings(vec![vec![0.0_f32; 768]])
+            .build()
+            .unwrap();
+        let mut pager = collection.query_paged(query, 3).await.unwrap();
+        let mut pages = vec![];
+        while let Some(page) = pager.next_page().await.unwrap() {
+            pages.push(page.into_iter().map(|hit| hit.id).collect::<Vec<_>>());
+        }
+        assert_eq!(
+            pages,
+            vec![&ids[0..3], &ids[3..6], &ids[6..7]]
+                .into_iter()
+                .map(|page| page.to_vec())
+                .collect::<Vec<_>>()
         );
     }
-    Ok(CollectionEntries {
-        ids,
-        metadatas,
-        documents,
-        embeddings,
-    })
-}
 
-#[cfg(test)]
-mod tests {
-    use serde_json::json;
+    #[test]
+    fn test_split_entries() {
+        let collection_entries = CollectionEntries {
+            ids: vec!["a", "b", "c", "d", "e"],
+            metadatas: None,
+            documents: Some(vec!["doc a", "doc b", "doc c", "doc d", "doc e"]),
+            embeddings: Some((0..5).map(|i| vec![i as f32]).collect()),
+        };
+        let chunks = split_entries(collection_entries, 2, None, None);
+        assert_eq!(chunks.len(), 3);
+        assert_eq!(chunks[0].ids, vec!["a", "b"]);
+        assert_eq!(chunks[2].ids, vec!["e"]);
+        for chunk in &chunks {
+            assert!(chunk.metadatas.is_none());
+            let documents = chunk.documents.as_ref().unwrap();
+            let embeddings = chunk.embeddings.as_ref().unwrap();
+            for (i, id) in chunk.ids.iter().enumerate() {
+                assert_eq!(documents[i], format!("doc {id}"));
+                let position = (id.as_bytes()[0] - b'a') as f32;
+                assert_eq!(embeddings[i], vec![position]);
+            }
+        }
 
-    use crate::{
-        collection::{CollectionEntries, GetOptions, QueryOptions},
-        embeddings::MockEmbeddingProvider,
-        ChromaClient,
-    };
+        assert!(split_entries(CollectionEntries::default(), 2, None, None).is_empty());
+    }
 
-    const TEST_COLLECTION: &str = "21-recipies-for-octopus";
+    /// A collection handle talking to a mock server.
+    fn mock_collection(
+        server: &wiremock::MockServer,
+        warning_callback: Option<WarningCallback>,
+    ) -> ChromaCollection {
+        ChromaCollection::mock(
+            crate::api::APIClientAsync::new(
+                server.uri(),
+                Default::default(),
+                "default_tenant".to_string(),
+                "default_database".to_string(),
+                Default::default(),
+            )
+            .unwrap()
+            .with_warning_callback(warning_callback),
+        )
+    }
 
+    #[cfg(feature = "tracing")]
     #[tokio::test]
-    async fn test_modify_collection() {
-        let client = ChromaClient::new(Default::default());
+    #[tracing_test::traced_test]
+    async fn test_tracing_spans() {
+        use wiremock::matchers::{method, path_regex};
+        use wiremock::{Mock, MockServer, ResponseTemplate};
 
-        let collection = client
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path_regex("/collections/mock/upsert$"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!(true)))
+            .mount(&server)
+            .await;
+        let collection = mock_collection(&server, None);
+        let collection_entries = CollectionEntries {
+            ids: vec!["a", "b"],
+            metadatas: None,
+            documents: Some(vec!["x", "y"]),
+            embeddings: None,
+        };
+        collection
+            .upsert(collection_entries, Some(Box::new(MockEmbeddingProvider)))
             .await
-            .unwrap()
-            .get_or_create_collection(TEST_COLLECTION, None)
+            .unwrap();
+
+        assert!(logs_contain(
+            "chroma.collection.upsert{collection_id=mock entry_count=2}:chroma.embeddings.mock{doc_count=2}"
+        ));
+        assert!(logs_contain(
+            "chroma.collection.upsert{collection_id=mock entry_count=2}:chroma.http.request{method=POST"
+        ));
+        assert!(logs_contain(&format!(
+            "url={}/api/v2/tenants/default_tenant/databases/default_database/collections/mock/upsert attempt=1 status=200 latency_ms=",
+            server.uri()
+        )));
+    }
+
+    #[tokio::test]
+    async fn test_update_metadata_and_documents() {
+        use wiremock::matchers::{body_json, body_partial_json, method, path_regex};
+        use wiremock::{Mock, MockServer, ResponseTemplate};
+
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path_regex("/collections/mock/update$"))
+            .and(body_json(json!({
+                "ids": ["a", "b"],
+                "embeddings": null,
+                "metadatas": [{"tag": "red"}, {"tag": "blue"}],
+                "documents": null,
+            })))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({})))
+            .expect(1)
+            .mount(&server)
+            .await;
+        Mock::given(method("POST"))
+            .and(path_regex("/collections/mock/update$"))
+            .and(body_partial_json(json!({
+                "ids": ["a"],
+                "metadatas": null,
+                "documents": ["new text"],
+            })))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({})))
+            .expect(2)
+            .mount(&server)
+            .await;
+        let collection = mock_collection(&server, None);
+
+        let metadatas = vec![
+            json!({"tag": "red"}).as_object().unwrap().clone(),
+            json!({"tag": "blue"}).as_object().unwrap().clone(),
+        ];
+        collection
+            .update_metadata(&["a", "b"], metadatas.clone())
             .await
             .unwrap();
+        assert!(matches!(
+            collection.update_metadata(&["a"], metadatas).await,
+            Err(ChromaError::Validation(_))
+        ));
 
-        //Test for setting invalid collection name. Should fail.
-        assert!(collection
-            .modify(Some("new name for test collection"), None)
+        collection
+            .update_documents(&["a"], &["new text"], Some(Box::new(MockEmbeddingProvider)))
             .await
-            .is_err());
+            .unwrap();
+        assert!(matches!(
+            collection
+                .update_documents(&["a"], &["new text"], None)
+                .await,
+            Err(ChromaError::InvalidArgument { .. })
+        ));
+        let collection = collection.with_embedding_function(Arc::new(MockEmbeddingProvider));
+        collection
+            .update_documents(&["a"], &["new text"], None)
+            .await
+            .unwrap();
+        assert!(matches!(
+            collection
+                .update_documents(&["a", "b"], &["new text"], None)
+                .await,
+            Err(ChromaError::Validation(_))
+        ));
 
-        //Test for setting new metadata. Should pass.
-        assert!(collection
-            .modify(
-                None,
-                Some(
-                    json!({
-                        "test": "test"
-                    })
-                    .as_object()
-                    .unwrap()
+        let requests = server.received_requests().await.unwrap();
+        let body: Value = requests.last().unwrap().body_json().unwrap();
+        assert_eq!(body["embeddings"][0].as_array().unwrap().len(), 768);
+    }
+
+    #[tokio::test]
+    async fn test_upsert_chunked_reports_failed_chunk() {
+        use std::sync::atomic::{AtomicUsize, Ordering};
+        use wiremock::matchers::{body_partial_json, method};
+        use wiremock::{Mock, MockServer, ResponseTemplate};
+
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(body_partial_json(json!({"ids": ["e2", "e3"]})))
+            .respond_with(ResponseTemplate::new(500).set_body_string("boom"))
+            .mount(&server)
+            .await;
+        Mock::given(method("POST"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!(true)))
+            .mount(&server)
+            .await;
+        let warned = Arc::new(AtomicUsize::new(0));
+        let callback_warned = warned.clone();
+        let collection = mock_collection(
+            &server,
+            Some(WarningCallback::new(move |_| {
+                callback_warned.fetch_add(1, Ordering::SeqCst);
+            })),
+        );
+
+        let ids = ["e0", "e1", "e2", "e3", "e4"];
+        let written = Arc::new(AtomicUsize::new(0));
+        let progress_written = written.clone();
+        let options = AddOptions {
+            progress: Some(Box::new(move |done, total| {
+                assert_eq!(total, 5);
+                progress_written.store(done, Ordering::SeqCst);
+            })),
+            ..Default::default()
+        };
+        let collection_entries = CollectionEntries {
+            ids: ids.to_vec(),
+            metadatas: None,
+            documents: None,
+            embeddings: Some(vec![vec![0.0_f32; 3]; 5]),
+        };
+        let error = collection
+            .upsert_chunked(collection_entries, 2, None, options)
+            .await
+            .unwrap_err();
+        assert!(matches!(
+            error,
+            ChromaError::ChunkFailed { chunk: 1, ref source }
+                if matches!(**source, ChromaError::ServerError { status: 500, .. })
+        ));
+        assert_eq!(written.load(Ordering::SeqCst), 2);
+
+        let collection_entries = CollectionEntries {
+            ids: vec!["e0", "e1", "e4"],
+            metadatas: None,
+            documents: None,
+            embeddings: Some(vec![vec![0.0_f32; 3]; 3]),
+        };
+        let options = AddOptions {
+            preflight_embeddings: true,
+            ..Default::default()
+        };
+        let result = collection
+            .upsert_chunked(collection_entries, 2, None, options)
+            .await
+            .unwrap();
+        assert_eq!(result.responses.len(), 2);
+        assert_eq!(*result.warnings, [Warning::PreflightSkipped]);
+        assert_eq!(warned.load(Ordering::SeqCst), 1);
+    }
+
+    #[tokio::test]
+    async fn test_upsert_with_options_embeds_in_order() {
+        use crate::commons::Embedding;
+        use crate::embeddings::EmbeddingFunction;
+        use wiremock::matchers::{body_partial_json, method, path_regex};
+        use wiremock::{Mock, MockServer, ResponseTemplate};
+
+        /// Embeds each document as its length, finishing the chunks of shorter documents last.
+        struct SlowLengthProvider;
+
+        #[async_trait::async_trait]
+        impl EmbeddingFunction for SlowLengthProvider {
+            async fn embed(&self, docs: &[&str]) -> anyhow::Result<Vec<Embedding>> {
+                let len = docs.first().map_or(0, |doc| doc.len() as u64);
+                tokio::time::sleep(std::time::Duration::from_millis(
+                    20_u64.saturating_sub(len * 3),
+                ))
+                .await;
+                Ok(docs.iter().map(|doc| vec![doc.len() as f32]).collect())
+            }
+        }
+
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path_regex("/collections/mock/upsert$"))
+            .and(body_partial_json(json!({
+                "ids": ["a", "b", "c", "d", "e"],
+                "embeddings": [[1.0], [2.0], [3.0], [4.0], [5.0]],
+            })))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!(true)))
+            .expect(4)
+            .mount(&server)
+            .await;
+        let collection = mock_collection(&server, None);
+
+        for concurrency in [None, Some(1), Some(2), Some(5)] {
+            let collection_entries = CollectionEntries {
+                ids: vec!["a", "b", "c", "d", "e"],
+                metadatas: None,
+                documents: Some(vec!["x", "xx", "xxx", "xxxx", "xxxxx"]),
+                embeddings: None,
+            };
+            collection
+                .upsert_with_options(
+                    collection_entries,
+                    Some(Box::new(SlowLengthProvider)),
+                    UpsertOptions { concurrency },
                 )
-            )
+                .await
+                .unwrap();
+        }
+    }
+
+    #[tokio::test]
+    async fn test_query_hits_fewer_results() {
+        use wiremock::matchers::method;
+        use wiremock::{Mock, MockServer, ResponseTemplate};
+
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "ids": [["a"]],
+                "documents": [["doc a"]],
+                "metadatas": [[null]],
+                "distances": [[0.5]],
+            })))
+            .mount(&server)
+            .await;
+        let collection = mock_collection(&server, None);
+
+        let hits = collection
+            .query_weighted(vec![(vec![0.0; 3], 1.0)], 3, None, None)
             .await
-            .is_ok());
+            .unwrap();
+        assert_eq!(hits.len(), 1);
+        assert_eq!(
+            *hits.warnings,
+            [Warning::FewerResults {
+                requested: 3,
+                returned: 1
+            }]
+        );
+
+        let hits = collection
+            .query_weighted(vec![(vec![0.0; 3], 1.0)], 1, None, None)
+            .await
+            .unwrap();
+        assert!(hits.warnings.is_empty());
     }
 
     #[tokio::test]
-    async fn test_add_to_collection() {
+    async fn test_add_chunked() {
         let client = ChromaClient::new(Default::default());
 
         let collection = client
@@ -526,443 +5290,1807 @@ mod tests {
             .await
             .unwrap();
 
-        let invalid_collection_entries = CollectionEntries {
-            ids: vec!["test1"],
+        let ids: Vec<String> = (0..10).map(|i| format!("chunked{i}")).collect();
+        collection
+            .delete(Some(ids.iter().map(String::as_str).collect()), None, None)
+            .await
+            .unwrap();
+        let collection_entries = CollectionEntries {
+            ids: ids.iter().map(String::as_str).collect(),
             metadatas: None,
-            documents: None,
+            documents: Some(vec!["Once upon a time there was a frog"; 10]),
             embeddings: None,
         };
+        let options = AddOptions {
+            concurrency: 2,
+            preflight_embeddings: true,
+            ..Default::default()
+        };
+        let result = collection
+            .add_chunked(
+                collection_entries,
+                3,
+                Some(Box::new(MockEmbeddingProvider)),
+                options,
+            )
+            .await
+            .unwrap();
+        assert_eq!(result.responses.len(), 4);
+    }
 
-        let response = collection.add(
-            invalid_collection_entries,
-            Some(Box::new(MockEmbeddingProvider)),
-        );
-        assert!(
-            response.await.is_err(),
-            "Embeddings and documents cannot both be None"
-        );
-
-        let invalid_collection_entries = CollectionEntries {
-            ids: vec!["test"],
-            metadatas: None,
-            documents: Some(vec!["Document content 1", "Document content 2"]),
+    #[test]
+    fn test_get_result_sort() {
+        let get_result = || GetResult {
+            ids: ["e", "d", "c", "b", "a", "f"].map(String::from).to_vec(),
+            metadatas: Some(vec![
+                Some(json!({"rank": 2}).as_object().unwrap().clone()),
+                Some(json!({"rank": "x"}).as_object().unwrap().clone()),
+                None,
+                Some(json!({"rank": 1.5}).as_object().unwrap().clone()),
+                Some(json!({"rank": 2}).as_object().unwrap().clone()),
+                Some(json!({"other": 0}).as_object().unwrap().clone()),
+            ]),
+            documents: Some(
+                ["e", "d", "c", "b", "a", "f"]
+                    .map(|id| Some(format!("doc {id}")))
+                    .to_vec(),
+            ),
             embeddings: None,
+            total: None,
         };
-        let response = collection.add(
-            invalid_collection_entries,
-            Some(Box::new(MockEmbeddingProvider)),
+
+        let mut by_id = get_result();
+        by_id.sort(&SortBy::Id);
+        assert_eq!(by_id.ids, vec!["a", "b", "c", "d", "e", "f"]);
+        let documents: Vec<String> = by_id.documents.unwrap().into_iter().flatten().collect();
+        assert_eq!(
+            documents,
+            ["a", "b", "c", "d", "e", "f"].map(|id| format!("doc {id}"))
         );
-        assert!(
-            response.await.is_err(),
-            "IDs, embeddings, metadatas, and documents must all be the same length"
+
+        // Numbers, then strings, then records without the key. Ties are broken by id.
+        let mut by_rank = get_result();
+        by_rank.sort(&SortBy::Metadata("rank".into()));
+        assert_eq!(by_rank.ids, vec!["b", "a", "e", "d", "c", "f"]);
+        assert_eq!(
+            by_rank.metadatas.unwrap()[0],
+            Some(json!({"rank": 1.5}).as_object().unwrap().clone())
         );
 
-        let valid_collection_entries = CollectionEntries {
-            ids: vec!["test1", "test2"],
-            metadatas: None,
-            documents: Some(vec!["Document content 1", "Document content 2"]),
+        // Integers too large for f64 to tell apart still sort exactly.
+        let mut large = GetResult {
+            ids: ["a", "b", "c"].map(String::from).to_vec(),
+            metadatas: Some(
+                [json!(u64::MAX), json!(u64::MAX - 1), json!(i64::MIN)]
+                    .map(|n| Some(json!({"n": n}).as_object().unwrap().clone()))
+                    .to_vec(),
+            ),
+            documents: None,
             embeddings: None,
+            total: None,
         };
-        let response = collection.add(
-            valid_collection_entries,
-            Some(Box::new(MockEmbeddingProvider)),
-        );
-        assert!(
-            response.await.is_ok(),
-            "IDs, embeddings, metadatas, and documents must all be the same length"
+        large.sort(&SortBy::Metadata("n".into()));
+        assert_eq!(large.ids, vec!["c", "b", "a"]);
+    }
+
+    #[tokio::test]
+    async fn test_query_batched() {
+        use wiremock::matchers::{method, path_regex};
+        use wiremock::{Mock, MockServer, Request, ResponseTemplate};
+
+        // Answers each query embedding [x] with a single hit "hit-x" at distance x, and fails on 13.
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path_regex("/collections/mock/query$"))
+            .respond_with(|request: &Request| {
+                let body: Value = request.body_json().unwrap();
+                let xs: Vec<f32> = body["query_embeddings"]
+                    .as_array()
+                    .unwrap()
+                    .iter()
+                    .map(|embedding| embedding[0].as_f64().unwrap() as f32)
+                    .collect();
+                if xs.contains(&13.0) {
+                    return ResponseTemplate::new(500).set_body_string("unlucky");
+                }
+                ResponseTemplate::new(200).set_body_json(json!({
+                    "ids": xs.iter().map(|x| vec![format!("hit-{x}")]).collect::<Vec<_>>(),
+                    "distances": xs.iter().map(|x| vec![x]).collect::<Vec<_>>(),
+                }))
+            })
+            .mount(&server)
+            .await;
+        let collection = mock_collection(&server, None);
+
+        let embeddings = |xs: &[u8]| xs.iter().map(|&x| vec![x as f32]).collect::<Vec<_>>();
+        let batched = collection
+            .query_batched(embeddings(&[1, 2, 3, 4, 5, 6, 7]), 1, None, 2, 3, false)
+            .await
+            .unwrap();
+        assert!(batched.failed.is_empty());
+        let expected: Vec<Vec<String>> = (1..=7).map(|x| vec![format!("hit-{x}")]).collect();
+        assert_eq!(batched.result.ids, expected);
+        assert_eq!(
+            batched.result.distances.unwrap(),
+            (1..=7).map(|x| vec![x as f32]).collect::<Vec<_>>()
         );
 
-        let invalid_collection_entries = CollectionEntries {
-            ids: vec!["test1", ""],
-            metadatas: None,
-            documents: Some(vec!["Document content 1", "Document content 2"]),
-            embeddings: None,
-        };
-        let response = collection.add(
-            invalid_collection_entries,
-            Some(Box::new(MockEmbeddingProvider)),
-        );
-        assert!(response.await.is_err(), "Empty IDs not allowed");
+        let error = collection
+            .query_batched(embeddings(&[1, 2, 13, 4, 5]), 1, None, 2, 2, false)
+            .await
+            .unwrap_err();
+        assert!(matches!(error, ChromaError::ChunkFailed { chunk: 1, .. }));
+
+        // The failed batch is the first one, so the distances column is backfilled for its queries.
+        let batched = collection
+            .query_batched(embeddings(&[13, 2, 3, 4, 5]), 1, None, 2, 2, true)
+            .await
+            .unwrap();
+        assert_eq!(batched.result.ids[0], Vec::<String>::new());
+        assert_eq!(batched.result.ids[1], Vec::<String>::new());
+        assert_eq!(batched.result.ids[2], vec!["hit-3"]);
+        assert_eq!(batched.result.ids[4], vec!["hit-5"]);
+        assert_eq!(batched.result.distances.as_ref().unwrap().len(), 5);
+        assert_eq!(batched.result.distances.unwrap()[2], vec![3.0]);
+        assert_eq!(batched.failed.len(), 1);
+        assert!(matches!(
+            batched.failed[0],
+            ChromaError::ChunkFailed { chunk: 0, .. }
+        ));
+
+        assert!(collection
+            .query_batched(vec![], 1, None, 2, 2, false)
+            .await
+            .unwrap()
+            .result
+            .ids
+            .is_empty());
+        assert!(matches!(
+            collection
+                .query_batched(embeddings(&[1]), 1, None, 0, 1, false)
+                .await,
+            Err(ChromaError::InvalidArgument { .. })
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_large_integer_metadata() {
+        use wiremock::matchers::{body_json, method, path_regex};
+        use wiremock::{Mock, MockServer, ResponseTemplate};
+
+        let server = MockServer::start().await;
+        let metadata = json!({"min": i64::MIN, "max": i64::MAX, "tags": [i64::MAX]});
+        Mock::given(method("POST"))
+            .and(path_regex("/collections/mock/upsert$"))
+            .and(body_json(json!({
+                "ids": ["a"],
+                "embeddings": [[0.5]],
+                "metadatas": [metadata],
+                "documents": null,
+            })))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({})))
+            .expect(1)
+            .mount(&server)
+            .await;
+        // Reading is exact up to u64::MAX, in case another client stored it.
+        Mock::given(method("POST"))
+            .and(path_regex("/collections/mock/get$"))
+            .and(body_json(json!({
+                "where": {"max": {"$eq": i64::MAX}},
+                "include": ["metadatas"],
+            })))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "ids": ["a"],
+                "metadatas": [{"min": i64::MIN, "max": i64::MAX, "big": u64::MAX}],
+            })))
+            .expect(1)
+            .mount(&server)
+            .await;
+        let collection = mock_collection(&server, None);
+
+        let entries = |metadata: &Value| CollectionEntries {
+            ids: vec!["a"],
+            embeddings: Some(vec![vec![0.5]]),
+            metadatas: Some(vec![metadata.as_object().unwrap().clone()]),
+            documents: None,
+        };
+        collection.upsert(entries(&metadata), None).await.unwrap();
+
+        for metadata in [json!({"id": u64::MAX}), json!({"ids": [1, u64::MAX]})] {
+            let Err(ChromaError::Validation(error)) =
+                collection.upsert(entries(&metadata), None).await
+            else {
+                panic!("{metadata} should be rejected");
+            };
+            assert_eq!(error.violations[0].rule, ValidationRule::IntegerOutOfRange);
+            assert!(error.to_string().contains(&u64::MAX.to_string()));
+        }
+
+        let get_options = |n: Value| {
+            GetOptions::builder()
+                .where_metadata(WhereFilter::field("max").eq(n))
+                .include(vec![Include::Metadatas])
+                .build()
+        };
+        let result = collection.get(get_options(json!(i64::MAX))).await.unwrap();
+        let metadata = result.metadatas.unwrap()[0].clone().unwrap();
+        assert_eq!(metadata["min"].as_i64(), Some(i64::MIN));
+        assert_eq!(metadata["max"].as_i64(), Some(i64::MAX));
+        assert_eq!(metadata["big"].as_u64(), Some(u64::MAX));
+
+        assert!(matches!(
+            collection.get(get_options(json!(u64::MAX))).await,
+            Err(ChromaError::InvalidArgument { .. })
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_query_shorthands() {
+        use wiremock::matchers::{body_json, method, path_regex};
+        use wiremock::{Mock, MockServer, ResponseTemplate};
+
+        let server = MockServer::start().await;
+        let response = ResponseTemplate::new(200).set_body_json(json!({"ids": [["a"]]}));
+        Mock::given(method("POST"))
+            .and(path_regex("/collections/mock/query$"))
+            .and(body_json(
+                json!({"query_embeddings": [[0.5, 0.25]], "n_results": 1}),
+            ))
+            .respond_with(response)
+            .expect(2)
+            .mount(&server)
+            .await;
+        let collection = mock_collection(&server, None);
+
+        let query_result = collection
+            .query_embeddings(vec![vec![0.5, 0.25]], 1)
+            .await
+            .unwrap();
+        assert_eq!(query_result.ids, [["a"]]);
+        let query_result = collection
+            .query_texts(&["frog"], 1, Box::new(FixedEmbedding))
+            .await
+            .unwrap();
+        assert_eq!(query_result.ids, [["a"]]);
+        assert!(collection
+            .query_texts(&[], 1, Box::new(FixedEmbedding))
+            .await
+            .is_err());
+
+        /// Embeds every text as [0.5, 0.25].
+        struct FixedEmbedding;
+
+        #[async_trait::async_trait]
+        impl crate::embeddings::EmbeddingFunction for FixedEmbedding {
+            async fn embed(&self, docs: &[&str]) -> anyhow::Result<Vec<Vec<f32>>> {
+                Ok(vec![vec![0.5, 0.25]; docs.len()])
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn test_query_logger() {
+        use std::sync::Mutex;
+        use wiremock::matchers::{method, path_regex};
+        use wiremock::{Mock, MockServer, ResponseTemplate};
+
+        struct Recorder(Mutex<Vec<QueryTrace>>);
+        impl QueryLogger for Recorder {
+            fn on_query(&self, trace: &QueryTrace) {
+                self.0.lock().unwrap().push(trace.clone());
+            }
+        }
+
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path_regex("/query$"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "ids": [["a", "b"]],
+                "distances": [[0.25, 0.5]],
+            })))
+            .mount(&server)
+            .await;
+        let recorder = Arc::new(Recorder(Mutex::new(vec![])));
+        let collection = mock_collection(&server, None)
+            .with_embedding_function(Arc::new(MockEmbeddingProvider))
+            .with_query_logger(recorder.clone());
+
+        let query = QueryOptions::builder()
+            .query_embeddings(vec![vec![0.5, 0.25]])
+            .n_results(2)
+            .build()
+            .unwrap();
+        collection.query(query, None).await.unwrap();
+        let query = QueryOptions::builder()
+            .query_texts(vec!["frog"])
+            .build()
+            .unwrap();
+        collection
+            .query(query, Some(Box::new(MockEmbeddingProvider)))
+            .await
+            .unwrap();
+        collection
+            .similarity_search("frog", 2, Some(json!({"color": "red"})), None)
+            .await
+            .unwrap();
+
+        let traces = recorder.0.lock().unwrap();
+        assert_eq!(traces.len(), 3);
+        assert!(traces.iter().all(|trace| trace.collection == "mock"
+            && trace.ids == [["a", "b"]]
+            && trace.query_embeddings.is_none()));
+        assert_eq!(traces[0].query_texts, None);
+        assert_eq!(traces[1].query_texts, Some(vec!["frog".to_string()]));
+        assert_eq!(traces[1].n_results, DEFAULT_N_RESULTS);
+        assert_eq!(traces[2].where_metadata, Some(json!({"color": "red"})));
+        assert_eq!(traces[2].distances, Some(vec![vec![0.25, 0.5]]));
+    }
+
+    #[tokio::test]
+    async fn test_non_json_success_responses() {
+        use wiremock::matchers::{method, path_regex};
+        use wiremock::{Mock, MockServer, ResponseTemplate};
+
+        let server = MockServer::start().await;
+        for response in [
+            ResponseTemplate::new(200),
+            ResponseTemplate::new(200).set_body_raw("OK", "text/plain"),
+        ] {
+            server.reset().await;
+            Mock::given(path_regex(
+                "/collections/mock(/(update|upsert|delete|count))?$",
+            ))
+            .respond_with(response)
+            .mount(&server)
+            .await;
+            let collection = mock_collection(&server, None);
+            let entries = || CollectionEntries {
+                ids: vec!["a"],
+                embeddings: Some(vec![vec![0.5, 0.25]]),
+                ..Default::default()
+            };
+
+            collection.modify(Some("renamed"), None).await.unwrap();
+            collection.update(entries(), None).await.unwrap();
+            assert_eq!(
+                collection.upsert(entries(), None).await.unwrap(),
+                Value::Null
+            );
+            collection
+                .delete(Some(vec!["a"]), None, None)
+                .await
+                .unwrap();
+
+            // Typed results still need JSON, and the error says what came instead.
+            let error = collection.count().await.unwrap_err();
+            let ChromaError::UnexpectedResponse { content_type, .. } = &error else {
+                panic!("Expected an unexpected response error, got {error:?}");
+            };
+            if content_type.is_some() {
+                assert_eq!(content_type.as_deref(), Some("text/plain"));
+                assert!(error.to_string().contains("\"OK\""));
+            } else {
+                assert!(error.to_string().contains("an empty body"));
+            }
+        }
+        server.reset().await;
+        Mock::given(method("POST"))
+            .and(path_regex("/collections/mock/get$"))
+            .respond_with(
+                ResponseTemplate::new(200).set_body_raw("<html>Bad gateway</html>", "text/html"),
+            )
+            .mount(&server)
+            .await;
+        let error = mock_collection(&server, None)
+            .get(GetOptions::default())
+            .await
+            .unwrap_err();
+        assert!(error.to_string().contains("text/html"));
+    }
+
+    #[tokio::test]
+    async fn test_sample() {
+        use std::collections::HashSet;
+        use wiremock::matchers::{method, path_regex};
+        use wiremock::{Mock, MockServer, Request, ResponseTemplate};
+
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path_regex("/count$"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(100))
+            .mount(&server)
+            .await;
+        // 100 records, of which the first 20 have the color red.
+        Mock::given(method("POST"))
+            .and(path_regex("/get$"))
+            .respond_with(|request: &Request| {
+                let body: Value = request.body_json().unwrap();
+                assert_eq!(body["limit"], 1);
+                let offset = body["offset"].as_u64().unwrap();
+                let matching = if body.get("where").is_some() { 20 } else { 100 };
+                let ids: Vec<String> = (offset < matching)
+                    .then(|| format!("id-{offset}"))
+                    .into_iter()
+                    .collect();
+                ResponseTemplate::new(200).set_body_json(json!({"ids": ids}))
+            })
+            .mount(&server)
+            .await;
+        let collection = mock_collection(&server, None);
+
+        let sample = collection.sample(10, None, Some(7)).await.unwrap();
+        assert_eq!(sample.ids.len(), 10);
+        assert_eq!(sample.ids.iter().collect::<HashSet<_>>().len(), 10);
+        let again = collection.sample(10, None, Some(7)).await.unwrap();
+        assert_eq!(sample.ids, again.ids);
+
+        let red = || Filter::new().metadata(json!({"color": "red"})).unwrap();
+        let sample = collection.sample(10, Some(red()), None).await.unwrap();
+        assert_eq!(sample.ids.iter().collect::<HashSet<_>>().len(), 10);
+        assert!(sample
+            .ids
+            .iter()
+            .all(|id| id["id-".len()..].parse::<usize>().unwrap() < 20));
+        // Fewer records match than requested.
+        let sample = collection.sample(30, Some(red()), None).await.unwrap();
+        assert_eq!(sample.ids.iter().collect::<HashSet<_>>().len(), 20);
+    }
+
+    #[tokio::test]
+    async fn test_get_with_count() {
+        use wiremock::matchers::{body_json, method, path_regex};
+        use wiremock::{Mock, MockServer, ResponseTemplate};
+
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path_regex("/count$"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(5))
+            .expect(3)
+            .mount(&server)
+            .await;
+        let ids = ["a", "b", "c", "d", "e"];
+        for offset in [0, 2, 4] {
+            let page = &ids[offset..(offset + 2).min(ids.len())];
+            Mock::given(method("POST"))
+                .and(path_regex("/get$"))
+                .and(body_json(json!({"limit": 2, "offset": offset})))
+                .respond_with(ResponseTemplate::new(200).set_body_json(json!({"ids": page})))
+                .expect(1)
+                .mount(&server)
+                .await;
+        }
+        // With a filter, the matching ids are counted.
+        let filter = json!({"color": "red"});
+        Mock::given(method("POST"))
+            .and(path_regex("/get$"))
+            .and(body_json(json!({"where": filter, "include": []})))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({"ids": ["a", "c", "e"]})))
+            .expect(1)
+            .mount(&server)
+            .await;
+        Mock::given(method("POST"))
+            .and(path_regex("/get$"))
+            .and(body_json(json!({"where": filter, "limit": 2, "offset": 2})))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({"ids": ["e"]})))
+            .expect(1)
+            .mount(&server)
+            .await;
+        let collection = mock_collection(&server, None);
 
-        let invalid_collection_entries = CollectionEntries {
-            ids: vec!["test", "test"],
-            metadatas: None,
-            documents: Some(vec!["Document content 1", "Document content 2"]),
-            embeddings: Some(vec![vec![1.0, 2.0], vec![3.0, 4.0]]),
-        };
-        let response = collection.add(invalid_collection_entries, None);
-        assert!(
-            response.await.is_err(),
-            "Expected IDs to be unique. Duplicates not allowed"
-        );
+        let mut fetched = vec![];
+        for offset in [0, 2, 4] {
+            let options = GetOptions::builder().limit(2).offset(offset).build();
+            let (get_result, total) = collection.get_with_count(options).await.unwrap();
+            assert_eq!(total, 5);
+            assert_eq!(get_result.total, Some(5));
+            fetched.extend(get_result.ids);
+        }
+        assert_eq!(fetched, ids);
 
-        let collection_entries = CollectionEntries {
-            ids: vec!["test1", "test2"],
-            metadatas: None,
-            documents: Some(vec!["Document content 1", "Document content 2"]),
-            embeddings: None,
+        let options = GetOptions::builder()
+            .where_metadata(filter.clone())
+            .limit(2)
+            .offset(2)
+            .build();
+        let (get_result, total) = collection.get_with_count(options).await.unwrap();
+        assert_eq!((get_result.ids, total), (vec!["e".to_string()], 3));
+
+        // A total reported by the server is picked up.
+        let get_result: GetResult =
+            serde_json::from_value(json!({"ids": ["a"], "total": 9})).unwrap();
+        assert_eq!(get_result.total, Some(9));
+    }
+
+    #[tokio::test]
+    async fn test_iter() {
+        use futures::{StreamExt, TryStreamExt};
+        use wiremock::matchers::{method, path_regex};
+        use wiremock::{Mock, MockServer, Request, ResponseTemplate};
+
+        let server = MockServer::start().await;
+        // 5 records, of which the first 4 have the color red.
+        Mock::given(method("POST"))
+            .and(path_regex("/get$"))
+            .respond_with(|request: &Request| {
+                let body: Value = request.body_json().unwrap();
+                let limit = body["limit"].as_u64().unwrap();
+                let offset = body["offset"].as_u64().unwrap();
+                let matching = if body.get("where").is_some() { 4 } else { 5 };
+                let ids: Vec<String> = (offset..matching.min(offset + limit))
+                    .map(|i| format!("id-{i}"))
+                    .collect();
+                ResponseTemplate::new(200).set_body_json(json!({"ids": ids}))
+            })
+            .mount(&server)
+            .await;
+        let collection = mock_collection(&server, None);
+        let page_ids = |pages: Vec<GetResult>| -> Vec<Vec<String>> {
+            pages.into_iter().map(|page| page.ids).collect()
         };
-        let response = collection.add(collection_entries, None);
-        assert!(
-            response.await.is_err(),
-            "embedding_function cannot be None if documents are provided and embeddings are None"
+
+        // The last page is short.
+        let pages: Vec<GetResult> = collection.iter_all(2).try_collect().await.unwrap();
+        assert_eq!(
+            page_ids(pages),
+            vec![vec!["id-0", "id-1"], vec!["id-2", "id-3"], vec!["id-4"]]
         );
+        assert_eq!(server.received_requests().await.unwrap().len(), 3);
 
-        let collection_entries = CollectionEntries {
-            ids: vec!["test1", "test2"],
-            metadatas: None,
-            documents: Some(vec!["Document content 1", "Document content 2"]),
-            embeddings: None,
-        };
-        let response = collection.add(collection_entries, Some(Box::new(MockEmbeddingProvider)));
-        assert!(
-            response.await.is_ok(),
-            "Embeddings are computed by the embedding_function if embeddings are None and documents are provided"
+        // The last page is full, so an empty page ends the stream without being yielded.
+        let get_options = GetOptions::builder()
+            .where_metadata(json!({"color": "red"}))
+            .include(vec![Include::Metadatas])
+            .build();
+        let pages: Vec<GetResult> = collection.iter(get_options, 2).try_collect().await.unwrap();
+        assert_eq!(
+            page_ids(pages),
+            vec![vec!["id-0", "id-1"], vec!["id-2", "id-3"]]
         );
+        let requests = server.received_requests().await.unwrap();
+        assert_eq!(requests.len(), 6);
+        for (request, offset) in requests[3..].iter().zip([0, 2, 4]) {
+            let body: Value = request.body_json().unwrap();
+            assert_eq!(body["where"], json!({"color": "red"}));
+            assert_eq!(body["include"], json!(["metadatas"]));
+            assert_eq!(body["limit"], 2);
+            assert_eq!(body["offset"], offset);
+        }
+
+        // The stream starts at the offset and stops after the limit, shortening the last page to it.
+        let get_options = GetOptions::builder().offset(1).limit(3).build();
+        let pages: Vec<GetResult> = collection.iter(get_options, 2).try_collect().await.unwrap();
+        assert_eq!(page_ids(pages), vec![vec!["id-1", "id-2"], vec!["id-3"]]);
+        let requests = server.received_requests().await.unwrap();
+        assert_eq!(requests.len(), 8);
+        for (request, (offset, limit)) in requests[6..].iter().zip([(1, 2), (3, 1)]) {
+            let body: Value = request.body_json().unwrap();
+            assert_eq!(body["offset"], offset);
+            assert_eq!(body["limit"], limit);
+        }
+
+        let pages: Vec<_> = collection.iter_all(0).collect().await;
+        assert!(matches!(
+            pages.as_slice(),
+            [Err(ChromaError::InvalidArgument { .. })]
+        ));
+        let get_options = GetOptions::builder().sort(SortBy::Id).build();
+        let pages: Vec<_> = collection.iter(get_options, 2).collect().await;
+        assert!(matches!(
+            pages.as_slice(),
+            [Err(ChromaError::InvalidArgument { .. })]
+        ));
+        assert_eq!(server.received_requests().await.unwrap().len(), 8);
     }
 
     #[tokio::test]
-    async fn test_upsert_collection() {
-        let client = ChromaClient::new(Default::default());
-
-        let collection = client
-            .await
-            .unwrap()
-            .get_or_create_collection(TEST_COLLECTION, None)
-            .await
-            .unwrap();
+    async fn test_get_all_sorted() {
+        use wiremock::matchers::{body_partial_json, method};
+        use wiremock::{Mock, MockServer, ResponseTemplate};
 
-        let invalid_collection_entries = CollectionEntries {
-            ids: vec!["test1"],
-            metadatas: None,
-            documents: None,
-            embeddings: None,
-        };
+        let server = MockServer::start().await;
+        for (offset, page) in [
+            (
+                0,
+                json!({"ids": ["c", "a"], "documents": ["doc c", "doc a"]}),
+            ),
+            (2, json!({"ids": ["b"], "documents": ["doc b"]})),
+        ] {
+            Mock::given(method("POST"))
+                .and(body_partial_json(json!({"limit": 2, "offset": offset})))
+                .respond_with(ResponseTemplate::new(200).set_body_json(page))
+                .expect(1)
+                .mount(&server)
+                .await;
+        }
+        let collection = mock_collection(&server, None);
 
-        let response = collection.upsert(
-            invalid_collection_entries,
-            Some(Box::new(MockEmbeddingProvider)),
+        let options = GetOptions::builder().sort(SortBy::Id).build();
+        let get_result = collection.get_all(options, 2).await.unwrap();
+        assert_eq!(get_result.ids, vec!["a", "b", "c"]);
+        assert_eq!(
+            get_result.documents.unwrap(),
+            vec![
+                Some("doc a".into()),
+                Some("doc b".into()),
+                Some("doc c".into())
+            ]
         );
-        assert!(
-            response.await.is_err(),
-            "Embeddings and documents cannot both be None"
+    }
+
+    #[tokio::test]
+    async fn test_query_exclude_ids() {
+        use std::sync::Mutex;
+        use wiremock::matchers::{body_partial_json, method, path_regex};
+        use wiremock::{Mock, MockServer, ResponseTemplate};
+
+        let server = MockServer::start().await;
+        let filter = json!({"color": "red"});
+        // 2 requested + 2 excluded.
+        Mock::given(method("POST"))
+            .and(path_regex("/query$"))
+            .and(body_partial_json(json!({"n_results": 4, "where": filter})))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "ids": [["a", "seen", "b", "c"], ["seen", "d"]],
+                "documents": [["doc a", "doc seen", "doc b", "doc c"], ["doc seen", "doc d"]],
+                "distances": [[0.1, 0.2, 0.3, 0.4], [0.1, 0.5]],
+                "metadatas": null,
+            })))
+            .expect(1)
+            .mount(&server)
+            .await;
+        let warnings = Arc::new(Mutex::new(vec![]));
+        let callback_warnings = warnings.clone();
+        let collection = mock_collection(
+            &server,
+            Some(WarningCallback::new(move |warning| {
+                callback_warnings.lock().unwrap().push(warning.clone());
+            })),
         );
 
-        let invalid_collection_entries = CollectionEntries {
-            ids: vec!["test"],
-            metadatas: None,
-            documents: Some(vec!["Document content 1", "Document content 2"]),
-            embeddings: None,
-        };
-        let response = collection.upsert(
-            invalid_collection_entries,
-            Some(Box::new(MockEmbeddingProvider)),
+        let query = QueryOptions::builder()
+            .query_embeddings(vec![vec![0.1, 0.2], vec![0.3, 0.4]])
+            .n_results(2)
+            .where_metadata(filter.clone())
+            .include(vec![Include::Documents, Include::Distances])
+            .exclude_ids(vec!["seen".into(), "other".into()])
+            .build()
+            .unwrap();
+        let result = collection.query(query, None).await.unwrap();
+        assert_eq!(result.ids, vec![vec!["a", "b"], vec!["d"]]);
+        assert_eq!(
+            result.documents,
+            Some(vec![
+                vec!["doc a".into(), "doc b".into()],
+                vec!["doc d".into()]
+            ])
         );
-        assert!(
-            response.await.is_err(),
-            "IDs, embeddings, metadatas, and documents must all be the same length"
+        assert_eq!(result.distances, Some(vec![vec![0.1, 0.3], vec![0.5]]));
+        assert_eq!(result.metadatas, None);
+        assert_eq!(
+            *warnings.lock().unwrap(),
+            [Warning::FewerResults {
+                requested: 2,
+                returned: 1
+            }]
         );
 
-        let valid_collection_entries = CollectionEntries {
-            ids: vec!["test1", "test2"],
-            metadatas: None,
-            documents: Some(vec!["Document content 1", "Document content 2"]),
-            embeddings: None,
+        // Half an extra result per excluded id, rounded up.
+        Mock::given(method("POST"))
+            .and(path_regex("/query$"))
+            .and(body_partial_json(json!({"n_results": 12})))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({"ids": [[]]})))
+            .expect(1)
+            .mount(&server)
+            .await;
+        let query = QueryOptions::builder()
+            .query_embeddings(vec![vec![0.1, 0.2]])
+            .exclude_ids(vec!["seen".into(), "other".into(), "third".into()])
+            .exclude_overfetch(0.5)
+            .build()
+            .unwrap();
+        collection.query(query, None).await.unwrap();
+
+        let query = QueryOptions::builder()
+            .query_embeddings(vec![vec![0.1, 0.2]])
+            .exclude_ids(vec!["seen".into()])
+            .exclude_overfetch(-1.0)
+            .build()
+            .unwrap();
+        assert!(matches!(
+            collection.query(query, None).await,
+            Err(ChromaError::InvalidArgument { .. })
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_embedding_function_mismatch() {
+        use crate::commons::Embedding;
+        use crate::embeddings::{EmbeddingFunction, EMBEDDING_FUNCTION_KEY};
+        use std::sync::Mutex;
+        use wiremock::matchers::{method, path_regex};
+        use wiremock::{Mock, MockServer, ResponseTemplate};
+
+        struct NamedProvider(Option<&'static str>);
+
+        #[async_trait::async_trait]
+        impl EmbeddingFunction for NamedProvider {
+            async fn embed(&self, docs: &[&str]) -> anyhow::Result<Vec<Embedding>> {
+                Ok(docs.iter().map(|_| vec![0.1, 0.2]).collect())
+            }
+
+            fn identity(&self) -> Option<String> {
+                self.0.map(str::to_string)
+            }
+        }
+
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path_regex("/query$"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({"ids": [[]]})))
+            .expect(5)
+            .mount(&server)
+            .await;
+        let warnings = Arc::new(Mutex::new(vec![]));
+        let callback_warnings = warnings.clone();
+        let mut collection = mock_collection(
+            &server,
+            Some(WarningCallback::new(move |warning| {
+                callback_warnings.lock().unwrap().push(warning.clone());
+            })),
+        );
+        let query = || {
+            QueryOptions::builder()
+                .query_texts(vec!["frog"])
+                .build()
+                .unwrap()
         };
-        let response = collection.upsert(
-            valid_collection_entries,
-            Some(Box::new(MockEmbeddingProvider)),
+
+        // Without a recorded embedding function, nothing is compared.
+        let model_a = || Some(Box::new(NamedProvider(Some("test:model-a"))) as Box<_>);
+        collection.query(query(), model_a()).await.unwrap();
+        assert!(warnings.lock().unwrap().is_empty());
+
+        collection.metadata = Some(
+            json!({EMBEDDING_FUNCTION_KEY: "test:model-a"})
+                .as_object()
+                .unwrap()
+                .clone(),
         );
-        assert!(
-            response.await.is_ok(),
-            "IDs, embeddings, metadatas, and documents must all be the same length"
+        assert_eq!(
+            collection.recorded_embedding_function(),
+            Some("test:model-a")
         );
+        collection.query(query(), model_a()).await.unwrap();
+        // Functions without an identity are not compared.
+        collection
+            .query(query(), Some(Box::new(NamedProvider(None))))
+            .await
+            .unwrap();
+        assert!(warnings.lock().unwrap().is_empty());
 
-        let invalid_collection_entries = CollectionEntries {
-            ids: vec!["test1", ""],
-            metadatas: None,
-            documents: Some(vec!["Document content 1", "Document content 2"]),
-            embeddings: None,
-        };
-        let response = collection.upsert(
-            invalid_collection_entries,
-            Some(Box::new(MockEmbeddingProvider)),
+        let model_b = || Some(Box::new(NamedProvider(Some("test:model-b"))) as Box<_>);
+        collection.query(query(), model_b()).await.unwrap();
+        assert_eq!(
+            *warnings.lock().unwrap(),
+            [Warning::EmbeddingFunctionMismatch {
+                collection: "test:model-a".into(),
+                query: "test:model-b".into()
+            }]
         );
-        assert!(response.await.is_err(), "Empty IDs not allowed");
 
-        let invalid_collection_entries = CollectionEntries {
-            ids: vec!["test", "test"],
-            metadatas: None,
-            documents: Some(vec!["Document content 1", "Document content 2"]),
-            embeddings: Some(vec![vec![1.0, 2.0], vec![3.0, 4.0]]),
-        };
-        let response = collection.upsert(invalid_collection_entries, None);
-        assert!(
-            response.await.is_err(),
-            "Expected IDs to be unique. Duplicates not allowed"
+        let collection = collection.with_strict_embedding_function(true);
+        collection.query(query(), model_a()).await.unwrap();
+        let error = collection.query(query(), model_b()).await.unwrap_err();
+        assert!(error.to_string().contains("test:model-b"));
+    }
+
+    #[tokio::test]
+    async fn test_get_embeddings_unavailable() {
+        use std::sync::Mutex;
+        use wiremock::matchers::{method, path_regex};
+        use wiremock::{Mock, MockServer, ResponseTemplate};
+
+        // Responses of deployments that strip embeddings from large results.
+        let fixtures = [
+            (json!({"ids": ["a", "b"], "embeddings": null}), 2),
+            (json!({"ids": ["a", "b"]}), 2),
+            (json!({"ids": ["a", "b"], "embeddings": [null, null]}), 2),
+            (
+                json!({"ids": ["a", "b"], "embeddings": [[0.1, 0.2], null]}),
+                1,
+            ),
+            (json!({"ids": ["a", "b"], "embeddings": [[0.1, 0.2]]}), 1),
+        ];
+        let server = MockServer::start().await;
+        let warnings = Arc::new(Mutex::new(vec![]));
+        let callback_warnings = warnings.clone();
+        let mut collection = mock_collection(
+            &server,
+            Some(WarningCallback::new(move |warning| {
+                callback_warnings.lock().unwrap().push(warning.clone());
+            })),
         );
+        let get_options = |include| GetOptions::builder().include(include).build();
 
-        let collection_entries = CollectionEntries {
-            ids: vec!["test1", "test2"],
-            metadatas: None,
-            documents: Some(vec!["Document content 1", "Document content 2"]),
-            embeddings: None,
-        };
-        let response = collection.upsert(collection_entries, None);
-        assert!(
-            response.await.is_err(),
-            "embedding_function cannot be None if documents are provided and embeddings are None"
+        for (body, missing) in fixtures {
+            server.reset().await;
+            Mock::given(method("POST"))
+                .and(path_regex("/get$"))
+                .respond_with(ResponseTemplate::new(200).set_body_json(body))
+                .mount(&server)
+                .await;
+
+            // Embeddings that were not requested are not missing.
+            collection
+                .get(get_options(vec![Include::Documents]))
+                .await
+                .unwrap();
+            assert!(warnings.lock().unwrap().is_empty());
+
+            let result = collection
+                .get(get_options(vec![Include::Embeddings]))
+                .await
+                .unwrap();
+            assert_eq!(result.ids, vec!["a", "b"]);
+            assert_eq!(
+                *warnings.lock().unwrap(),
+                [Warning::EmbeddingsUnavailable { missing, total: 2 }]
+            );
+            warnings.lock().unwrap().clear();
+
+            collection = collection.with_strict_embeddings(true);
+            let error = collection
+                .get(get_options(vec![Include::Embeddings]))
+                .await
+                .unwrap_err();
+            assert!(matches!(
+                error,
+                ChromaError::EmbeddingsUnavailable { missing: m, total: 2 } if m == missing
+            ));
+            assert!(warnings.lock().unwrap().is_empty());
+            collection = collection.with_strict_embeddings(false);
+        }
+
+        server.reset().await;
+        Mock::given(method("POST"))
+            .and(path_regex("/get$"))
+            .respond_with(
+                ResponseTemplate::new(200)
+                    .set_body_json(json!({"ids": ["a"], "embeddings": [[0.1, 0.2]]})),
+            )
+            .mount(&server)
+            .await;
+        collection = collection.with_strict_embeddings(true);
+        collection
+            .get(get_options(vec![Include::Embeddings]))
+            .await
+            .unwrap();
+        assert!(warnings.lock().unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_query_embeddings_unavailable() {
+        use std::sync::Mutex;
+        use wiremock::matchers::{method, path_regex};
+        use wiremock::{Mock, MockServer, ResponseTemplate};
+
+        // Responses of deployments that strip embeddings from large results.
+        let fixtures = [
+            (json!({"ids": [["a", "b"], ["c"]], "embeddings": null}), 3),
+            (json!({"ids": [["a", "b"], ["c"]]}), 3),
+            (
+                json!({"ids": [["a", "b"], ["c"]], "embeddings": [null, null]}),
+                3,
+            ),
+            (
+                json!({"ids": [["a", "b"], ["c"]], "embeddings": [[[0.1], null], [[0.2]]]}),
+                3,
+            ),
+            (
+                json!({"ids": [["a", "b"], ["c"]], "embeddings": [[[0.1]], [[0.2]]]}),
+                1,
+            ),
+        ];
+        let server = MockServer::start().await;
+        let warnings = Arc::new(Mutex::new(vec![]));
+        let callback_warnings = warnings.clone();
+        let mut collection = mock_collection(
+            &server,
+            Some(WarningCallback::new(move |warning| {
+                callback_warnings.lock().unwrap().push(warning.clone());
+            })),
         );
+        let query = || {
+            QueryOptions::builder()
+                .query_embeddings(vec![vec![0.1], vec![0.2]])
+                .n_results(2)
+                .include(vec![Include::Embeddings])
+                .build()
+                .unwrap()
+        };
 
-        let collection_entries = CollectionEntries {
-            ids: vec!["test1", "test2"],
-            metadatas: None,
-            documents: Some(vec!["Document content 1", "Document content 2"]),
-            embeddings: None,
+        for (body, missing) in fixtures {
+            server.reset().await;
+            Mock::given(method("POST"))
+                .and(path_regex("/query$"))
+                .respond_with(ResponseTemplate::new(200).set_body_json(body))
+                .mount(&server)
+                .await;
+
+            let result = collection.query(query(), None).await.unwrap();
+            assert_eq!(result.ids, vec![vec!["a", "b"], vec!["c"]]);
+            if missing == 3 {
+                assert!(result.embeddings.is_none());
+            }
+            assert!(warnings
+                .lock()
+                .unwrap()
+                .contains(&Warning::EmbeddingsUnavailable { missing, total: 3 }));
+            warnings.lock().unwrap().clear();
+
+            collection = collection.with_strict_embeddings(true);
+            let error = collection.query(query(), None).await.unwrap_err();
+            assert!(matches!(
+                error,
+                ChromaError::EmbeddingsUnavailable { missing: m, total: 3 } if m == missing
+            ));
+            collection = collection.with_strict_embeddings(false);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_search() {
+        use crate::search::SearchRequest;
+        use crate::version::ChromaVersion;
+        use wiremock::matchers::{body_json, method, path_regex};
+        use wiremock::{Mock, MockServer, ResponseTemplate};
+
+        let server = MockServer::start().await;
+        let collection = mock_collection(&server, None);
+        let request = || {
+            SearchRequest::builder()
+                .query_embedding(vec![0.5])
+                .limit(2)
+                .include(vec![Include::Distances])
+                .build()
         };
-        let response = collection.upsert(collection_entries, Some(Box::new(MockEmbeddingProvider)));
+        Mock::given(method("POST"))
+            .and(path_regex("/collections/[^/]+/search$"))
+            .and(body_json(request().to_json()))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "ids": [["a", "b"]],
+                "scores": [[0.1, 0.2]],
+            })))
+            .up_to_n_times(1)
+            .mount(&server)
+            .await;
+        let result = collection.search(request()).await.unwrap();
+        assert_eq!(result.ids, vec!["a", "b"]);
+        assert_eq!(result.scores, Some(vec![Some(0.1), Some(0.2)]));
+
+        // A missing collection is still reported as such.
+        Mock::given(method("POST"))
+            .and(path_regex("/search$"))
+            .respond_with(ResponseTemplate::new(404).set_body_json(json!({
+                "error": "NotFoundError",
+                "message": "Collection mock does not exist.",
+            })))
+            .up_to_n_times(1)
+            .mount(&server)
+            .await;
+        let error = collection.search(request()).await.unwrap_err();
         assert!(
-            response.await.is_ok(),
-            "Embeddings are computed by the embedding_function if embeddings are None and documents are provided"
+            matches!(error, ChromaError::CollectionNotFound(_)),
+            "{error}"
         );
+
+        // Servers without the endpoint answer as for an unknown route.
+        for response in [
+            ResponseTemplate::new(404).set_body_json(json!({"detail": "Not Found"})),
+            ResponseTemplate::new(404),
+            ResponseTemplate::new(405),
+        ] {
+            server.reset().await;
+            Mock::given(method("POST"))
+                .respond_with(response)
+                .mount(&server)
+                .await;
+            let error = collection.search(request()).await.unwrap_err();
+            assert!(
+                matches!(&error, ChromaError::UnsupportedServer { feature, version: None } if feature == "search"),
+                "{error}"
+            );
+        }
+
+        // Without sending a request when the server is known to be too old.
+        server.reset().await;
+        collection
+            .api
+            .set_server_version(ChromaVersion::parse("0.6.3").unwrap());
+        let error = collection.search(request()).await.unwrap_err();
+        assert_eq!(error.to_string(), "Chroma 0.6.3 does not support search");
+        assert!(server.received_requests().await.unwrap().is_empty());
     }
 
     #[tokio::test]
-    async fn test_get_all_embeddings_from_collection() {
-        let client = ChromaClient::new(Default::default());
+    async fn test_search_collection() {
+        use crate::search::SearchRequest;
 
+        let client = ChromaClient::new(Default::default()).await.unwrap();
         let collection = client
-            .await
-            .unwrap()
             .get_or_create_collection(TEST_COLLECTION, None)
             .await
             .unwrap();
+        let request = SearchRequest::builder()
+            .query_embedding(vec![0.0_f32; 768])
+            .limit(3)
+            .include(vec![Include::Documents, Include::Distances])
+            .build();
+        let result = collection.search(request).await;
+        // Only Chroma 1.x servers serve the search endpoint.
+        if !client.version_info().await.unwrap().at_least(1, 0, 0) {
+            assert!(matches!(result, Err(ChromaError::UnsupportedServer { .. })));
+            return;
+        }
+        match result {
+            Ok(result) => {
+                assert!(result.ids.len() <= 3);
+                assert_eq!(
+                    result.scores.map(|scores| scores.len()),
+                    Some(result.ids.len())
+                );
+            }
+            // Not every 1.x server serves it.
+            Err(ChromaError::UnsupportedServer { .. }) => {}
+            Err(e) => panic!("{e}"),
+        }
+    }
 
-        let get_all_query = GetOptions {
-            ids: vec![],
-            where_metadata: None,
-            limit: None,
-            offset: None,
-            where_document: None,
-            include: None,
+    #[tokio::test]
+    async fn test_query_with_meta() {
+        use wiremock::matchers::{method, path_regex};
+        use wiremock::{Mock, MockServer, ResponseTemplate};
+
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path_regex("/query$"))
+            .respond_with(
+                ResponseTemplate::new(200)
+                    .insert_header("X-Request-Id", "req-123")
+                    .insert_header("Server-Timing", "query;dur=12")
+                    .insert_header("X-Unlisted", "ignored")
+                    .set_body_json(json!({"ids": [["a"]]})),
+            )
+            .mount(&server)
+            .await;
+        Mock::given(method("POST"))
+            .and(path_regex("/get$"))
+            .respond_with(
+                ResponseTemplate::new(200)
+                    .insert_header("X-Unlisted", "kept")
+                    .set_body_json(json!({"ids": ["a"]})),
+            )
+            .mount(&server)
+            .await;
+
+        let collection = mock_collection(&server, None);
+        let query = QueryOptions {
+            query_embeddings: Some(vec![vec![0.1, 0.2]]),
+            ..Default::default()
         };
-        let get_all_result = collection.get(get_all_query).await.unwrap();
+        let (result, meta) = collection.query_with_meta(query, None).await.unwrap();
+        assert_eq!(result.ids, vec![vec!["a".to_string()]]);
+        assert_eq!(meta.status, 200);
+        assert_eq!(meta.header("x-request-id"), Some("req-123"));
+        assert_eq!(
+            meta.server_timing,
+            Some(std::time::Duration::from_millis(12))
+        );
+        assert_eq!(meta.headers.len(), 2);
 
-        assert_eq!(get_all_result.ids.len(), collection.count().await.unwrap());
+        let mut collection = mock_collection(&server, None);
+        collection.api = Arc::new(
+            crate::api::APIClientAsync::new(
+                server.uri(),
+                Default::default(),
+                "default_tenant".to_string(),
+                "default_database".to_string(),
+                Default::default(),
+            )
+            .unwrap()
+            .with_response_headers(Some(vec!["X-Unlisted".to_string()])),
+        );
+        let (result, meta) = collection
+            .get_with_meta(GetOptions::default())
+            .await
+            .unwrap();
+        assert_eq!(result.ids, vec!["a".to_string()]);
+        assert_eq!(meta.header("X-UNLISTED"), Some("kept"));
+        assert_eq!(meta.header("x-request-id"), None);
     }
 
     #[tokio::test]
-    async fn test_update_collection() {
-        let client = ChromaClient::new(Default::default());
+    async fn test_query_with_timeout() {
+        use std::time::Duration;
+        use wiremock::matchers::method;
+        use wiremock::{Mock, MockServer, ResponseTemplate};
+
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .respond_with(
+                ResponseTemplate::new(200)
+                    .set_body_json(json!({"ids": [[]]}))
+                    .set_delay(Duration::from_millis(500)),
+            )
+            .mount(&server)
+            .await;
+        let collection = mock_collection(&server, None);
+        let query = || {
+            QueryOptions::builder()
+                .query_embeddings(vec![vec![0.0; 3]])
+                .build()
+                .unwrap()
+        };
+
+        let error = collection
+            .query_with_timeout(query(), None, Duration::from_millis(50))
+            .await
+            .unwrap_err();
+        assert!(matches!(error, ChromaError::Transport(e) if e.is_timeout()));
+
+        let query_result = collection
+            .query_with_timeout(query(), None, Duration::from_secs(5))
+            .await
+            .unwrap();
+        assert!(query_result.ids[0].is_empty());
+
+        let query_options = QueryOptions::builder()
+            .query_embeddings(vec![vec![0.0; 3]])
+            .timeout(Duration::from_millis(50))
+            .build()
+            .unwrap();
+        let error = collection.query(query_options, None).await.unwrap_err();
+        assert!(matches!(error, ChromaError::Transport(e) if e.is_timeout()));
+    }
+
+    /// The body of a write built as a [Value], as it was before [WriteBody](super::WriteBody), to check that both serialize the same.
+    fn value_body(entries: &CollectionEntries, float_precision: Option<u8>) -> Value {
+        let embeddings = match (entries.embeddings.clone(), float_precision) {
+            (Some(embeddings), Some(_)) => embeddings
+                .into_iter()
+                .map(|embedding| {
+                    embedding
+                        .into_iter()
+                        .map(|x| Value::from(component_json(x, float_precision)))
+                        .collect::<Value>()
+                })
+                .collect(),
+            (embeddings, _) => json!(embeddings),
+        };
+        json!({
+            "ids": entries.ids,
+            "embeddings": embeddings,
+            "metadatas": entries.metadatas,
+            "documents": entries.documents,
+        })
+    }
+
+    #[test]
+    fn test_write_body_matches_value_body() {
+        let metadata = |value: Value| json!({ "z": value, "a": 1 }).as_object().unwrap().clone();
+        let full = CollectionEntries {
+            ids: vec!["plain", "quote\"d", "ünïcødé\n"],
+            metadatas: Some(vec![
+                metadata(json!(i64::MAX)),
+                metadata(json!({"nested": [1.5, "tab\t"]})),
+                metadata(json!(null)),
+            ]),
+            documents: Some(vec!["", "emoji 🐙", "control \u{1}"]),
+            embeddings: Some(vec![
+                vec![0.1, -0.123_456_79, 1e-7],
+                vec![f32::MAX, f32::MIN_POSITIVE, -1.0],
+                vec![f32::NAN, f32::INFINITY],
+            ]),
+        };
+        let sparse = CollectionEntries {
+            ids: vec!["a", "b"],
+            documents: Some(vec!["x", "y"]),
+            ..Default::default()
+        };
+        for entries in [&full, &sparse, &CollectionEntries::default()] {
+            for float_precision in [None, Some(0), Some(3), Some(6)] {
+                let body =
+                    serde_json::to_vec(&super::WriteBody::new(entries, float_precision)).unwrap();
+                assert_eq!(
+                    String::from_utf8(body).unwrap(),
+                    value_body(entries, float_precision).to_string(),
+                    "{float_precision:?}"
+                );
+            }
+        }
+    }
 
-        let collection = client
-            .await
-            .unwrap()
-            .get_or_create_collection(TEST_COLLECTION, None)
-            .await
-            .unwrap();
+    #[test]
+    fn test_estimated_body_size() {
+        fn serialized_len(entries: &CollectionEntries, float_precision: Option<u8>) -> usize {
+            serde_json::to_vec(&value_body(entries, float_precision))
+                .unwrap()
+                .len()
+        }
 
-        let valid_collection_entries = CollectionEntries {
-            ids: vec!["test1"],
+        let metadata = |value: Value| json!({ "key": value }).as_object().unwrap().clone();
+        let full = CollectionEntries {
+            ids: vec!["plain", "quote\"d", "ünïcødé\n"],
+            metadatas: Some(vec![
+                metadata(json!(1)),
+                metadata(json!("tab\t")),
+                metadata(json!(null)),
+            ]),
+            documents: Some(vec!["", "emoji 🐙", "control \u{1}"]),
+            embeddings: Some(vec![
+                vec![0.1, -0.123_456_79, 1e-7],
+                vec![f32::MAX, 0.0, -1.0],
+                vec![],
+            ]),
+        };
+        let sparse = CollectionEntries {
+            ids: vec!["a", "b"],
             metadatas: None,
             documents: None,
-            embeddings: None,
+            embeddings: Some(vec![vec![0.5; 4], vec![0.25; 4]]),
         };
+        for entries in [&full, &sparse, &CollectionEntries::default()] {
+            assert_eq!(entries.estimated_body_size(), serialized_len(entries, None));
+            for precision in [0, 3, 6] {
+                assert_eq!(
+                    entries.estimated_body_size_with(Some(precision)),
+                    serialized_len(entries, Some(precision))
+                );
+            }
+        }
 
-        let response = collection
-            .update(
-                valid_collection_entries,
-                Some(Box::new(MockEmbeddingProvider)),
-            )
-            .await;
-
-        println!("{:?}", response);
-
+        let embeddings = CollectionEntries {
+            ids: vec!["a"],
+            embeddings: Some(vec![(0..768).map(|i| (i as f32).sin()).collect()]),
+            ..Default::default()
+        };
         assert!(
-            response.is_ok(),
-            "Embeddings and documents can both be None"
+            embeddings.estimated_body_size_with(Some(4)) * 2 < embeddings.estimated_body_size()
         );
+    }
 
-        let invalid_collection_entries = CollectionEntries {
-            ids: vec!["test"],
+    #[test]
+    fn test_split_entries_by_bytes() {
+        let ids = ["a", "b", "c", "d", "e"];
+        let documents = vec!["x".repeat(100); 5];
+        let collection_entries = || CollectionEntries {
+            ids: ids.to_vec(),
             metadatas: None,
-            documents: Some(vec!["Document content 1", "Document content 2"]),
+            documents: Some(documents.iter().map(String::as_str).collect()),
             embeddings: None,
         };
-        let response = collection.update(
-            invalid_collection_entries,
-            Some(Box::new(MockEmbeddingProvider)),
+        let max_chunk_bytes = 300;
+        let chunks = split_entries(collection_entries(), 10, Some(max_chunk_bytes), None);
+        let chunk_ids: Vec<Vec<&str>> = chunks.iter().map(|chunk| chunk.ids.clone()).collect();
+        assert_eq!(chunk_ids, vec![vec!["a", "b"], vec!["c", "d"], vec!["e"]]);
+        for chunk in &chunks {
+            assert!(chunk.estimated_body_size() <= max_chunk_bytes);
+            assert_eq!(chunk.documents.as_ref().unwrap().len(), chunk.ids.len());
+        }
+
+        // The record count still caps the chunks, and oversized records go on their own.
+        let chunks = split_entries(collection_entries(), 1, Some(max_chunk_bytes), None);
+        assert_eq!(chunks.len(), 5);
+        let chunks = split_entries(collection_entries(), 10, Some(10), None);
+        assert_eq!(chunks.len(), 5);
+    }
+
+    #[test]
+    fn test_where_filter() {
+        let field = WhereFilter::field;
+        assert_eq!(
+            field("color").eq("red").into_value(),
+            json!({"color": {"$eq": "red"}})
         );
-        assert!(
-            response.await.is_err(),
-            "IDs, embeddings, metadatas, and documents must all be the same length"
+        assert_eq!(
+            field("color").ne("red").into_value(),
+            json!({"color": {"$ne": "red"}})
+        );
+        assert_eq!(
+            field("price").gt(10).into_value(),
+            json!({"price": {"$gt": 10}})
+        );
+        assert_eq!(
+            field("price").gte(10.5).into_value(),
+            json!({"price": {"$gte": 10.5}})
+        );
+        assert_eq!(
+            field("price").lt(10).into_value(),
+            json!({"price": {"$lt": 10}})
+        );
+        assert_eq!(
+            field("price").lte(10).into_value(),
+            json!({"price": {"$lte": 10}})
+        );
+        assert_eq!(
+            field("color").in_(["red", "blue"]).into_value(),
+            json!({"color": {"$in": ["red", "blue"]}})
+        );
+        assert_eq!(
+            field("year").nin([1999, 2000]).into_value(),
+            json!({"year": {"$nin": [1999, 2000]}})
+        );
+        assert_eq!(
+            field("active").eq(true).into_value(),
+            json!({"active": {"$eq": true}})
         );
 
-        let valid_collection_entries = CollectionEntries {
-            ids: vec!["test1", "test2"],
-            metadatas: None,
-            documents: Some(vec!["Document content 1", "Document content 2"]),
-            embeddings: None,
-        };
-        let response = collection.update(
-            valid_collection_entries,
-            Some(Box::new(MockEmbeddingProvider)),
+        let filter = WhereFilter::or([
+            WhereFilter::and([field("price").gte(10.0), field("color").eq("red")]),
+            field("clearance").eq(true),
+        ]);
+        assert_eq!(
+            Value::from(filter),
+            json!({"$or": [
+                {"$and": [{"price": {"$gte": 10.0}}, {"color": {"$eq": "red"}}]},
+                {"clearance": {"$eq": true}},
+            ]})
         );
-        assert!(
-            response.await.is_ok(),
-            "IDs, embeddings, metadatas, and documents must all be the same length"
+        assert_eq!(
+            WhereFilter::and([field("color").eq("red")]).into_value(),
+            json!({"color": {"$eq": "red"}})
         );
 
-        let invalid_collection_entries = CollectionEntries {
-            ids: vec!["test1", ""],
-            metadatas: None,
-            documents: Some(vec!["Document content 1", "Document content 2"]),
-            embeddings: None,
-        };
-        let response = collection.update(
-            invalid_collection_entries,
-            Some(Box::new(MockEmbeddingProvider)),
+        let options = GetOptions::builder()
+            .where_metadata(field("color").eq("red"))
+            .build();
+        assert_eq!(
+            options.where_metadata,
+            Some(json!({"color": {"$eq": "red"}}))
         );
-        assert!(response.await.is_err(), "Empty IDs not allowed");
+    }
 
-        let invalid_collection_entries = CollectionEntries {
-            ids: vec!["test", "test"],
-            metadatas: None,
-            documents: Some(vec!["Document content 1", "Document content 2"]),
-            embeddings: Some(vec![vec![1.0, 2.0], vec![3.0, 4.0]]),
-        };
-        let response = collection.update(invalid_collection_entries, None);
-        assert!(
-            response.await.is_err(),
-            "Expected IDs to be unique. Duplicates not allowed"
+    #[test]
+    fn test_where_document_filter() {
+        assert_eq!(
+            WhereDocumentFilter::contains("octopus").into_value(),
+            json!({"$contains": "octopus"})
+        );
+        assert_eq!(
+            WhereDocumentFilter::not_contains("squid").into_value(),
+            json!({"$not_contains": "squid"})
+        );
+        let filter = WhereDocumentFilter::or([
+            WhereDocumentFilter::and([
+                WhereDocumentFilter::contains("octopus"),
+                WhereDocumentFilter::not_contains("squid"),
+            ]),
+            WhereDocumentFilter::contains("recipe"),
+        ]);
+        assert_eq!(
+            Value::from(filter),
+            json!({"$or": [
+                {"$and": [{"$contains": "octopus"}, {"$not_contains": "squid"}]},
+                {"$contains": "recipe"},
+            ]})
         );
 
-        let collection_entries = CollectionEntries {
-            ids: vec!["test1", "test2"],
-            metadatas: None,
-            documents: Some(vec!["Document content 1", "Document content 2"]),
-            embeddings: None,
+        let options = QueryOptions::builder()
+            .query_embeddings(vec![vec![0.0]])
+            .where_document(WhereDocumentFilter::contains("octopus"))
+            .build()
+            .unwrap();
+        assert_eq!(
+            options.where_document,
+            Some(json!({"$contains": "octopus"}))
+        );
+    }
+
+    #[tokio::test]
+    async fn test_metadata_schema_shared_by_writers() {
+        use crate::schema::{MetadataSchema, MetadataType, METADATA_SCHEMA_KEY};
+        use wiremock::matchers::{body_partial_json, method, path_regex};
+        use wiremock::{Mock, MockServer, Request, ResponseTemplate};
+
+        let server = MockServer::start().await;
+        let schema = MetadataSchema::new()
+            .require("source", MetadataType::String)
+            .require("created_at", MetadataType::Int);
+
+        // The first writer saves the schema into the collection metadata, keeping its other keys.
+        Mock::given(method("PUT"))
+            .and(path_regex("/collections/mock$"))
+            .and(body_partial_json(
+                json!({"new_metadata": {"owner": "search"}}),
+            ))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({})))
+            .expect(1)
+            .mount(&server)
+            .await;
+        let mut writer = mock_collection(&server, None);
+        writer.metadata = Some(json!({"owner": "search"}).as_object().unwrap().clone());
+        writer.save_metadata_schema(schema.clone()).await.unwrap();
+        let stored = writer.metadata().unwrap()[METADATA_SCHEMA_KEY].clone();
+
+        // The second writer loads it from the collection and enforces it.
+        Mock::given(method("GET"))
+            .and(path_regex("/collections/mock$"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "id": "mock",
+                "name": "mock",
+                "metadata": {"owner": "search", METADATA_SCHEMA_KEY: stored},
+            })))
+            .mount(&server)
+            .await;
+        Mock::given(method("POST"))
+            .and(path_regex("/(add|update)$"))
+            .respond_with(|request: &Request| {
+                let body: Value = request.body_json().unwrap();
+                assert_eq!(body["ids"], json!(["good"]));
+                ResponseTemplate::new(200).set_body_json(json!({}))
+            })
+            .expect(2)
+            .mount(&server)
+            .await;
+        let mut other_writer = mock_collection(&server, None);
+        assert!(other_writer.load_metadata_schema().await.unwrap());
+        assert_eq!(other_writer.metadata_schema(), Some(&schema));
+
+        let metadatas = vec![
+            json!({"source": "web", "created_at": 1}),
+            json!({"source": 7, "created_at": 1, "extra": true}),
+            json!({"source": "web"}),
+        ]
+        .into_iter()
+        .map(|m| m.as_object().unwrap().clone())
+        .collect();
+        let entries = CollectionEntries {
+            ids: vec!["good", "bad-types", "missing"],
+            embeddings: Some(vec![vec![1.0]; 3]),
+            metadatas: Some(metadatas),
+            documents: None,
         };
-        let response = collection.update(collection_entries, None);
-        assert!(
-            response.await.is_err(),
-            "embedding_function cannot be None if documents are provided and embeddings are None"
+        let Err(ChromaError::Validation(error)) = other_writer.add(entries, None).await else {
+            panic!("expected a validation error");
+        };
+        let rejected: Vec<(Option<usize>, ValidationRule)> =
+            error.violations.iter().map(|v| (v.index, v.rule)).collect();
+        assert_eq!(
+            rejected,
+            vec![
+                (Some(1), ValidationRule::MetadataSchema),
+                (Some(1), ValidationRule::MetadataSchema),
+                (Some(2), ValidationRule::MetadataSchema),
+            ]
         );
 
-        let collection_entries = CollectionEntries {
-            ids: vec!["test1", "test2"],
-            metadatas: None,
-            documents: Some(vec!["Document content 1", "Document content 2"]),
+        let entries = CollectionEntries {
+            ids: vec!["good"],
+            embeddings: Some(vec![vec![1.0]]),
+            metadatas: Some(vec![json!({"source": "web", "created_at": 1})
+                .as_object()
+                .unwrap()
+                .clone()]),
+            documents: None,
+        };
+        other_writer.add(entries, None).await.unwrap();
+
+        // Updates merge into the existing metadata, so required keys may be left out.
+        let entries = CollectionEntries {
+            ids: vec!["good"],
             embeddings: None,
+            metadatas: Some(vec![json!({"source": "api"}).as_object().unwrap().clone()]),
+            documents: None,
         };
-        let response = collection.update(collection_entries, Some(Box::new(MockEmbeddingProvider)));
-        assert!(
-            response.await.is_ok(),
-            "Embeddings are computed by the embedding_function if embeddings are None and documents are provided"
-        );
+        other_writer.update(entries, None).await.unwrap();
     }
 
     #[tokio::test]
-    async fn test_query_collection() {
-        let client = ChromaClient::new(Default::default());
+    async fn test_empty_batches_and_filters() {
+        use std::sync::Mutex;
+        use wiremock::{Mock, MockServer, ResponseTemplate};
 
-        let collection = client
+        let server = MockServer::start().await;
+        Mock::given(wiremock::matchers::any())
+            .respond_with(ResponseTemplate::new(200))
+            .expect(0)
+            .mount(&server)
+            .await;
+        let warnings = Arc::new(Mutex::new(vec![]));
+        let callback_warnings = warnings.clone();
+        let collection = mock_collection(
+            &server,
+            Some(WarningCallback::new(move |warning| {
+                callback_warnings.lock().unwrap().push(warning.clone());
+            })),
+        );
+
+        // Empty writes and deletes by ids are no-ops that warn.
+        collection
+            .add(CollectionEntries::default(), None)
             .await
-            .unwrap()
-            .get_or_create_collection(TEST_COLLECTION, None)
+            .unwrap();
+        collection
+            .upsert(CollectionEntries::default(), None)
             .await
             .unwrap();
-        assert!(collection.count().await.is_ok());
+        collection
+            .update(CollectionEntries::default(), None)
+            .await
+            .unwrap();
+        collection
+            .delete(Some(vec![]), Some(json!({"color": "red"})), None)
+            .await
+            .unwrap();
+        assert_eq!(
+            *warnings.lock().unwrap(),
+            ["add", "upsert", "update", "delete"]
+                .map(|operation| Warning::EmptyBatch { operation })
+        );
 
-        let query = QueryOptions {
-            query_texts: None,
-            query_embeddings: None,
-            where_metadata: None,
-            where_document: None,
-            n_results: None,
-            include: None,
+        // Empty batches must still be consistent.
+        let inconsistent = CollectionEntries {
+            documents: Some(vec!["orphan document"]),
+            ..Default::default()
         };
-        let query_result = collection.query(query, None);
-        assert!(
-            query_result.await.is_err(),
-            "query_texts and query_embeddings cannot both be None"
-        );
+        assert!(matches!(
+            collection
+                .upsert(inconsistent, Some(Box::new(MockEmbeddingProvider)))
+                .await,
+            Err(ChromaError::Validation(_))
+        ));
 
+        // Empty query inputs are invalid.
         let query = QueryOptions {
-            query_texts: Some(vec![
-                "Writing tests help me find bugs",
-                "Running them does not",
-            ]),
-            query_embeddings: None,
-            where_metadata: None,
-            where_document: None,
-            n_results: None,
-            include: None,
+            query_embeddings: Some(vec![]),
+            ..Default::default()
         };
-        let query_result = collection.query(query, Some(Box::new(MockEmbeddingProvider)));
-        assert!(
-            query_result.await.is_ok(),
-            "query_embeddings will be computed from query_texts if embedding_function is provided"
-        );
-
+        assert!(matches!(
+            collection.query(query, None).await,
+            Err(ChromaError::InvalidArgument { .. })
+        ));
         let query = QueryOptions {
-            query_texts: Some(vec![
-                "Writing tests help me find bugs",
-                "Running them does not",
-            ]),
-            query_embeddings: Some(vec![vec![0.0_f32; 768], vec![0.0_f32; 768]]),
-            where_metadata: None,
-            where_document: None,
-            n_results: None,
-            include: None,
+            query_texts: Some(vec![]),
+            ..Default::default()
         };
-        let query_result = collection.query(query, Some(Box::new(MockEmbeddingProvider)));
-        assert!(
-            query_result.await.is_err(),
-            "Both query_embeddings and query_texts cannot be provided"
-        );
+        assert!(matches!(
+            collection
+                .query(query, Some(Box::new(MockEmbeddingProvider)))
+                .await,
+            Err(ChromaError::InvalidArgument { .. })
+        ));
 
+        // Empty filter objects are rejected.
         let query = QueryOptions {
-            query_texts: None,
-            query_embeddings: Some(vec![vec![0.0_f32; 768], vec![0.0_f32; 768]]),
-            where_metadata: None,
-            where_document: None,
-            n_results: None,
-            include: None,
+            query_embeddings: Some(vec![vec![0.0]]),
+            where_metadata: Some(json!({})),
+            ..Default::default()
         };
-        let query_result = collection.query(query, None);
-        assert!(
-            query_result.await.is_ok(),
-            "Use provided query_embeddings if embedding_function is None"
-        );
+        assert!(matches!(
+            collection.query(query, None).await,
+            Err(ChromaError::InvalidArgument { .. })
+        ));
+        let get = GetOptions::builder().where_document(json!({})).build();
+        assert!(matches!(
+            collection.get(get).await,
+            Err(ChromaError::InvalidArgument { .. })
+        ));
+        assert!(matches!(
+            collection.delete(None, Some(json!({})), None).await,
+            Err(ChromaError::InvalidArgument { .. })
+        ));
     }
 
     #[tokio::test]
-    async fn test_delete_from_collection() {
-        let client = ChromaClient::new(Default::default());
+    async fn test_chunked_respects_max_batch_size() {
+        use wiremock::matchers::method;
+        use wiremock::{Mock, MockServer, ResponseTemplate};
 
-        let collection = client
-            .await
-            .unwrap()
-            .get_or_create_collection(TEST_COLLECTION, None)
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({})))
+            .expect(3)
+            .mount(&server)
+            .await;
+        let collection = mock_collection(&server, None);
+        collection.api.set_max_batch_size(2);
+
+        let collection_entries = CollectionEntries {
+            ids: vec!["a", "b", "c", "d", "e"],
+            embeddings: Some(vec![vec![0.0]; 5]),
+            ..Default::default()
+        };
+        let result = collection
+            .upsert_chunked(collection_entries, 100, None, AddOptions::default())
             .await
             .unwrap();
+        assert_eq!(result.responses.len(), 3);
+    }
 
-        let valid_collection_entries = CollectionEntries {
-            ids: vec!["123ABC"],
+    #[tokio::test]
+    async fn test_delete_all_from_collection() {
+        let client = ChromaClient::new(Default::default()).await.unwrap();
+        let collection = client
+            .get_or_create_collection("delete-all-test", None)
+            .await
+            .unwrap();
+        let collection_entries = CollectionEntries {
+            ids: vec!["a", "b", "c"],
             metadatas: None,
-            documents: Some(vec!["Document content 1"]),
+            documents: Some(vec!["Document a", "Document b", "Document c"]),
             embeddings: None,
         };
+        collection
+            .upsert(collection_entries, Some(Box::new(MockEmbeddingProvider)))
+            .await
+            .unwrap();
 
-        let response = collection.add(
-            valid_collection_entries,
-            Some(Box::new(MockEmbeddingProvider)),
+        let mut deleted = collection.delete_all().await.unwrap();
+        deleted.sort();
+        assert_eq!(deleted, vec!["a", "b", "c"]);
+        assert_eq!(collection.count().await.unwrap(), 0);
+        assert!(collection.delete_all().await.unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_filtered_deletes() {
+        use wiremock::matchers::{body_partial_json, method, path_regex};
+        use wiremock::{Mock, MockServer, ResponseTemplate};
+
+        let server = MockServer::start().await;
+        let collection = mock_collection(&server, None);
+        let filter = json!({"color": "red"});
+        for (where_metadata, where_document) in [
+            (Value::Null, Value::Null),
+            (filter.clone(), Value::Null),
+            (Value::Null, filter.clone()),
+        ] {
+            server.reset().await;
+            let mut get_body = json!({
+                "where": where_metadata,
+                "where_document": where_document,
+                "include": [],
+            });
+            get_body
+                .as_object_mut()
+                .unwrap()
+                .retain(|_, v| !v.is_null());
+            Mock::given(method("POST"))
+                .and(path_regex("/get$"))
+                .and(body_partial_json(&get_body))
+                .respond_with(
+                    ResponseTemplate::new(200).set_body_json(json!({"ids": ["a", "b", "c"]})),
+                )
+                .expect(1)
+                .mount(&server)
+                .await;
+            // One delete carrying the same filters, not the fetched ids.
+            Mock::given(method("POST"))
+                .and(path_regex("/delete$"))
+                .and(body_partial_json(json!({
+                    "ids": null,
+                    "where": where_metadata,
+                    "where_document": where_document,
+                })))
+                .respond_with(ResponseTemplate::new(200))
+                .expect(1)
+                .mount(&server)
+                .await;
+
+            let deleted = match (&where_metadata, &where_document) {
+                (Value::Null, Value::Null) => collection.delete_all().await,
+                (filter, Value::Null) => collection.delete_by_metadata(filter.clone()).await,
+                (_, filter) => collection.delete_by_document(filter.clone()).await,
+            };
+            assert_eq!(deleted.unwrap(), vec!["a", "b", "c"]);
+            server.verify().await;
+            assert_eq!(server.received_requests().await.unwrap().len(), 2);
+        }
+
+        let error = collection.delete_by_metadata(json!({})).await.unwrap_err();
+        assert!(matches!(error, ChromaError::InvalidArgument { .. }));
+    }
+
+    #[tokio::test]
+    async fn test_refresh_checks_id() {
+        use wiremock::matchers::{method, path_regex};
+        use wiremock::{Mock, MockServer, ResponseTemplate};
+
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path_regex("/collections/mock$"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "id": "mock", "name": "mock", "metadata": {"a": 1}, "dimension": 3,
+            })))
+            .up_to_n_times(1)
+            .mount(&server)
+            .await;
+        // Renamed elsewhere, and another collection took the name.
+        Mock::given(method("GET"))
+            .and(path_regex("/collections/mock$"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "id": "other", "name": "mock", "metadata": {"b": 2}, "dimension": 5,
+            })))
+            .mount(&server)
+            .await;
+        let mut collection = mock_collection(&server, None);
+
+        collection.refresh().await.unwrap();
+        assert_eq!(collection.dimension(), Some(3));
+        assert_eq!(
+            collection.metadata(),
+            Some(&json!({"a": 1}).as_object().unwrap().clone())
         );
-        assert!(response.await.is_ok());
 
-        let response = collection.delete(Some(vec!["123ABC"]), None, None).await;
+        let error = collection.refresh().await.unwrap_err();
+        assert!(
+            matches!(error, ChromaError::CollectionNotFound(_)),
+            "{error}"
+        );
+        assert_eq!(collection.id(), "mock");
+        assert_eq!(collection.dimension(), Some(3));
+    }
 
-        assert!(response.is_ok(),);
+    #[tokio::test]
+    async fn test_similarity_search_filters() {
+        use wiremock::matchers::{method, path_regex};
+        use wiremock::{Mock, MockServer, ResponseTemplate};
+
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path_regex("/query$"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "ids": [["a"]],
+                "distances": [[0.25]],
+            })))
+            .mount(&server)
+            .await;
+        let collection = mock_collection(&server, None);
+        let not_deleted = json!({"deleted": {"$ne": true}});
+        let red = json!({"color": "red"});
+
+        // Without an attached embedding function, one must be supplied.
+        assert!(collection
+            .similarity_search("frog", 1, None, None)
+            .await
+            .is_err());
+        let hits = collection
+            .similarity_search(
+                "frog",
+                1,
+                Some(red.clone()),
+                Some(Arc::new(MockEmbeddingProvider)),
+            )
+            .await
+            .unwrap();
+        assert_eq!(hits[0].id, "a");
+
+        let collection = collection
+            .with_embedding_function(Arc::new(MockEmbeddingProvider))
+            .with_exclusion_filter(not_deleted.clone());
+        collection
+            .similarity_search("frog", 1, Some(red.clone()), None)
+            .await
+            .unwrap();
+        collection
+            .similarity_search("frog", 1, None, None)
+            .await
+            .unwrap();
+
+        let wheres: Vec<Value> = server
+            .received_requests()
+            .await
+            .unwrap()
+            .iter()
+            .map(|request| request.body_json::<Value>().unwrap()["where"].clone())
+            .collect();
+        assert_eq!(
+            wheres,
+            [
+                red.clone(),
+                json!({"$and": [not_deleted.clone(), red]}),
+                not_deleted,
+            ]
+        );
     }
 }
@@ -1,6 +1,8 @@
 use serde_json::{Map, Value};
 
-pub(super) type Result<T> = anyhow::Result<T>;
+use crate::error::ChromaError;
+
+pub(super) type Result<T> = std::result::Result<T, ChromaError>;
 pub(super) type ConfigurationJson = Map<String, Value>;
 pub(super) type Metadata = Map<String, Value>;
 pub(super) type Metadatas = Vec<Metadata>;
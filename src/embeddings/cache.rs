@@ -0,0 +1,200 @@
+use std::collections::{HashMap, HashSet};
+
+use anyhow::{bail, Result};
+use async_trait::async_trait;
+
+use super::EmbeddingFunction;
+use crate::commons::Embedding;
+
+/// A cache of embeddings, keyed by the embedded document text.
+pub trait EmbeddingCache: Send + Sync {
+    fn get(&self, text: &str) -> Option<Embedding>;
+
+    fn insert(&self, text: &str, embedding: Embedding);
+}
+
+/// Wraps an embedding function to only embed the documents missing from the cache.
+///
+/// Repeated upserts of unchanged documents are then served from the cache instead of the provider.
+pub struct CachedEmbeddingFunction<F: EmbeddingFunction, C: EmbeddingCache> {
+    inner: F,
+    cache: C,
+}
+
+impl<F: EmbeddingFunction, C: EmbeddingCache> CachedEmbeddingFunction<F, C> {
+    pub fn new(inner: F, cache: C) -> Self {
+        Self { inner, cache }
+    }
+
+    pub fn inner(&self) -> &F {
+        &self.inner
+    }
+
+    pub fn cache(&self) -> &C {
+        &self.cache
+    }
+}
+
+#[async_trait]
+impl<F: EmbeddingFunction, C: EmbeddingCache> EmbeddingFunction for CachedEmbeddingFunction<F, C> {
+    async fn embed(&self, docs: &[&str]) -> Result<Vec<Embedding>> {
+        self.embed_parallel(docs, 1).await
+    }
+
+    /// Only the cache misses are passed on to the inner function's [embed_parallel](EmbeddingFunction::embed_parallel).
+    async fn embed_parallel(&self, docs: &[&str], concurrency: usize) -> Result<Vec<Embedding>> {
+        let mut found: HashMap<&str, Embedding> = HashMap::new();
+        let mut seen = HashSet::new();
+        let mut misses = vec![];
+        for &doc in docs {
+            if !seen.insert(doc) {
+                continue;
+            }
+            match self.cache.get(doc) {
+                Some(embedding) => {
+                    found.insert(doc, embedding);
+                }
+                None => misses.push(doc),
+            }
+        }
+
+        if !misses.is_empty() {
+            let embeddings = self.inner.embed_parallel(&misses, concurrency).await?;
+            if embeddings.len() != misses.len() {
+                bail!(
+                    "Embedding function returned {} embeddings for {} documents",
+                    embeddings.len(),
+                    misses.len()
+                );
+            }
+            for (doc, embedding) in misses.into_iter().zip(embeddings) {
+                self.cache.insert(doc, embedding.clone());
+                found.insert(doc, embedding);
+            }
+        }
+
+        Ok(docs.iter().map(|doc| found[doc].clone()).collect())
+    }
+
+    fn identity(&self) -> Option<String> {
+        self.inner.identity()
+    }
+
+    async fn health_check(&self) -> Result<()> {
+        self.inner.health_check().await
+    }
+}
+
+/// An in-memory cache evicting the least recently used embeddings beyond its capacity.
+#[cfg(feature = "embedding-cache")]
+pub struct InMemoryLruCache {
+    cache: std::sync::Mutex<lru::LruCache<String, Embedding>>,
+}
+
+#[cfg(feature = "embedding-cache")]
+impl InMemoryLruCache {
+    /// A cache holding at most `capacity` embeddings, or one if `capacity` is 0.
+    pub fn new(capacity: usize) -> Self {
+        let capacity = std::num::NonZeroUsize::new(capacity).unwrap_or(std::num::NonZeroUsize::MIN);
+        Self {
+            cache: std::sync::Mutex::new(lru::LruCache::new(capacity)),
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.lock().len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.lock().is_empty()
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, lru::LruCache<String, Embedding>> {
+        // The cache is never left half-updated, so a poisoned lock is still usable.
+        self.cache
+            .lock()
+            .unwrap_or_else(std::sync::PoisonError::into_inner)
+    }
+}
+
+#[cfg(feature = "embedding-cache")]
+impl EmbeddingCache for InMemoryLruCache {
+    fn get(&self, text: &str) -> Option<Embedding> {
+        self.lock().get(text).cloned()
+    }
+
+    fn insert(&self, text: &str, embedding: Embedding) {
+        self.lock().put(text.to_string(), embedding);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Mutex;
+
+    /// Embeds each document as its length, counting the documents it was asked to embed.
+    #[derive(Default)]
+    struct CountingProvider {
+        embedded: AtomicUsize,
+    }
+
+    #[async_trait]
+    impl EmbeddingFunction for CountingProvider {
+        async fn embed(&self, docs: &[&str]) -> Result<Vec<Embedding>> {
+            self.embedded.fetch_add(docs.len(), Ordering::SeqCst);
+            Ok(docs.iter().map(|doc| vec![doc.len() as f32]).collect())
+        }
+    }
+
+    #[derive(Default)]
+    struct MapCache(Mutex<HashMap<String, Embedding>>);
+
+    impl EmbeddingCache for MapCache {
+        fn get(&self, text: &str) -> Option<Embedding> {
+            self.0.lock().unwrap().get(text).cloned()
+        }
+
+        fn insert(&self, text: &str, embedding: Embedding) {
+            self.0.lock().unwrap().insert(text.to_string(), embedding);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_cache_hits_skip_the_provider() {
+        let cached = CachedEmbeddingFunction::new(CountingProvider::default(), MapCache::default());
+        assert_eq!(
+            cached.embed(&["frog", "cow", "frog"]).await.unwrap(),
+            vec![vec![4.0], vec![3.0], vec![4.0]]
+        );
+        assert_eq!(cached.inner().embedded.load(Ordering::SeqCst), 2);
+
+        assert_eq!(
+            cached.embed(&["cow", "horse", "frog"]).await.unwrap(),
+            vec![vec![3.0], vec![5.0], vec![4.0]]
+        );
+        assert_eq!(cached.inner().embedded.load(Ordering::SeqCst), 3);
+
+        cached.embed(&["cow", "horse"]).await.unwrap();
+        assert_eq!(cached.inner().embedded.load(Ordering::SeqCst), 3);
+    }
+
+    #[cfg(feature = "embedding-cache")]
+    #[tokio::test]
+    async fn test_in_memory_lru_cache() {
+        let cached =
+            CachedEmbeddingFunction::new(CountingProvider::default(), InMemoryLruCache::new(2));
+        cached.embed(&["frog", "cow"]).await.unwrap();
+        // Touch "frog" so that "cow" is the least recently used.
+        cached.embed(&["frog"]).await.unwrap();
+        cached.embed(&["horse"]).await.unwrap();
+        assert_eq!(cached.cache().len(), 2);
+        assert_eq!(cached.inner().embedded.load(Ordering::SeqCst), 3);
+
+        cached.embed(&["frog", "horse"]).await.unwrap();
+        assert_eq!(cached.inner().embedded.load(Ordering::SeqCst), 3);
+        cached.embed(&["cow"]).await.unwrap();
+        assert_eq!(cached.inner().embedded.load(Ordering::SeqCst), 4);
+    }
+}
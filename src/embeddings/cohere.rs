@@ -0,0 +1,217 @@
+use anyhow::bail;
+use async_trait::async_trait;
+use reqwest::StatusCode;
+use serde::{Deserialize, Serialize};
+
+use super::{map_probe_error, validate_probe_embeddings, EmbeddingFunction, HEALTH_CHECK_PROBE};
+use crate::commons::Embedding;
+
+const COHERE_EMBEDDINGS_ENDPOINT: &str = "https://api.cohere.com/v1/embed";
+const COHERE_EMBEDDINGS_MODEL: &str = "embed-english-v3.0";
+
+/// The kind of input being embedded. Cohere v3 models embed documents and queries differently.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CohereInputType {
+    #[default]
+    SearchDocument,
+    SearchQuery,
+    Classification,
+    Clustering,
+}
+
+#[derive(Debug, Serialize)]
+struct EmbeddingRequest<'a> {
+    pub model: &'a str,
+    pub texts: &'a [&'a str],
+    pub input_type: CohereInputType,
+}
+
+#[derive(Debug, Deserialize)]
+struct EmbeddingResponse {
+    pub embeddings: Vec<Embedding>,
+}
+
+/// Represents the Cohere Embeddings provider
+pub struct CohereEmbeddings {
+    config: CohereConfig,
+    client: reqwest::Client,
+}
+
+/// Defaults to the "embed-english-v3.0" model, embedding search documents
+/// The API key can be set in the COHERE_API_KEY environment variable
+pub struct CohereConfig {
+    pub api_endpoint: String,
+    pub api_key: String,
+    pub model: String,
+    pub input_type: CohereInputType,
+}
+
+impl Default for CohereConfig {
+    fn default() -> Self {
+        Self {
+            api_endpoint: COHERE_EMBEDDINGS_ENDPOINT.to_string(),
+            api_key: std::env::var("COHERE_API_KEY").expect("COHERE_API_KEY env is not set"),
+            model: COHERE_EMBEDDINGS_MODEL.to_string(),
+            input_type: CohereInputType::default(),
+        }
+    }
+}
+
+impl CohereEmbeddings {
+    pub fn new(config: CohereConfig) -> Self {
+        Self {
+            config,
+            client: reqwest::Client::new(),
+        }
+    }
+
+    async fn post(&self, docs: &[&str]) -> anyhow::Result<Vec<Embedding>> {
+        let req = EmbeddingRequest {
+            model: &self.config.model,
+            texts: docs,
+            input_type: self.config.input_type,
+        };
+        let res = self
+            .client
+            .post(&self.config.api_endpoint)
+            .header("Authorization", format!("Bearer {}", self.config.api_key))
+            .json(&req)
+            .send()
+            .await?
+            .error_for_status()?;
+        let body = res.json::<EmbeddingResponse>().await?;
+        if body.embeddings.len() != docs.len() {
+            bail!(
+                "Cohere returned {} embeddings for {} documents",
+                body.embeddings.len(),
+                docs.len()
+            );
+        }
+        Ok(body.embeddings)
+    }
+}
+
+#[async_trait]
+impl EmbeddingFunction for CohereEmbeddings {
+    /// Embed all the documents in a single request.
+    async fn embed(&self, docs: &[&str]) -> anyhow::Result<Vec<Embedding>> {
+        if docs.is_empty() {
+            return Ok(vec![]);
+        }
+        self.post(docs).await
+    }
+
+    fn identity(&self) -> Option<String> {
+        Some(format!("cohere:{}", self.config.model))
+    }
+
+    /// Embed a one-word probe, turning authentication and model errors into actionable messages.
+    async fn health_check(&self) -> anyhow::Result<()> {
+        let embeddings = self.post(&[HEALTH_CHECK_PROBE]).await.map_err(|e| {
+            map_probe_error(
+                "Cohere",
+                "the COHERE_API_KEY env or CohereConfig::api_key",
+                &self.config.model,
+                "CohereConfig::model",
+                &[StatusCode::NOT_FOUND, StatusCode::BAD_REQUEST],
+                e,
+            )
+        })?;
+        validate_probe_embeddings(&embeddings)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::collection::CollectionEntries;
+    use crate::embeddings::mock_provider_status;
+    use crate::ChromaClient;
+    use serde_json::json;
+    use wiremock::matchers::{body_json, header, method};
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+
+    fn test_config(server: &MockServer) -> CohereConfig {
+        CohereConfig {
+            api_endpoint: format!("{}/v1/embed", server.uri()),
+            api_key: "test-key".to_string(),
+            model: COHERE_EMBEDDINGS_MODEL.to_string(),
+            input_type: CohereInputType::SearchQuery,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_cohere_embeddings() {
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(header("Authorization", "Bearer test-key"))
+            .and(body_json(json!({
+                "model": COHERE_EMBEDDINGS_MODEL,
+                "texts": ["Once upon a time there was a frog", "Once upon a time there was a cow"],
+                "input_type": "search_query",
+            })))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "embeddings": [[0.1, 0.2], [0.3, 0.4]],
+            })))
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        let cohere_embeddings = CohereEmbeddings::new(test_config(&server));
+        let embeddings = cohere_embeddings
+            .embed(&[
+                "Once upon a time there was a frog",
+                "Once upon a time there was a cow",
+            ])
+            .await
+            .unwrap();
+        assert_eq!(embeddings, vec![vec![0.1, 0.2], vec![0.3, 0.4]]);
+    }
+
+    #[tokio::test]
+    async fn test_cohere_health_check() {
+        let server = mock_provider_status(401).await;
+        let cohere_embeddings = CohereEmbeddings::new(test_config(&server));
+        let error = cohere_embeddings.health_check().await.unwrap_err();
+        assert!(error.to_string().contains("COHERE_API_KEY"));
+    }
+
+    #[tokio::test]
+    async fn test_cohere_upsert() {
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "embeddings": vec![vec![0.0_f32; 768]; 3],
+            })))
+            .mount(&server)
+            .await;
+
+        let client = ChromaClient::new(Default::default());
+        let collection = client
+            .await
+            .unwrap()
+            .get_or_create_collection("cohere-test-collection", None)
+            .await
+            .unwrap();
+        let cohere_embeddings = CohereEmbeddings::new(test_config(&server));
+
+        let docs = vec![
+            "Once upon a time there was a frog",
+            "Once upon a time there was a cow",
+            "Once upon a time there was a wolverine",
+        ];
+
+        let collection_entries = CollectionEntries {
+            ids: vec!["test1", "test2", "test3"],
+            metadatas: None,
+            documents: Some(docs),
+            embeddings: None,
+        };
+
+        collection
+            .upsert(collection_entries, Some(Box::new(cohere_embeddings)))
+            .await
+            .unwrap();
+    }
+}
@@ -0,0 +1,122 @@
+use std::sync::Arc;
+
+use async_trait::async_trait;
+use fastembed::{InitOptions, TextEmbedding};
+
+use super::EmbeddingFunction;
+use crate::commons::Embedding;
+
+/// Local embeddings with [fastembed](https://github.com/Anush008/fastembed-rs), running ONNX models like `BAAI/bge-small-en-v1.5`
+/// in process, without network calls once the model is downloaded.
+///
+/// Loading the model is expensive, so clones share the loaded model.
+#[derive(Clone)]
+pub struct FastEmbedEmbeddings {
+    model: Arc<TextEmbedding>,
+    identity: String,
+}
+
+impl FastEmbedEmbeddings {
+    /// Load the model, downloading it to `options.cache_dir` if it is not there yet.
+    /// This blocks the thread until the model is loaded, call it from `spawn_blocking` in async code.
+    pub fn new(options: InitOptions) -> anyhow::Result<Self> {
+        let model_info = TextEmbedding::get_model_info(&options.model_name)?;
+        let identity = format!("fastembed:{}", model_info.model_code);
+        let model = TextEmbedding::try_new(options)?;
+        Ok(Self {
+            model: Arc::new(model),
+            identity,
+        })
+    }
+}
+
+#[async_trait]
+impl EmbeddingFunction for FastEmbedEmbeddings {
+    /// Embed the documents on the blocking thread pool, as inference blocks for as long as it runs.
+    async fn embed(&self, docs: &[&str]) -> anyhow::Result<Vec<Embedding>> {
+        let model = self.model.clone();
+        let docs: Vec<String> = docs.iter().map(|doc| doc.to_string()).collect();
+        tokio::task::spawn_blocking(move || model.embed(docs, None)).await?
+    }
+
+    fn identity(&self) -> Option<String> {
+        Some(self.identity.clone())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::client::{ChromaClient, ChromaClientOptions};
+    use crate::collection::CollectionEntries;
+    use fastembed::EmbeddingModel;
+    use serde_json::{json, Value};
+    use wiremock::matchers::{method, path_regex};
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+
+    #[tokio::test]
+    async fn test_fastembed_upsert() {
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path_regex("/collections$"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "id": "c1",
+                "name": "fastembed",
+                "metadata": null,
+            })))
+            .mount(&server)
+            .await;
+        Mock::given(method("POST"))
+            .and(path_regex("/collections/c1/upsert$"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(true))
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        let options = InitOptions::new(EmbeddingModel::AllMiniLML6V2);
+        let embeddings = tokio::task::spawn_blocking(move || FastEmbedEmbeddings::new(options))
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(
+            embeddings.identity().as_deref(),
+            Some("fastembed:Qdrant/all-MiniLM-L6-v2-onnx")
+        );
+
+        let options = ChromaClientOptions::builder()
+            .url(server.uri())
+            .build()
+            .unwrap();
+        let client = ChromaClient::new(options).await.unwrap();
+        let collection = client
+            .get_or_create_collection("fastembed", None)
+            .await
+            .unwrap();
+        let collection_entries = CollectionEntries {
+            ids: vec!["1", "2", "3"],
+            metadatas: None,
+            documents: Some(vec![
+                "9 octopus recipes",
+                "Superman vs Superman",
+                "How to cook an octopus",
+            ]),
+            embeddings: None,
+        };
+        collection
+            .upsert(collection_entries, Some(Box::new(embeddings.clone())))
+            .await
+            .unwrap();
+
+        let requests = server.received_requests().await.unwrap();
+        let upsert = requests
+            .iter()
+            .find(|request| request.url.path().ends_with("/upsert"))
+            .unwrap();
+        let body: Value = serde_json::from_slice(&upsert.body).unwrap();
+        let sent = body["embeddings"].as_array().unwrap();
+        assert_eq!(sent.len(), 3);
+        assert!(sent
+            .iter()
+            .all(|embedding| embedding.as_array().unwrap().len() == 384));
+    }
+}
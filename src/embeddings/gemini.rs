@@ -0,0 +1,220 @@
+use async_trait::async_trait;
+use reqwest::StatusCode;
+use serde::{Deserialize, Serialize};
+
+use super::{map_probe_error, validate_probe_embeddings, EmbeddingFunction, HEALTH_CHECK_PROBE};
+use crate::commons::Embedding;
+
+const GEMINI_API_ENDPOINT: &str = "https://generativelanguage.googleapis.com/v1beta";
+const GEMINI_EMBEDDINGS_MODEL: &str = "text-embedding-004";
+const GEMINI_CONCURRENCY: usize = 8;
+
+/// What the embeddings are for. Gemini models adapt the embeddings to it.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum GeminiTaskType {
+    /// Documents to search, for retrieval.
+    #[default]
+    RetrievalDocument,
+    /// Search queries, for retrieval.
+    RetrievalQuery,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct EmbedContentRequest<'a> {
+    pub content: Content<'a>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub task_type: Option<GeminiTaskType>,
+}
+
+#[derive(Debug, Serialize)]
+struct Content<'a> {
+    pub parts: [Part<'a>; 1],
+}
+
+#[derive(Debug, Serialize)]
+struct Part<'a> {
+    pub text: &'a str,
+}
+
+#[derive(Debug, Deserialize)]
+struct EmbedContentResponse {
+    pub embedding: ContentEmbedding,
+}
+
+#[derive(Debug, Deserialize)]
+struct ContentEmbedding {
+    pub values: Embedding,
+}
+
+/// Represents the Google Gemini Embeddings provider
+pub struct GeminiEmbeddings {
+    config: GeminiConfig,
+    client: reqwest::Client,
+}
+
+/// Defaults to the "text-embedding-004" model, embedding documents for retrieval
+/// The API key can be set in the GOOGLE_API_KEY environment variable
+pub struct GeminiConfig {
+    /// The base URL of the API, the model's `:embedContent` path is appended to it.
+    pub api_endpoint: String,
+    pub api_key: String,
+    pub model: String,
+    /// Leave it out for the model's default.
+    pub task_type: Option<GeminiTaskType>,
+    /// The API embeds one document per request, this many are sent at once.
+    pub concurrency: usize,
+}
+
+impl Default for GeminiConfig {
+    fn default() -> Self {
+        Self {
+            api_endpoint: GEMINI_API_ENDPOINT.to_string(),
+            api_key: std::env::var("GOOGLE_API_KEY").expect("GOOGLE_API_KEY env is not set"),
+            model: GEMINI_EMBEDDINGS_MODEL.to_string(),
+            task_type: Some(GeminiTaskType::default()),
+            concurrency: GEMINI_CONCURRENCY,
+        }
+    }
+}
+
+impl GeminiEmbeddings {
+    pub fn new(config: GeminiConfig) -> Self {
+        Self {
+            config,
+            client: reqwest::Client::new(),
+        }
+    }
+
+    async fn post(&self, doc: &str) -> anyhow::Result<Embedding> {
+        let req = EmbedContentRequest {
+            content: Content {
+                parts: [Part { text: doc }],
+            },
+            task_type: self.config.task_type,
+        };
+        let url = format!(
+            "{}/models/{}:embedContent",
+            self.config.api_endpoint, self.config.model
+        );
+        let res = self
+            .client
+            .post(url)
+            .header("x-goog-api-key", &self.config.api_key)
+            .json(&req)
+            .send()
+            .await?
+            .error_for_status()?;
+        Ok(res.json::<EmbedContentResponse>().await?.embedding.values)
+    }
+}
+
+#[async_trait]
+impl EmbeddingFunction for GeminiEmbeddings {
+    /// Embed the documents with one request each, `concurrency` at a time.
+    async fn embed(&self, docs: &[&str]) -> anyhow::Result<Vec<Embedding>> {
+        let mut embeddings = Vec::with_capacity(docs.len());
+        for wave in docs.chunks(self.config.concurrency.max(1)) {
+            let results = futures::future::join_all(wave.iter().map(|doc| self.post(doc))).await;
+            for result in results {
+                embeddings.push(result?);
+            }
+        }
+        Ok(embeddings)
+    }
+
+    fn identity(&self) -> Option<String> {
+        Some(format!("gemini:{}", self.config.model))
+    }
+
+    /// Embed a one-word probe, turning authentication and model errors into actionable messages.
+    async fn health_check(&self) -> anyhow::Result<()> {
+        let embedding = self.post(HEALTH_CHECK_PROBE).await.map_err(|e| {
+            map_probe_error(
+                "Gemini",
+                "the GOOGLE_API_KEY env or GeminiConfig::api_key",
+                &self.config.model,
+                "GeminiConfig::model and task_type",
+                &[StatusCode::NOT_FOUND, StatusCode::BAD_REQUEST],
+                e,
+            )
+        })?;
+        validate_probe_embeddings(&[embedding])
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::embeddings::mock_provider_status;
+    use serde_json::{json, Value};
+    use wiremock::matchers::{header, method, path};
+    use wiremock::{Mock, MockServer, Request, ResponseTemplate};
+
+    fn test_config(server: &MockServer) -> GeminiConfig {
+        GeminiConfig {
+            api_endpoint: format!("{}/v1beta", server.uri()),
+            api_key: "test-key".to_string(),
+            model: GEMINI_EMBEDDINGS_MODEL.to_string(),
+            task_type: Some(GeminiTaskType::RetrievalQuery),
+            concurrency: 2,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_gemini_embeddings() {
+        let server = MockServer::start().await;
+        // Embeds each document as its length, answering the shorter ones later so that they finish out of order.
+        Mock::given(method("POST"))
+            .and(path("/v1beta/models/text-embedding-004:embedContent"))
+            .and(header("x-goog-api-key", "test-key"))
+            .respond_with(|request: &Request| {
+                let body: Value = request.body_json().unwrap();
+                assert_eq!(body["taskType"], "RETRIEVAL_QUERY");
+                let text = body["content"]["parts"][0]["text"].as_str().unwrap();
+                ResponseTemplate::new(200)
+                    .set_delay(std::time::Duration::from_millis(
+                        50_u64.saturating_sub(text.len() as u64 * 10),
+                    ))
+                    .set_body_json(json!({"embedding": {"values": [text.len(), 0.5]}}))
+            })
+            .expect(3)
+            .mount(&server)
+            .await;
+
+        let gemini_embeddings = GeminiEmbeddings::new(test_config(&server));
+        let embeddings = gemini_embeddings.embed(&["a", "bb", "ccc"]).await.unwrap();
+        assert_eq!(
+            embeddings,
+            vec![vec![1.0, 0.5], vec![2.0, 0.5], vec![3.0, 0.5]]
+        );
+        assert!(gemini_embeddings.embed(&[]).await.unwrap().is_empty());
+        assert_eq!(
+            gemini_embeddings.identity().as_deref(),
+            Some("gemini:text-embedding-004")
+        );
+    }
+
+    #[test]
+    fn test_gemini_request() {
+        let request = EmbedContentRequest {
+            content: Content {
+                parts: [Part { text: "frog" }],
+            },
+            task_type: Some(GeminiTaskType::RetrievalDocument),
+        };
+        assert_eq!(
+            serde_json::to_value(request).unwrap(),
+            json!({"content": {"parts": [{"text": "frog"}]}, "taskType": "RETRIEVAL_DOCUMENT"})
+        );
+    }
+
+    #[tokio::test]
+    async fn test_gemini_health_check() {
+        let server = mock_provider_status(403).await;
+        let gemini_embeddings = GeminiEmbeddings::new(test_config(&server));
+        let error = gemini_embeddings.health_check().await.unwrap_err();
+        assert!(error.to_string().contains("GOOGLE_API_KEY"));
+    }
+}
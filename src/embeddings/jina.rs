@@ -0,0 +1,245 @@
+use anyhow::bail;
+use async_trait::async_trait;
+use reqwest::StatusCode;
+use serde::{Deserialize, Serialize};
+
+use super::{map_probe_error, validate_probe_embeddings, EmbeddingFunction, HEALTH_CHECK_PROBE};
+use crate::commons::Embedding;
+
+const JINA_EMBEDDINGS_ENDPOINT: &str = "https://api.jina.ai/v1/embeddings";
+const JINA_EMBEDDINGS_MODEL: &str = "jina-embeddings-v3";
+
+/// The downstream task the embeddings are for. Jina v3 models adapt the embeddings to it.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize)]
+pub enum JinaTask {
+    /// Documents to search, for retrieval.
+    #[default]
+    #[serde(rename = "retrieval.passage")]
+    RetrievalPassage,
+    /// Search queries, for retrieval.
+    #[serde(rename = "retrieval.query")]
+    RetrievalQuery,
+    /// Texts compared symmetrically, e.g. for semantic similarity.
+    #[serde(rename = "text-matching")]
+    TextMatching,
+    #[serde(rename = "classification")]
+    Classification,
+    /// Texts to cluster or rerank.
+    #[serde(rename = "separation")]
+    Separation,
+}
+
+#[derive(Debug, Serialize)]
+struct EmbeddingRequest<'a> {
+    pub input: &'a [&'a str],
+    pub model: &'a str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub task: Option<JinaTask>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub dimensions: Option<u32>,
+}
+
+#[derive(Debug, Deserialize)]
+struct EmbeddingResponse {
+    pub data: Vec<EmbeddingData>,
+}
+
+#[derive(Debug, Deserialize)]
+struct EmbeddingData {
+    pub embedding: Embedding,
+    pub index: usize,
+}
+
+/// Represents the Jina AI Embeddings provider
+pub struct JinaEmbeddings {
+    config: JinaConfig,
+    client: reqwest::Client,
+}
+
+/// Defaults to the "jina-embeddings-v3" model, embedding documents for retrieval at the model's full dimension
+/// The API key can be set in the JINA_API_KEY environment variable
+pub struct JinaConfig {
+    pub api_endpoint: String,
+    pub api_key: String,
+    pub model: String,
+    /// Leave it out for models without task adapters, like the v2 models.
+    pub task: Option<JinaTask>,
+    /// Truncate the embeddings to this many dimensions, for models trained with Matryoshka representation learning.
+    pub dimensions: Option<u32>,
+}
+
+impl Default for JinaConfig {
+    fn default() -> Self {
+        Self {
+            api_endpoint: JINA_EMBEDDINGS_ENDPOINT.to_string(),
+            api_key: std::env::var("JINA_API_KEY").expect("JINA_API_KEY env is not set"),
+            model: JINA_EMBEDDINGS_MODEL.to_string(),
+            task: Some(JinaTask::default()),
+            dimensions: None,
+        }
+    }
+}
+
+impl JinaEmbeddings {
+    pub fn new(config: JinaConfig) -> Self {
+        Self {
+            config,
+            client: reqwest::Client::new(),
+        }
+    }
+
+    async fn post(&self, docs: &[&str]) -> anyhow::Result<Vec<Embedding>> {
+        let req = EmbeddingRequest {
+            input: docs,
+            model: &self.config.model,
+            task: self.config.task,
+            dimensions: self.config.dimensions,
+        };
+        let res = self
+            .client
+            .post(&self.config.api_endpoint)
+            .header("Authorization", format!("Bearer {}", self.config.api_key))
+            .json(&req)
+            .send()
+            .await?
+            .error_for_status()?;
+        let mut body = res.json::<EmbeddingResponse>().await?;
+        if body.data.len() != docs.len() {
+            bail!(
+                "Jina returned {} embeddings for {} documents",
+                body.data.len(),
+                docs.len()
+            );
+        }
+        body.data.sort_by_key(|data| data.index);
+        Ok(body.data.into_iter().map(|data| data.embedding).collect())
+    }
+}
+
+#[async_trait]
+impl EmbeddingFunction for JinaEmbeddings {
+    /// Embed all the documents in a single request.
+    async fn embed(&self, docs: &[&str]) -> anyhow::Result<Vec<Embedding>> {
+        if docs.is_empty() {
+            return Ok(vec![]);
+        }
+        self.post(docs).await
+    }
+
+    /// Includes the dimensions when truncating, since truncated embeddings are not comparable with full ones.
+    fn identity(&self) -> Option<String> {
+        Some(match self.config.dimensions {
+            Some(dimensions) => format!("jina:{}@{dimensions}", self.config.model),
+            None => format!("jina:{}", self.config.model),
+        })
+    }
+
+    /// Embed a one-word probe, turning authentication and model errors into actionable messages.
+    async fn health_check(&self) -> anyhow::Result<()> {
+        let embeddings = self.post(&[HEALTH_CHECK_PROBE]).await.map_err(|e| {
+            map_probe_error(
+                "Jina",
+                "the JINA_API_KEY env or JinaConfig::api_key",
+                &self.config.model,
+                "JinaConfig::model, task and dimensions",
+                &[
+                    StatusCode::NOT_FOUND,
+                    StatusCode::BAD_REQUEST,
+                    StatusCode::UNPROCESSABLE_ENTITY,
+                ],
+                e,
+            )
+        })?;
+        validate_probe_embeddings(&embeddings)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::embeddings::mock_provider_status;
+    use serde_json::json;
+    use wiremock::matchers::{body_json, header, method};
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+
+    fn test_config(server: &MockServer) -> JinaConfig {
+        JinaConfig {
+            api_endpoint: format!("{}/v1/embeddings", server.uri()),
+            api_key: "test-key".to_string(),
+            model: JINA_EMBEDDINGS_MODEL.to_string(),
+            task: Some(JinaTask::RetrievalQuery),
+            dimensions: Some(2),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_jina_embeddings() {
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(header("Authorization", "Bearer test-key"))
+            .and(body_json(json!({
+                "input": ["Once upon a time there was a frog", "Once upon a time there was a cow"],
+                "model": JINA_EMBEDDINGS_MODEL,
+                "task": "retrieval.query",
+                "dimensions": 2,
+            })))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "model": JINA_EMBEDDINGS_MODEL,
+                "object": "list",
+                "usage": {"total_tokens": 16, "prompt_tokens": 16},
+                "data": [
+                    {"object": "embedding", "index": 1, "embedding": [0.3, 0.4]},
+                    {"object": "embedding", "index": 0, "embedding": [0.1, 0.2]},
+                ],
+            })))
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        let jina_embeddings = JinaEmbeddings::new(test_config(&server));
+        let embeddings = jina_embeddings
+            .embed(&[
+                "Once upon a time there was a frog",
+                "Once upon a time there was a cow",
+            ])
+            .await
+            .unwrap();
+        assert_eq!(embeddings, vec![vec![0.1, 0.2], vec![0.3, 0.4]]);
+        assert_eq!(
+            jina_embeddings.identity().as_deref(),
+            Some("jina:jina-embeddings-v3@2")
+        );
+    }
+
+    #[test]
+    fn test_jina_request_without_task() {
+        let request = EmbeddingRequest {
+            input: &["frog"],
+            model: "jina-embeddings-v2-base-en",
+            task: None,
+            dimensions: None,
+        };
+        assert_eq!(
+            serde_json::to_value(request).unwrap(),
+            json!({"input": ["frog"], "model": "jina-embeddings-v2-base-en"})
+        );
+        assert_eq!(
+            serde_json::to_value(JinaTask::TextMatching).unwrap(),
+            json!("text-matching")
+        );
+    }
+
+    #[tokio::test]
+    async fn test_jina_health_check() {
+        let server = mock_provider_status(401).await;
+        let jina_embeddings = JinaEmbeddings::new(test_config(&server));
+        let error = jina_embeddings.health_check().await.unwrap_err();
+        assert!(error.to_string().contains("JINA_API_KEY"));
+
+        // Jina answers unsupported tasks or dimensions with a 422.
+        let server = mock_provider_status(422).await;
+        let jina_embeddings = JinaEmbeddings::new(test_config(&server));
+        let error = jina_embeddings.health_check().await.unwrap_err();
+        assert!(error.to_string().contains("task and dimensions"));
+    }
+}
@@ -1,13 +1,166 @@
+use std::sync::Arc;
+
 use super::commons::Embedding;
-use anyhow::Result;
+use anyhow::{bail, Result};
 use async_trait::async_trait;
+#[cfg(any(
+    feature = "cohere",
+    feature = "gemini",
+    feature = "jina",
+    feature = "voyage"
+))]
+use reqwest::StatusCode;
 
+pub mod cache;
+#[cfg(feature = "cohere")]
+pub mod cohere;
+#[cfg(feature = "fastembed")]
+pub mod fastembed;
+#[cfg(feature = "gemini")]
+pub mod gemini;
+#[cfg(feature = "jina")]
+pub mod jina;
+#[cfg(feature = "ollama")]
+pub mod ollama;
 #[cfg(feature = "openai")]
 pub mod openai;
+#[cfg(feature = "voyage")]
+pub mod voyage;
 
 #[async_trait]
 pub trait EmbeddingFunction: Send + Sync {
     async fn embed(&self, docs: &[&str]) -> Result<Vec<Embedding>>;
+
+    /// Embed the documents with up to `concurrency` calls to [embed](Self::embed) in flight, returning the
+    /// embeddings in the order of `docs`. A concurrency of 0 or 1 embeds everything in a single call.
+    ///
+    /// The default implementation splits the documents into `concurrency` contiguous chunks and embeds them
+    /// at once. Providers with a batch API can override it to send a single request instead.
+    async fn embed_parallel(&self, docs: &[&str], concurrency: usize) -> Result<Vec<Embedding>> {
+        if concurrency <= 1 || docs.len() <= 1 {
+            return self.embed(docs).await;
+        }
+        let chunk_size = docs.len().div_ceil(concurrency);
+        let results =
+            futures::future::join_all(docs.chunks(chunk_size).map(|chunk| self.embed(chunk))).await;
+
+        let mut embeddings = Vec::with_capacity(docs.len());
+        for (chunk, result) in docs.chunks(chunk_size).zip(results) {
+            let chunk_embeddings = result?;
+            if chunk_embeddings.len() != chunk.len() {
+                bail!(
+                    "Embedding function returned {} embeddings for {} documents",
+                    chunk_embeddings.len(),
+                    chunk.len()
+                );
+            }
+            embeddings.extend(chunk_embeddings);
+        }
+        Ok(embeddings)
+    }
+
+    /// The provider and model, like `openai:text-embedding-3-small`, recorded in collection metadata by
+    /// [ChromaCollection::save_embedding_function](crate::ChromaCollection::save_embedding_function) so that queries
+    /// embedded with another model can be detected. `None` if unknown, which skips the check.
+    fn identity(&self) -> Option<String> {
+        None
+    }
+
+    /// Check that the provider is usable before sending it real work.
+    /// The default implementation embeds a one-word probe and validates the output.
+    async fn health_check(&self) -> Result<()> {
+        let embeddings = self.embed(&[HEALTH_CHECK_PROBE]).await?;
+        validate_probe_embeddings(&embeddings)
+    }
+}
+
+/// The collection metadata key holding the [identity](EmbeddingFunction::identity) of the embedding function the collection was populated with.
+pub const EMBEDDING_FUNCTION_KEY: &str = "chromadb-rs:embedding_function";
+
+pub(crate) const HEALTH_CHECK_PROBE: &str = "ping";
+
+/// Validate that a probe produced exactly one non-empty vector of finite values.
+pub(crate) fn validate_probe_embeddings(embeddings: &[Embedding]) -> Result<()> {
+    let [embedding] = embeddings else {
+        bail!(
+            "Embedding health check expected 1 embedding, got {}",
+            embeddings.len()
+        );
+    };
+    if embedding.is_empty() {
+        bail!("Embedding health check returned an empty vector");
+    }
+    if embedding.iter().any(|value| !value.is_finite()) {
+        bail!("Embedding health check returned non-finite values");
+    }
+    Ok(())
+}
+
+/// Turn the HTTP error of a hosted provider's health check probe into an actionable message.
+///
+/// A 401 or 403 points at `key_hint`, where the API key is set. The `model_statuses` point at
+/// `model_hint`, the settings that pick the model. Other errors are returned as is.
+#[cfg(any(
+    feature = "cohere",
+    feature = "gemini",
+    feature = "jina",
+    feature = "voyage"
+))]
+pub(crate) fn map_probe_error(
+    provider: &str,
+    key_hint: &str,
+    model: &str,
+    model_hint: &str,
+    model_statuses: &[StatusCode],
+    e: anyhow::Error,
+) -> anyhow::Error {
+    match e.downcast_ref::<reqwest::Error>().and_then(|e| e.status()) {
+        Some(StatusCode::UNAUTHORIZED | StatusCode::FORBIDDEN) => {
+            anyhow::anyhow!("{provider} rejected the API key. Check {key_hint}: {e}")
+        }
+        Some(status) if model_statuses.contains(&status) => anyhow::anyhow!(
+            "{provider} rejected the request for model {model:?}. Check {model_hint}: {e}"
+        ),
+        _ => e,
+    }
+}
+
+#[async_trait]
+impl<T: EmbeddingFunction + ?Sized> EmbeddingFunction for Arc<T> {
+    async fn embed(&self, docs: &[&str]) -> Result<Vec<Embedding>> {
+        (**self).embed(docs).await
+    }
+
+    async fn embed_parallel(&self, docs: &[&str], concurrency: usize) -> Result<Vec<Embedding>> {
+        (**self).embed_parallel(docs, concurrency).await
+    }
+
+    fn identity(&self) -> Option<String> {
+        (**self).identity()
+    }
+
+    async fn health_check(&self) -> Result<()> {
+        (**self).health_check().await
+    }
+}
+
+/// A mock provider API answering every request with `status`, to test how health checks report it.
+#[cfg(all(
+    test,
+    any(
+        feature = "cohere",
+        feature = "gemini",
+        feature = "jina",
+        feature = "voyage"
+    )
+))]
+pub(crate) async fn mock_provider_status(status: u16) -> wiremock::MockServer {
+    let server = wiremock::MockServer::start().await;
+    wiremock::Mock::given(wiremock::matchers::any())
+        .respond_with(wiremock::ResponseTemplate::new(status))
+        .mount(&server)
+        .await;
+    server
 }
 
 #[derive(Clone)]
@@ -15,7 +168,137 @@ pub struct MockEmbeddingProvider;
 
 #[async_trait]
 impl EmbeddingFunction for MockEmbeddingProvider {
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(
+            name = "chroma.embeddings.mock",
+            level = "debug",
+            skip_all,
+            fields(doc_count = docs.len())
+        )
+    )]
     async fn embed(&self, docs: &[&str]) -> Result<Vec<Embedding>> {
+        #[cfg(feature = "tracing")]
+        tracing::debug!("embedding with zero vectors");
         Ok(docs.iter().map(|_| vec![0.0_f32; 768]).collect())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct BrokenEmbeddingProvider(Vec<Embedding>);
+
+    #[async_trait]
+    impl EmbeddingFunction for BrokenEmbeddingProvider {
+        async fn embed(&self, _docs: &[&str]) -> Result<Vec<Embedding>> {
+            Ok(self.0.clone())
+        }
+    }
+
+    #[tokio::test]
+    async fn test_health_check() {
+        assert!(MockEmbeddingProvider.health_check().await.is_ok());
+        assert!(Arc::new(MockEmbeddingProvider).health_check().await.is_ok());
+
+        for embeddings in [
+            vec![],
+            vec![vec![]],
+            vec![vec![1.0, f32::NAN]],
+            vec![vec![1.0]; 2],
+        ] {
+            assert!(BrokenEmbeddingProvider(embeddings)
+                .health_check()
+                .await
+                .is_err());
+        }
+    }
+
+    #[cfg(any(
+        feature = "cohere",
+        feature = "gemini",
+        feature = "jina",
+        feature = "voyage"
+    ))]
+    #[tokio::test]
+    async fn test_map_probe_error() {
+        let map = |status| async move {
+            let server = mock_provider_status(status).await;
+            let e = reqwest::get(server.uri())
+                .await
+                .unwrap()
+                .error_for_status()
+                .unwrap_err();
+            map_probe_error(
+                "Acme",
+                "the ACME_API_KEY env",
+                "acme-1",
+                "AcmeConfig::model",
+                &[StatusCode::NOT_FOUND],
+                e.into(),
+            )
+            .to_string()
+        };
+        assert!(map(401).await.starts_with(
+            "Acme rejected the API key. Check the ACME_API_KEY env: HTTP status client error (401"
+        ));
+        assert!(map(403).await.contains("ACME_API_KEY"));
+        assert!(map(404).await.starts_with(
+            "Acme rejected the request for model \"acme-1\". Check AcmeConfig::model:"
+        ));
+        assert!(map(500).await.starts_with("HTTP status server error (500"));
+    }
+
+    /// Embeds each document as its length, sleeping longer for shorter documents so that chunks finish out of order.
+    struct SlowProvider;
+
+    #[async_trait]
+    impl EmbeddingFunction for SlowProvider {
+        async fn embed(&self, docs: &[&str]) -> Result<Vec<Embedding>> {
+            let len = docs.first().map_or(0, |doc| doc.len() as u64);
+            let delay = 20_u64.saturating_sub(len * 2);
+            tokio::time::sleep(std::time::Duration::from_millis(delay)).await;
+            Ok(docs.iter().map(|doc| vec![doc.len() as f32]).collect())
+        }
+    }
+
+    #[tokio::test]
+    async fn test_embed_parallel_preserves_order() {
+        let docs: Vec<String> = (1..=7).map(|len| "x".repeat(len)).collect();
+        let docs: Vec<&str> = docs.iter().map(String::as_str).collect();
+        let expected: Vec<Embedding> = (1..=7).map(|len| vec![len as f32]).collect();
+
+        for concurrency in [0, 1, 2, 3, 7, 16] {
+            assert_eq!(
+                SlowProvider
+                    .embed_parallel(&docs, concurrency)
+                    .await
+                    .unwrap(),
+                expected,
+                "concurrency {concurrency}"
+            );
+        }
+        assert_eq!(
+            Arc::new(SlowProvider)
+                .embed_parallel(&docs, 3)
+                .await
+                .unwrap(),
+            expected
+        );
+        assert!(SlowProvider
+            .embed_parallel(&[], 4)
+            .await
+            .unwrap()
+            .is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_embed_parallel_checks_chunk_lengths() {
+        let provider = BrokenEmbeddingProvider(vec![vec![1.0]]);
+        assert!(provider
+            .embed_parallel(&["a", "b", "c", "d"], 2)
+            .await
+            .is_err());
+    }
+}
@@ -0,0 +1,178 @@
+use anyhow::bail;
+use async_trait::async_trait;
+use futures::future::join_all;
+use reqwest::StatusCode;
+use serde::{Deserialize, Serialize};
+
+use super::{validate_probe_embeddings, EmbeddingFunction, HEALTH_CHECK_PROBE};
+use crate::commons::Embedding;
+
+const OLLAMA_ENDPOINT: &str = "http://localhost:11434";
+const OLLAMA_EMBEDDINGS_MODEL: &str = "nomic-embed-text";
+
+#[derive(Debug, Serialize)]
+struct EmbeddingRequest<'a> {
+    pub model: &'a str,
+    pub prompt: &'a str,
+}
+
+#[derive(Debug, Deserialize)]
+struct EmbeddingResponse {
+    pub embedding: Embedding,
+}
+
+/// Represents the Ollama Embeddings provider, running locally
+pub struct OllamaEmbeddings {
+    config: OllamaConfig,
+    client: reqwest::Client,
+}
+
+/// Defaults to the "nomic-embed-text" model served at http://localhost:11434
+pub struct OllamaConfig {
+    pub endpoint: String,
+    pub model: String,
+}
+
+impl Default for OllamaConfig {
+    fn default() -> Self {
+        Self {
+            endpoint: OLLAMA_ENDPOINT.to_string(),
+            model: OLLAMA_EMBEDDINGS_MODEL.to_string(),
+        }
+    }
+}
+
+impl OllamaEmbeddings {
+    pub fn new(config: OllamaConfig) -> Self {
+        Self {
+            config,
+            client: reqwest::Client::new(),
+        }
+    }
+
+    /// Embed all the documents with one concurrent request each, instead of one after the other like [embed](EmbeddingFunction::embed).
+    pub async fn embed_batched(&self, docs: &[&str]) -> anyhow::Result<Vec<Embedding>> {
+        join_all(docs.iter().map(|doc| self.post(doc)))
+            .await
+            .into_iter()
+            .collect()
+    }
+
+    async fn post(&self, prompt: &str) -> anyhow::Result<Embedding> {
+        let req = EmbeddingRequest {
+            model: &self.config.model,
+            prompt,
+        };
+        let res = self
+            .client
+            .post(format!(
+                "{}/api/embeddings",
+                self.config.endpoint.trim_end_matches('/')
+            ))
+            .json(&req)
+            .send()
+            .await?
+            .error_for_status()?;
+        Ok(res.json::<EmbeddingResponse>().await?.embedding)
+    }
+}
+
+#[async_trait]
+impl EmbeddingFunction for OllamaEmbeddings {
+    /// Embed the documents one request at a time.
+    async fn embed(&self, docs: &[&str]) -> anyhow::Result<Vec<Embedding>> {
+        let mut embeddings = Vec::with_capacity(docs.len());
+        for doc in docs {
+            embeddings.push(self.post(doc).await?);
+        }
+        Ok(embeddings)
+    }
+
+    fn identity(&self) -> Option<String> {
+        Some(format!("ollama:{}", self.config.model))
+    }
+
+    /// Embed a one-word probe, turning connection and missing model errors into actionable messages.
+    async fn health_check(&self) -> anyhow::Result<()> {
+        let embeddings = match self.post(HEALTH_CHECK_PROBE).await {
+            Ok(embedding) => vec![embedding],
+            Err(e) => match e.downcast_ref::<reqwest::Error>() {
+                Some(re) if re.is_connect() => bail!(
+                    "Could not connect to Ollama at {}. Check that it is running and OllamaConfig::endpoint: {e}",
+                    self.config.endpoint
+                ),
+                Some(re) if re.status() == Some(StatusCode::NOT_FOUND) => bail!(
+                    "Ollama does not have the model {:?}. Pull it with `ollama pull {}`: {e}",
+                    self.config.model,
+                    self.config.model
+                ),
+                _ => return Err(e),
+            },
+        };
+        validate_probe_embeddings(&embeddings)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+    use wiremock::matchers::{body_json, method, path};
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+
+    async fn mock_ollama() -> MockServer {
+        let server = MockServer::start().await;
+        for (prompt, embedding) in [("frog", [0.1, 0.2]), ("cow", [0.3, 0.4])] {
+            Mock::given(method("POST"))
+                .and(path("/api/embeddings"))
+                .and(body_json(
+                    json!({"model": OLLAMA_EMBEDDINGS_MODEL, "prompt": prompt}),
+                ))
+                .respond_with(
+                    ResponseTemplate::new(200).set_body_json(json!({"embedding": embedding})),
+                )
+                .mount(&server)
+                .await;
+        }
+        server
+    }
+
+    fn test_config(server: &MockServer) -> OllamaConfig {
+        OllamaConfig {
+            endpoint: server.uri(),
+            ..Default::default()
+        }
+    }
+
+    #[tokio::test]
+    async fn test_ollama_embeddings() {
+        let server = mock_ollama().await;
+        let ollama_embeddings = OllamaEmbeddings::new(test_config(&server));
+        let expected = vec![vec![0.1, 0.2], vec![0.3, 0.4]];
+        assert_eq!(
+            ollama_embeddings.embed(&["frog", "cow"]).await.unwrap(),
+            expected
+        );
+        assert_eq!(
+            ollama_embeddings
+                .embed_batched(&["frog", "cow"])
+                .await
+                .unwrap(),
+            expected
+        );
+        assert!(ollama_embeddings.embed(&["frog", "unknown"]).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_ollama_health_check() {
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .respond_with(ResponseTemplate::new(404))
+            .mount(&server)
+            .await;
+
+        let ollama_embeddings = OllamaEmbeddings::new(test_config(&server));
+        let error = ollama_embeddings.health_check().await.unwrap_err();
+        assert!(error.to_string().contains("ollama pull"));
+    }
+}
@@ -1,8 +1,10 @@
+use anyhow::bail;
 use async_trait::async_trait;
+use reqwest::StatusCode;
 use serde::{Deserialize, Serialize};
 use serde_json::Value;
 
-use super::EmbeddingFunction;
+use super::{validate_probe_embeddings, EmbeddingFunction, HEALTH_CHECK_PROBE};
 use crate::commons::Embedding;
 
 const OPENAI_EMBEDDINGS_ENDPOINT: &str = "https://api.openai.com/v1/embeddings";
@@ -11,12 +13,14 @@ const OPENAI_EMBEDDINGS_MODEL: &str = "text-embedding-3-small";
 #[derive(Debug, Deserialize)]
 struct EmbeddingData {
     pub embedding: Vec<f32>,
+    /// The position of the embedded document in the request's `input`.
+    pub index: usize,
 }
 
 #[derive(Debug, Serialize)]
 struct EmbeddingRequest<'a> {
     pub model: &'a str,
-    pub input: &'a str,
+    pub input: &'a [&'a str],
 }
 
 #[derive(Debug, Deserialize)]
@@ -72,19 +76,70 @@ impl OpenAIEmbeddings {
 
 #[async_trait]
 impl EmbeddingFunction for OpenAIEmbeddings {
+    /// Embed all the documents in a single request.
     async fn embed(&self, docs: &[&str]) -> anyhow::Result<Vec<Embedding>> {
-        let mut embeddings = Vec::new();
-        for doc in docs {
-            let req = EmbeddingRequest {
-                model: &self.config.model,
-                input: doc,
-            };
-            let res = self.post(req).await?;
-            let body = serde_json::from_value::<EmbeddingResponse>(res)?;
-            embeddings.push(body.data[0].embedding.clone());
+        if docs.is_empty() {
+            return Ok(vec![]);
         }
+        let req = EmbeddingRequest {
+            model: &self.config.model,
+            input: docs,
+        };
+        let res = self.post(req).await?;
+        let mut body = serde_json::from_value::<EmbeddingResponse>(res)?;
+        // OpenAI does not guarantee the order of the embeddings in the response.
+        body.data.sort_by_key(|data| data.index);
+        if body.data.len() != docs.len()
+            || body
+                .data
+                .iter()
+                .enumerate()
+                .any(|(i, data)| data.index != i)
+        {
+            bail!(
+                "OpenAI returned embeddings for {} of the {} documents",
+                body.data.len(),
+                docs.len()
+            );
+        }
+        Ok(body.data.into_iter().map(|data| data.embedding).collect())
+    }
+
+    /// The embeddings endpoint takes the whole batch at once, so this is a single request whatever the concurrency.
+    async fn embed_parallel(
+        &self,
+        docs: &[&str],
+        _concurrency: usize,
+    ) -> anyhow::Result<Vec<Embedding>> {
+        self.embed(docs).await
+    }
+
+    fn identity(&self) -> Option<String> {
+        Some(format!("openai:{}", self.config.model))
+    }
 
-        Ok(embeddings)
+    /// Embed a one-word probe, turning authentication and model errors into actionable messages.
+    async fn health_check(&self) -> anyhow::Result<()> {
+        let req = EmbeddingRequest {
+            model: &self.config.model,
+            input: &[HEALTH_CHECK_PROBE],
+        };
+        let res = match self.post(req).await {
+            Ok(res) => res,
+            Err(e) => match e.downcast_ref::<reqwest::Error>().and_then(|e| e.status()) {
+                Some(StatusCode::UNAUTHORIZED | StatusCode::FORBIDDEN) => bail!(
+                    "OpenAI rejected the API key. Check the OPENAI_API_KEY env or OpenAIConfig::api_key: {e}"
+                ),
+                Some(StatusCode::NOT_FOUND) => bail!(
+                    "OpenAI does not know the model {:?}. Check OpenAIConfig::model: {e}",
+                    self.config.model
+                ),
+                _ => return Err(e),
+            },
+        };
+        let body = serde_json::from_value::<EmbeddingResponse>(res)?;
+        let embeddings: Vec<Embedding> = body.data.into_iter().map(|d| d.embedding).collect();
+        validate_probe_embeddings(&embeddings)
     }
 }
 
@@ -123,4 +178,42 @@ mod tests {
             .await
             .unwrap();
     }
+
+    #[tokio::test]
+    async fn test_openai_embeddings_single_request() {
+        use serde_json::json;
+        use wiremock::matchers::{body_json, method};
+        use wiremock::{Mock, MockServer, ResponseTemplate};
+
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(body_json(json!({
+                "model": OPENAI_EMBEDDINGS_MODEL,
+                "input": ["frog", "cow", "wolverine"],
+            })))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "data": [
+                    {"embedding": [0.3], "index": 2},
+                    {"embedding": [0.1], "index": 0},
+                    {"embedding": [0.2], "index": 1},
+                ]
+            })))
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        let openai_embeddings = OpenAIEmbeddings::new(OpenAIConfig {
+            api_endpoint: server.uri(),
+            api_key: "test-key".into(),
+            model: OPENAI_EMBEDDINGS_MODEL.into(),
+        });
+        assert_eq!(
+            openai_embeddings
+                .embed(&["frog", "cow", "wolverine"])
+                .await
+                .unwrap(),
+            vec![vec![0.1], vec![0.2], vec![0.3]]
+        );
+        assert!(openai_embeddings.embed(&[]).await.unwrap().is_empty());
+    }
 }
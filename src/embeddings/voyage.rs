@@ -0,0 +1,188 @@
+use anyhow::bail;
+use async_trait::async_trait;
+use reqwest::StatusCode;
+use serde::{Deserialize, Serialize};
+
+use super::{map_probe_error, validate_probe_embeddings, EmbeddingFunction, HEALTH_CHECK_PROBE};
+use crate::commons::Embedding;
+
+const VOYAGE_EMBEDDINGS_ENDPOINT: &str = "https://api.voyageai.com/v1/embeddings";
+const VOYAGE_EMBEDDINGS_MODEL: &str = "voyage-large-2-instruct";
+
+/// The kind of input being embedded. Voyage prepends a retrieval prompt matching the input type.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum VoyageInputType {
+    #[default]
+    Document,
+    Query,
+}
+
+#[derive(Debug, Serialize)]
+struct EmbeddingRequest<'a> {
+    pub input: &'a [&'a str],
+    pub model: &'a str,
+    pub input_type: VoyageInputType,
+}
+
+#[derive(Debug, Deserialize)]
+struct EmbeddingResponse {
+    pub data: Vec<EmbeddingData>,
+}
+
+#[derive(Debug, Deserialize)]
+struct EmbeddingData {
+    pub embedding: Embedding,
+    pub index: usize,
+}
+
+/// Represents the Voyage AI Embeddings provider
+pub struct VoyageEmbeddings {
+    config: VoyageConfig,
+    client: reqwest::Client,
+}
+
+/// Defaults to the "voyage-large-2-instruct" model, embedding documents
+/// The API key can be set in the VOYAGE_API_KEY environment variable
+pub struct VoyageConfig {
+    pub api_endpoint: String,
+    pub api_key: String,
+    pub model: String,
+    pub input_type: VoyageInputType,
+}
+
+impl Default for VoyageConfig {
+    fn default() -> Self {
+        Self {
+            api_endpoint: VOYAGE_EMBEDDINGS_ENDPOINT.to_string(),
+            api_key: std::env::var("VOYAGE_API_KEY").expect("VOYAGE_API_KEY env is not set"),
+            model: VOYAGE_EMBEDDINGS_MODEL.to_string(),
+            input_type: VoyageInputType::default(),
+        }
+    }
+}
+
+impl VoyageEmbeddings {
+    pub fn new(config: VoyageConfig) -> Self {
+        Self {
+            config,
+            client: reqwest::Client::new(),
+        }
+    }
+
+    async fn post(&self, docs: &[&str]) -> anyhow::Result<Vec<Embedding>> {
+        let req = EmbeddingRequest {
+            input: docs,
+            model: &self.config.model,
+            input_type: self.config.input_type,
+        };
+        let res = self
+            .client
+            .post(&self.config.api_endpoint)
+            .header("Authorization", format!("Bearer {}", self.config.api_key))
+            .json(&req)
+            .send()
+            .await?
+            .error_for_status()?;
+        let mut body = res.json::<EmbeddingResponse>().await?;
+        if body.data.len() != docs.len() {
+            bail!(
+                "Voyage returned {} embeddings for {} documents",
+                body.data.len(),
+                docs.len()
+            );
+        }
+        body.data.sort_by_key(|data| data.index);
+        Ok(body.data.into_iter().map(|data| data.embedding).collect())
+    }
+}
+
+#[async_trait]
+impl EmbeddingFunction for VoyageEmbeddings {
+    /// Embed all the documents in a single request.
+    async fn embed(&self, docs: &[&str]) -> anyhow::Result<Vec<Embedding>> {
+        if docs.is_empty() {
+            return Ok(vec![]);
+        }
+        self.post(docs).await
+    }
+
+    fn identity(&self) -> Option<String> {
+        Some(format!("voyage:{}", self.config.model))
+    }
+
+    /// Embed a one-word probe, turning authentication and model errors into actionable messages.
+    async fn health_check(&self) -> anyhow::Result<()> {
+        let embeddings = self.post(&[HEALTH_CHECK_PROBE]).await.map_err(|e| {
+            map_probe_error(
+                "Voyage",
+                "the VOYAGE_API_KEY env or VoyageConfig::api_key",
+                &self.config.model,
+                "VoyageConfig::model",
+                &[StatusCode::NOT_FOUND, StatusCode::BAD_REQUEST],
+                e,
+            )
+        })?;
+        validate_probe_embeddings(&embeddings)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::embeddings::mock_provider_status;
+    use serde_json::json;
+    use wiremock::matchers::{body_json, header, method};
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+
+    fn test_config(server: &MockServer) -> VoyageConfig {
+        VoyageConfig {
+            api_endpoint: format!("{}/v1/embeddings", server.uri()),
+            api_key: "test-key".to_string(),
+            model: VOYAGE_EMBEDDINGS_MODEL.to_string(),
+            input_type: VoyageInputType::Query,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_voyage_embeddings() {
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(header("Authorization", "Bearer test-key"))
+            .and(body_json(json!({
+                "input": ["Once upon a time there was a frog", "Once upon a time there was a cow"],
+                "model": VOYAGE_EMBEDDINGS_MODEL,
+                "input_type": "query",
+            })))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "object": "list",
+                "data": [
+                    {"object": "embedding", "embedding": [0.3, 0.4], "index": 1},
+                    {"object": "embedding", "embedding": [0.1, 0.2], "index": 0},
+                ],
+                "model": VOYAGE_EMBEDDINGS_MODEL,
+                "usage": {"total_tokens": 16},
+            })))
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        let voyage_embeddings = VoyageEmbeddings::new(test_config(&server));
+        let embeddings = voyage_embeddings
+            .embed(&[
+                "Once upon a time there was a frog",
+                "Once upon a time there was a cow",
+            ])
+            .await
+            .unwrap();
+        assert_eq!(embeddings, vec![vec![0.1, 0.2], vec![0.3, 0.4]]);
+    }
+
+    #[tokio::test]
+    async fn test_voyage_health_check() {
+        let server = mock_provider_status(401).await;
+        let voyage_embeddings = VoyageEmbeddings::new(test_config(&server));
+        let error = voyage_embeddings.health_check().await.unwrap_err();
+        assert!(error.to_string().contains("VOYAGE_API_KEY"));
+    }
+}
@@ -0,0 +1,407 @@
+use std::fmt;
+use std::time::Duration;
+
+use crate::collection::ValidationError;
+use crate::version::ChromaVersion;
+
+/// The error type returned by the client and collection methods.
+///
+/// It converts into an [anyhow::Error] with `?`, for callers that do not need to tell the failures apart.
+#[derive(Debug)]
+#[non_exhaustive]
+pub enum ChromaError {
+    /// The arguments or options passed in were invalid.
+    InvalidArgument { message: String },
+    /// The entries failed client-side validation, e.g. because of empty or duplicate ids.
+    Validation(ValidationError),
+    /// The server could not find the requested collection.
+    CollectionNotFound(String),
+    /// The server could not find the tenant, e.g. the one set in [ChromaClientOptions](crate::ChromaClientOptions).
+    TenantNotFound(String),
+    /// The server could not find the database, e.g. the one set in [ChromaClientOptions](crate::ChromaClientOptions).
+    DatabaseNotFound(String),
+    /// The server rejected the entries because of duplicate ids.
+    /// Duplicates found by client-side validation are reported as [Validation](Self::Validation) instead,
+    /// along with the other invalid records.
+    DuplicateIds(String),
+    /// A collection, tenant or database with the same name already exists.
+    AlreadyExists(String),
+    /// The embeddings do not match the dimensionality of the collection.
+    DimensionMismatch { expected: usize, got: usize },
+    /// The server rejected the credentials.
+    AuthenticationFailed(String),
+    /// The account ran out of quota, as reported by Chroma Cloud.
+    QuotaExceeded { status: u16, body: String },
+    /// The server is rate limiting the client (429). Back off for `retry_after` before retrying.
+    ///
+    /// `retry_after` is read from the `Retry-After` header, in seconds or as an HTTP date,
+    /// or else from the `RateLimit-Reset` header. `limit` and `remaining` are read from the
+    /// `RateLimit-Limit` and `RateLimit-Remaining` headers, or their `X-` prefixed variants.
+    RateLimited {
+        retry_after: Option<Duration>,
+        limit: Option<u64>,
+        remaining: Option<u64>,
+        body: String,
+    },
+    /// The server responded with any other non-success status.
+    ServerError { status: u16, body: String },
+    /// The embedding function failed.
+    EmbeddingError(anyhow::Error),
+    /// The request could not be sent, or its response could not be read.
+    Transport(reqwest::Error),
+    /// The server responded with success, but not with the expected JSON, e.g. an empty or plain text body from a proxy.
+    UnexpectedResponse {
+        content_type: Option<String>,
+        message: String,
+    },
+    /// The server does not allow resetting. It must be started with `ALLOW_RESET=TRUE`.
+    ResetNotAllowed(String),
+    /// A chunk of a chunked write failed. The chunks before it were written.
+    ChunkFailed {
+        chunk: usize,
+        source: Box<ChromaError>,
+    },
+    /// Reading or writing a local file failed, e.g. the journal of a [JournaledCollection](crate::journal::JournaledCollection).
+    Io(std::io::Error),
+    /// A line of a [JournaledCollection](crate::journal::JournaledCollection) journal could not be parsed. Nothing was replayed.
+    JournalCorrupted { line: usize, message: String },
+    /// Embeddings were requested, but the server left them out for `missing` of the `total` records returned,
+    /// as some managed deployments do for large results. Only returned by handles
+    /// [with_strict_embeddings](crate::ChromaCollection::with_strict_embeddings), others report a warning.
+    EmbeddingsUnavailable { missing: usize, total: usize },
+    /// The server does not support `feature`, like [search](crate::ChromaCollection::search) on servers before Chroma 1.x.
+    /// `version` is the version of the server, if it was fetched.
+    UnsupportedServer {
+        feature: String,
+        version: Option<ChromaVersion>,
+    },
+}
+
+impl ChromaError {
+    pub(crate) fn invalid_argument(message: impl Into<String>) -> Self {
+        Self::InvalidArgument {
+            message: message.into(),
+        }
+    }
+
+    /// Quote the id of the failed request in the message, to find it in the server logs.
+    /// Errors without a message from the server, like [DimensionMismatch](Self::DimensionMismatch), are left as is.
+    pub(crate) fn with_request_id(mut self, request_id: &str) -> Self {
+        match &mut self {
+            Self::InvalidArgument { message: body }
+            | Self::CollectionNotFound(body)
+            | Self::TenantNotFound(body)
+            | Self::DatabaseNotFound(body)
+            | Self::DuplicateIds(body)
+            | Self::AlreadyExists(body)
+            | Self::AuthenticationFailed(body)
+            | Self::QuotaExceeded { body, .. }
+            | Self::RateLimited { body, .. }
+            | Self::ServerError { body, .. }
+            | Self::ResetNotAllowed(body) => {
+                if !body.is_empty() {
+                    body.push(' ');
+                }
+                body.push_str(&format!("(request id {request_id})"));
+            }
+            _ => {}
+        }
+        self
+    }
+
+    /// Classify a non-success response from the server.
+    /// A JSON body like `{"error": "NotFoundError", "message": "..."}` is reduced to its message.
+    ///
+    /// Not found errors are only classified when the message names a collection, tenant or database.
+    /// Others, like a bare 404 for an unknown route, are a [ServerError](Self::ServerError).
+    pub(crate) fn from_response(status: u16, body: String) -> Self {
+        let (kind, body) = match parse_error_body(&body) {
+            Some((kind, message)) => (kind, message),
+            None => (String::new(), body),
+        };
+        if let Some((expected, got)) = parse_dimension_mismatch(&body) {
+            return Self::DimensionMismatch { expected, got };
+        }
+        let lowercase = body.to_ascii_lowercase();
+        match status {
+            401 | 403 => Self::AuthenticationFailed(body),
+            429 => Self::RateLimited {
+                retry_after: None,
+                limit: None,
+                remaining: None,
+                body,
+            },
+            400..=499 if lowercase.contains("quota") => Self::QuotaExceeded { status, body },
+            409 => Self::AlreadyExists(body),
+            _ if kind == "UniqueConstraintError"
+                || lowercase.contains("already exists")
+                || lowercase.contains("unique constraint") =>
+            {
+                Self::AlreadyExists(body)
+            }
+            _ if kind == "DuplicateIDError" || lowercase.contains("expected ids to be unique") => {
+                Self::DuplicateIds(body)
+            }
+            _ if status == 404
+                || kind == "NotFoundError"
+                || lowercase.contains("does not exist")
+                || lowercase.contains("not found") =>
+            {
+                match not_found_subject(&lowercase) {
+                    Some("collection") => Self::CollectionNotFound(body),
+                    Some("database") => Self::DatabaseNotFound(body),
+                    Some("tenant") => Self::TenantNotFound(body),
+                    _ => Self::ServerError { status, body },
+                }
+            }
+            400 | 422 if kind == "InvalidArgumentError" => Self::InvalidArgument { message: body },
+            _ => Self::ServerError { status, body },
+        }
+    }
+}
+
+/// The kind of resource a not found message is about, the first of "collection", "database" and "tenant" it names.
+/// E.g. "database" for "Database foo not found for tenant bar".
+fn not_found_subject(lowercase: &str) -> Option<&'static str> {
+    ["collection", "database", "tenant"]
+        .into_iter()
+        .filter_map(|subject| Some((lowercase.find(subject)?, subject)))
+        .min()
+        .map(|(_, subject)| subject)
+}
+
+/// Extract the error type and message from a JSON error body like `{"error": "NotFoundError", "message": "..."}`.
+fn parse_error_body(body: &str) -> Option<(String, String)> {
+    #[derive(serde::Deserialize)]
+    struct ErrorBody {
+        #[serde(default)]
+        error: String,
+        message: String,
+    }
+    let ErrorBody { error, message } = serde_json::from_str(body).ok()?;
+    Some((error, message))
+}
+
+/// Extract the dimensions from Chroma's
+/// "Embedding dimension 2 does not match collection dimensionality 768" message.
+fn parse_dimension_mismatch(body: &str) -> Option<(usize, usize)> {
+    let (_, rest) = body.split_once("dimension ")?;
+    let (got, rest) = rest.split_once(" does not match collection dimensionality ")?;
+    let expected: String = rest.chars().take_while(char::is_ascii_digit).collect();
+    Some((expected.parse().ok()?, got.trim().parse().ok()?))
+}
+
+impl fmt::Display for ChromaError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::InvalidArgument { message } => write!(f, "Invalid argument: {message}"),
+            Self::Validation(e) => write!(f, "{e}"),
+            Self::CollectionNotFound(body) => write!(f, "Collection not found: {body}"),
+            Self::TenantNotFound(body) => write!(f, "Tenant not found: {body}"),
+            Self::DatabaseNotFound(body) => write!(f, "Database not found: {body}"),
+            Self::DuplicateIds(body) => write!(f, "Duplicate ids: {body}"),
+            Self::AlreadyExists(body) => write!(f, "Already exists: {body}"),
+            Self::DimensionMismatch { expected, got } => write!(
+                f,
+                "Embedding dimension {got} does not match collection dimensionality {expected}"
+            ),
+            Self::AuthenticationFailed(body) => write!(f, "Authentication failed: {body}"),
+            Self::QuotaExceeded { status, body } => {
+                write!(f, "Out of quota ({status}): {body}")
+            }
+            Self::RateLimited {
+                retry_after: Some(retry_after),
+                body,
+                ..
+            } => write!(f, "Rate limited, retry after {retry_after:?}: {body}"),
+            Self::RateLimited { body, .. } => write!(f, "Rate limited: {body}"),
+            Self::ServerError { status, body } => write!(f, "Server error {status}: {body}"),
+            Self::EmbeddingError(e) => write!(f, "Embedding function failed: {e}"),
+            Self::Transport(e) => write!(f, "Request failed: {e}"),
+            Self::UnexpectedResponse {
+                content_type,
+                message,
+            } => write!(
+                f,
+                "Unexpected response with content type {}: {message}",
+                content_type.as_deref().unwrap_or("(none)")
+            ),
+            Self::ResetNotAllowed(body) => write!(
+                f,
+                "Resetting is not allowed by this configuration, start the server with ALLOW_RESET=TRUE: {body}"
+            ),
+            Self::ChunkFailed { chunk, source } => write!(f, "Chunk {chunk} failed: {source}"),
+            Self::Io(e) => write!(f, "I/O error: {e}"),
+            Self::JournalCorrupted { line, message } => {
+                write!(f, "Journal line {line} is corrupted: {message}")
+            }
+            Self::EmbeddingsUnavailable { missing, total } => write!(
+                f,
+                "Embeddings were requested, but the server returned none for {missing} of {total} records"
+            ),
+            Self::UnsupportedServer {
+                feature,
+                version: Some(version),
+            } => write!(f, "Chroma {version} does not support {feature}"),
+            Self::UnsupportedServer { feature, .. } => {
+                write!(f, "The server does not support {feature}")
+            }
+        }
+    }
+}
+
+impl std::error::Error for ChromaError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Self::Validation(e) => Some(e),
+            Self::EmbeddingError(e) => Some(e.as_ref()),
+            Self::Transport(e) => Some(e),
+            Self::ChunkFailed { source, .. } => Some(source.as_ref()),
+            Self::Io(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<ValidationError> for ChromaError {
+    fn from(e: ValidationError) -> Self {
+        Self::Validation(e)
+    }
+}
+
+impl From<std::io::Error> for ChromaError {
+    fn from(e: std::io::Error) -> Self {
+        Self::Io(e)
+    }
+}
+
+impl From<reqwest::Error> for ChromaError {
+    fn from(e: reqwest::Error) -> Self {
+        Self::Transport(e)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_from_response() {
+        assert!(matches!(
+            ChromaError::from_response(
+                400,
+                "InvalidDimension: Embedding dimension 2 does not match collection dimensionality 768".into()
+            ),
+            ChromaError::DimensionMismatch {
+                expected: 768,
+                got: 2
+            }
+        ));
+        assert!(matches!(
+            ChromaError::from_response(401, "Unauthorized".into()),
+            ChromaError::AuthenticationFailed(_)
+        ));
+        assert!(matches!(
+            ChromaError::from_response(500, "Collection foo does not exist.".into()),
+            ChromaError::CollectionNotFound(_)
+        ));
+        assert!(matches!(
+            ChromaError::from_response(429, "Too Many Requests".into()),
+            ChromaError::RateLimited {
+                retry_after: None,
+                ..
+            }
+        ));
+        assert!(matches!(
+            ChromaError::from_response(400, "Quota exceeded: max records per collection".into()),
+            ChromaError::QuotaExceeded { status: 400, .. }
+        ));
+        assert!(matches!(
+            ChromaError::from_response(500, "Internal error".into()),
+            ChromaError::ServerError { status: 500, .. }
+        ));
+    }
+
+    #[test]
+    fn test_from_not_found_response() {
+        assert!(matches!(
+            ChromaError::from_response(
+                404,
+                r#"{"error": "NotFoundError", "message": "Database mydb not found for tenant acme"}"#
+                    .into()
+            ),
+            ChromaError::DatabaseNotFound(_)
+        ));
+        assert!(matches!(
+            ChromaError::from_response(404, "Tenant acme does not exist".into()),
+            ChromaError::TenantNotFound(_)
+        ));
+        // A bare 404, e.g. from a wrong path prefix, is not about a collection.
+        assert!(matches!(
+            ChromaError::from_response(404, String::new()),
+            ChromaError::ServerError { status: 404, .. }
+        ));
+        assert!(matches!(
+            ChromaError::from_response(404, r#"{"detail": "Not Found"}"#.into()),
+            ChromaError::ServerError { status: 404, .. }
+        ));
+        assert!(matches!(
+            ChromaError::from_response(
+                400,
+                r#"{"error": "DuplicateIDError", "message": "Expected IDs to be unique, found duplicates of: a"}"#
+                    .into()
+            ),
+            ChromaError::DuplicateIds(message) if message.ends_with("duplicates of: a")
+        ));
+    }
+
+    #[test]
+    fn test_from_json_response() {
+        let error = ChromaError::from_response(
+            404,
+            r#"{"error": "NotFoundError", "message": "Collection foo does not exist."}"#.into(),
+        );
+        assert!(
+            matches!(&error, ChromaError::CollectionNotFound(message) if message == "Collection foo does not exist.")
+        );
+        assert!(matches!(
+            ChromaError::from_response(
+                500,
+                r#"{"error": "UniqueConstraintError", "message": "Collection foo already exists"}"#
+                    .into()
+            ),
+            ChromaError::AlreadyExists(_)
+        ));
+        assert!(matches!(
+            ChromaError::from_response(409, "Collection foo already exists".into()),
+            ChromaError::AlreadyExists(_)
+        ));
+        assert!(matches!(
+            ChromaError::from_response(
+                400,
+                r#"{"error": "InvalidArgumentError", "message": "Expected ids to be non-empty"}"#
+                    .into()
+            ),
+            ChromaError::InvalidArgument { message } if message == "Expected ids to be non-empty"
+        ));
+        assert!(matches!(
+            ChromaError::from_response(500, r#"{"detail": "oops"}"#.into()),
+            ChromaError::ServerError { body, .. } if body == r#"{"detail": "oops"}"#
+        ));
+    }
+
+    #[test]
+    fn test_into_anyhow() {
+        fn fails() -> anyhow::Result<()> {
+            Err(ChromaError::from_response(
+                404,
+                "Collection gone does not exist.".into(),
+            ))?
+        }
+        let error = fails().unwrap_err();
+        assert!(matches!(
+            error.downcast_ref::<ChromaError>(),
+            Some(ChromaError::CollectionNotFound(_))
+        ));
+    }
+}
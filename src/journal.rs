@@ -0,0 +1,493 @@
+use std::fs::{self, File, OpenOptions};
+use std::io::{ErrorKind, Write};
+use std::path::{Path, PathBuf};
+
+use futures::lock::Mutex;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use super::{
+    collection::{ChromaCollection, CollectionEntries},
+    commons::{Embeddings, Metadatas, Result},
+    embeddings::EmbeddingFunction,
+    error::ChromaError,
+};
+
+/// Whether a write of a [JournaledCollection] reached the server, or was journaled to be replayed later.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WriteOutcome {
+    Sent,
+    Journaled,
+}
+
+/// A write recorded in the journal, one JSON object per line.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(tag = "op", rename_all = "snake_case")]
+enum Operation {
+    /// Adds are journaled as upserts, so that replaying them twice is harmless.
+    Upsert {
+        ids: Vec<String>,
+        embeddings: Option<Embeddings>,
+        metadatas: Option<Metadatas>,
+        documents: Option<Vec<String>>,
+    },
+    Delete {
+        ids: Option<Vec<String>>,
+        where_metadata: Option<Value>,
+        where_document: Option<Value>,
+    },
+}
+
+/// A collection whose writes survive losing the connection to the server.
+///
+/// Adds, upserts and deletes that fail with a [ChromaError::Transport] error are appended to a local journal file
+/// instead of being dropped. The journal is replayed in order by [flush](Self::flush), and before any later write so
+/// that writes are never reordered. Replay is at-least-once: an operation may be sent again if the process stops
+/// right after the server applied it, which is why adds are replayed as upserts.
+///
+/// Embeddings are computed before journaling, so a failing embedding function is reported as usual.
+/// An operation the server rejects during replay stays at the head of the journal and fails every flush until the journal is fixed.
+pub struct JournaledCollection {
+    collection: ChromaCollection,
+    path: PathBuf,
+    lock: Mutex<()>,
+}
+
+impl JournaledCollection {
+    /// Journal the failed writes to `collection` in the file at `path`, replaying any operations already in it on the next write or flush.
+    pub fn new(collection: ChromaCollection, path: impl Into<PathBuf>) -> Self {
+        Self {
+            collection,
+            path: path.into(),
+            lock: Mutex::new(()),
+        }
+    }
+
+    pub fn collection(&self) -> &ChromaCollection {
+        &self.collection
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Add the entries, see [ChromaCollection::add]. If the server cannot be reached they are journaled, and replayed as an upsert.
+    ///
+    /// # Errors
+    ///
+    /// * The same as [ChromaCollection::add], except for transport errors
+    /// * If the journal cannot be read or written, or is corrupted
+    pub async fn add(
+        &self,
+        collection_entries: CollectionEntries<'_>,
+        embedding_function: Option<Box<dyn EmbeddingFunction>>,
+    ) -> Result<WriteOutcome> {
+        let operation = upsert_operation(collection_entries, embedding_function).await?;
+        self.write(operation, true).await
+    }
+
+    /// Upsert the entries, see [ChromaCollection::upsert]. If the server cannot be reached they are journaled.
+    ///
+    /// # Errors
+    ///
+    /// * The same as [ChromaCollection::upsert], except for transport errors
+    /// * If the journal cannot be read or written, or is corrupted
+    pub async fn upsert(
+        &self,
+        collection_entries: CollectionEntries<'_>,
+        embedding_function: Option<Box<dyn EmbeddingFunction>>,
+    ) -> Result<WriteOutcome> {
+        let operation = upsert_operation(collection_entries, embedding_function).await?;
+        self.write(operation, false).await
+    }
+
+    /// Delete the matching entries, see [ChromaCollection::delete]. If the server cannot be reached the delete is journaled.
+    ///
+    /// # Errors
+    ///
+    /// * The same as [ChromaCollection::delete], except for transport errors
+    /// * If the journal cannot be read or written, or is corrupted
+    pub async fn delete(
+        &self,
+        ids: Option<Vec<&str>>,
+        where_metadata: Option<Value>,
+        where_document: Option<Value>,
+    ) -> Result<WriteOutcome> {
+        let operation = Operation::Delete {
+            ids: ids.map(|ids| ids.into_iter().map(str::to_string).collect()),
+            where_metadata,
+            where_document,
+        };
+        self.write(operation, false).await
+    }
+
+    /// The number of journaled operations waiting to be replayed.
+    ///
+    /// # Errors
+    ///
+    /// * If the journal cannot be read, or is corrupted
+    pub async fn pending_operations(&self) -> Result<usize> {
+        let _guard = self.lock.lock().await;
+        Ok(self.load()?.len())
+    }
+
+    /// Replay the journaled operations in order, removing each one from the journal once the server accepted it.
+    /// Returns the number of operations replayed.
+    ///
+    /// # Errors
+    ///
+    /// * The error of the first operation that failed. It and the operations after it stay in the journal.
+    /// * If the journal cannot be read or written, or is corrupted
+    pub async fn flush(&self) -> Result<usize> {
+        let _guard = self.lock.lock().await;
+        self.replay().await
+    }
+
+    async fn write(&self, operation: Operation, add: bool) -> Result<WriteOutcome> {
+        let _guard = self.lock.lock().await;
+        let result = match self.replay().await {
+            Ok(_) => self.send(&operation, add).await,
+            Err(e) => Err(e),
+        };
+        match result {
+            Ok(()) => Ok(WriteOutcome::Sent),
+            Err(ChromaError::Transport(e)) => {
+                log::warn!("Journaling a write to {}: {e}", self.collection.name());
+                self.append(&operation)?;
+                Ok(WriteOutcome::Journaled)
+            }
+            Err(e) => Err(e),
+        }
+    }
+
+    async fn replay(&self) -> Result<usize> {
+        let mut operations = self.load()?;
+        let mut replayed = 0;
+        while !operations.is_empty() {
+            self.send(&operations[0], false).await?;
+            operations.remove(0);
+            self.save(&operations)?;
+            replayed += 1;
+        }
+        Ok(replayed)
+    }
+
+    async fn send(&self, operation: &Operation, add: bool) -> Result<()> {
+        match operation {
+            Operation::Upsert {
+                ids,
+                embeddings,
+                metadatas,
+                documents,
+            } => {
+                let collection_entries = CollectionEntries {
+                    ids: ids.iter().map(String::as_str).collect(),
+                    embeddings: embeddings.clone(),
+                    metadatas: metadatas.clone(),
+                    documents: documents
+                        .as_ref()
+                        .map(|documents| documents.iter().map(String::as_str).collect()),
+                };
+                if add {
+                    self.collection.add(collection_entries, None).await?;
+                } else {
+                    self.collection.upsert(collection_entries, None).await?;
+                }
+                Ok(())
+            }
+            Operation::Delete {
+                ids,
+                where_metadata,
+                where_document,
+            } => {
+                let ids = ids
+                    .as_ref()
+                    .map(|ids| ids.iter().map(String::as_str).collect());
+                self.collection
+                    .delete(ids, where_metadata.clone(), where_document.clone())
+                    .await
+            }
+        }
+    }
+
+    /// Read the journaled operations. A last line cut short by a crash mid-write is dropped, and the journal rewritten without it.
+    fn load(&self) -> Result<Vec<Operation>> {
+        let contents = match fs::read_to_string(&self.path) {
+            Ok(contents) => contents,
+            Err(e) if e.kind() == ErrorKind::NotFound => return Ok(vec![]),
+            Err(e) => return Err(e.into()),
+        };
+        let torn = !contents.is_empty() && !contents.ends_with('\n');
+        let lines: Vec<&str> = contents.lines().collect();
+        let mut operations = Vec::with_capacity(lines.len());
+        for (index, line) in lines.iter().enumerate() {
+            if line.trim().is_empty() {
+                continue;
+            }
+            match serde_json::from_str(line) {
+                Ok(operation) => operations.push(operation),
+                Err(e) if torn && index + 1 == lines.len() => {
+                    log::warn!(
+                        "Dropping the incomplete last line of the journal {}: {e}",
+                        self.path.display()
+                    );
+                }
+                Err(e) => {
+                    return Err(ChromaError::JournalCorrupted {
+                        line: index + 1,
+                        message: e.to_string(),
+                    })
+                }
+            }
+        }
+        if torn {
+            self.save(&operations)?;
+        }
+        Ok(operations)
+    }
+
+    /// Replace the journal with the given operations, through a temporary file so that a crash leaves either version intact.
+    fn save(&self, operations: &[Operation]) -> Result<()> {
+        let mut tmp_path = self.path.clone().into_os_string();
+        tmp_path.push(".tmp");
+        let tmp_path = PathBuf::from(tmp_path);
+
+        let mut file = File::create(&tmp_path)?;
+        for operation in operations {
+            file.write_all(&journal_line(operation)?)?;
+        }
+        file.sync_all()?;
+        fs::rename(&tmp_path, &self.path)?;
+        Ok(())
+    }
+
+    fn append(&self, operation: &Operation) -> Result<()> {
+        let mut file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.path)?;
+        file.write_all(&journal_line(operation)?)?;
+        file.sync_data()?;
+        Ok(())
+    }
+}
+
+fn journal_line(operation: &Operation) -> Result<Vec<u8>> {
+    let mut line = serde_json::to_vec(operation).map_err(std::io::Error::from)?;
+    line.push(b'\n');
+    Ok(line)
+}
+
+/// Turn the entries into an owned operation, computing the embeddings from the documents if an embedding function is given.
+async fn upsert_operation(
+    collection_entries: CollectionEntries<'_>,
+    embedding_function: Option<Box<dyn EmbeddingFunction>>,
+) -> Result<Operation> {
+    let CollectionEntries {
+        ids,
+        embeddings,
+        metadatas,
+        documents,
+    } = collection_entries;
+    let embeddings = match (embeddings, &documents, embedding_function) {
+        (None, Some(documents), Some(embedding_function)) => Some(
+            embedding_function
+                .embed(documents)
+                .await
+                .map_err(ChromaError::EmbeddingError)?,
+        ),
+        (Some(_), _, Some(_)) => {
+            return Err(ChromaError::invalid_argument(
+                "embedding_function should be None if embeddings are provided",
+            ))
+        }
+        (embeddings, _, _) => embeddings,
+    };
+    Ok(Operation::Upsert {
+        ids: ids.into_iter().map(str::to_string).collect(),
+        embeddings,
+        metadatas,
+        documents: documents.map(|documents| documents.into_iter().map(str::to_string).collect()),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::api::{APIClientAsync, HttpConfig};
+    use serde_json::json;
+    use std::sync::{Arc, Mutex as StdMutex};
+    use std::time::Duration;
+    use wiremock::matchers::{method, path_regex};
+    use wiremock::{Mock, MockServer, Request, ResponseTemplate};
+
+    fn collection_at(endpoint: String, request_timeout: Option<Duration>) -> ChromaCollection {
+        ChromaCollection::mock(
+            APIClientAsync::new(
+                endpoint,
+                Default::default(),
+                "default_tenant".to_string(),
+                "default_database".to_string(),
+                HttpConfig {
+                    request_timeout,
+                    ..Default::default()
+                },
+            )
+            .unwrap(),
+        )
+    }
+
+    /// A collection whose server refuses every connection.
+    fn offline_collection() -> ChromaCollection {
+        collection_at("http://127.0.0.1:1".to_string(), None)
+    }
+
+    fn journal_path(name: &str) -> PathBuf {
+        std::env::temp_dir().join(format!("chromadb-journal-{name}-{}", uuid::Uuid::new_v4()))
+    }
+
+    fn entries<'a>(ids: Vec<&'a str>) -> CollectionEntries<'a> {
+        let embeddings = ids.iter().map(|_| vec![0.5, 0.25]).collect();
+        CollectionEntries {
+            ids,
+            embeddings: Some(embeddings),
+            metadatas: None,
+            documents: None,
+        }
+    }
+
+    /// Record the operation and ids of every write the server receives, in order.
+    async fn record_writes(server: &MockServer) -> Arc<StdMutex<Vec<String>>> {
+        let writes = Arc::new(StdMutex::new(vec![]));
+        let recorded = writes.clone();
+        Mock::given(method("POST"))
+            .and(path_regex("/collections/mock/(add|upsert|delete)$"))
+            .respond_with(move |request: &Request| {
+                let operation = request.url.path().rsplit('/').next().unwrap().to_string();
+                let body: Value = request.body_json().unwrap();
+                recorded
+                    .lock()
+                    .unwrap()
+                    .push(format!("{operation} {}", body["ids"]));
+                ResponseTemplate::new(200).set_body_json(json!(true))
+            })
+            .mount(server)
+            .await;
+        writes
+    }
+
+    #[tokio::test]
+    async fn test_journal_replays_in_order() {
+        let path = journal_path("replay");
+        let offline = JournaledCollection::new(offline_collection(), &path);
+        assert_eq!(
+            offline.upsert(entries(vec!["a"]), None).await.unwrap(),
+            WriteOutcome::Journaled
+        );
+        assert_eq!(
+            offline.delete(Some(vec!["a"]), None, None).await.unwrap(),
+            WriteOutcome::Journaled
+        );
+        assert_eq!(
+            offline.add(entries(vec!["b", "c"]), None).await.unwrap(),
+            WriteOutcome::Journaled
+        );
+        assert_eq!(offline.pending_operations().await.unwrap(), 3);
+        assert!(matches!(
+            offline.flush().await,
+            Err(ChromaError::Transport(_))
+        ));
+        assert_eq!(offline.pending_operations().await.unwrap(), 3);
+
+        let server = MockServer::start().await;
+        let writes = record_writes(&server).await;
+        let online = JournaledCollection::new(collection_at(server.uri(), None), &path);
+        assert_eq!(online.flush().await.unwrap(), 3);
+        assert_eq!(online.pending_operations().await.unwrap(), 0);
+        assert_eq!(
+            *writes.lock().unwrap(),
+            [r#"upsert ["a"]"#, r#"delete ["a"]"#, r#"upsert ["b","c"]"#]
+        );
+
+        assert_eq!(
+            online.add(entries(vec!["d"]), None).await.unwrap(),
+            WriteOutcome::Sent
+        );
+        assert_eq!(writes.lock().unwrap().last().unwrap(), r#"add ["d"]"#);
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_journal_with_intermittent_transport() {
+        let server = MockServer::start().await;
+        // The first upsert times out: the server applied it, but the client cannot know.
+        Mock::given(method("POST"))
+            .and(path_regex("/upsert$"))
+            .respond_with(
+                ResponseTemplate::new(200)
+                    .set_body_json(json!(true))
+                    .set_delay(Duration::from_secs(2)),
+            )
+            .up_to_n_times(1)
+            .with_priority(1)
+            .mount(&server)
+            .await;
+        let writes = record_writes(&server).await;
+        let path = journal_path("intermittent");
+        let journaled = JournaledCollection::new(
+            collection_at(server.uri(), Some(Duration::from_millis(200))),
+            &path,
+        );
+
+        assert_eq!(
+            journaled.upsert(entries(vec!["a"]), None).await.unwrap(),
+            WriteOutcome::Journaled
+        );
+        assert_eq!(journaled.pending_operations().await.unwrap(), 1);
+        assert_eq!(
+            journaled.upsert(entries(vec!["b"]), None).await.unwrap(),
+            WriteOutcome::Sent
+        );
+        assert_eq!(journaled.pending_operations().await.unwrap(), 0);
+        // The timed-out upsert reached the server, then was replayed before the next write.
+        assert_eq!(server.received_requests().await.unwrap().len(), 3);
+        assert_eq!(
+            *writes.lock().unwrap(),
+            [r#"upsert ["a"]"#, r#"upsert ["b"]"#]
+        );
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_journal_corruption() {
+        let path = journal_path("corrupted");
+        let line = String::from_utf8(
+            journal_line(&Operation::Delete {
+                ids: Some(vec!["a".to_string()]),
+                where_metadata: None,
+                where_document: None,
+            })
+            .unwrap(),
+        )
+        .unwrap();
+
+        // A last line cut short is dropped, and the journal stays appendable.
+        fs::write(&path, format!("{line}{{\"op\":\"ups")).unwrap();
+        let offline = JournaledCollection::new(offline_collection(), &path);
+        assert_eq!(offline.pending_operations().await.unwrap(), 1);
+        offline.delete(Some(vec!["b"]), None, None).await.unwrap();
+        assert_eq!(offline.pending_operations().await.unwrap(), 2);
+
+        // Anything else is reported, without replaying.
+        fs::write(&path, format!("{line}not json\n{line}")).unwrap();
+        assert!(matches!(
+            offline.pending_operations().await,
+            Err(ChromaError::JournalCorrupted { line: 2, .. })
+        ));
+        assert!(matches!(
+            offline.flush().await,
+            Err(ChromaError::JournalCorrupted { line: 2, .. })
+        ));
+        fs::remove_file(&path).unwrap();
+    }
+}
@@ -4,40 +4,119 @@
 //! * `client` - To interface with the ChromaDB server.
 //! * `collection` - To interface with an associated ChromaDB collection.
 //!
-//! ### Instantiating [ChromaClient](crate::ChromaClient)
+//! The `store` module wraps both into a simple vector store.
+//! The `journal` module journals writes locally while the server cannot be reached, see [JournaledCollection](crate::journal::JournaledCollection).
+//! The `trace` module exports queries and their results for relevance evaluation, see [QueryLogger](crate::trace::QueryLogger).
+//! The `search` module models the search endpoint of Chroma 1.x servers, see [ChromaCollection::search].
+//! The `middleware` module hooks into every HTTP request, e.g. for metrics or custom auth, see [Middleware](crate::middleware::Middleware).
+//!
+//! With the `blocking` feature, the `blocking` module wraps both for synchronous applications, see `blocking::ChromaClient`.
+//!
+//! With the `tracing` feature, collection operations emit `INFO` spans like `chroma.collection.upsert{collection_id, entry_count}`,
+//! and every HTTP request a `DEBUG` span `chroma.http.request{method, url, attempt, status, latency_ms}` nested in them.
+//!
+//! ### Getting started with [ChromaStore](crate::ChromaStore)
+//! If all you need is a vector store, [ChromaStore] bundles a client, a collection and an embedding function.
 //! ```
-//! use chromadb::client::{ChromaAuthMethod, ChromaClient, ChromaClientOptions, ChromaTokenHeader};
-//! use chromadb::collection::{ChromaCollection, GetResult, GetOptions};
-//! use serde_json::json;
+//!# use std::sync::Arc;
+//! use chromadb::ChromaStore;
+//! use chromadb::embeddings::MockEmbeddingProvider;
+//!
+//!# include!("../doctests/mock_chroma.rs");
+//!# #[tokio::main(flavor = "current_thread")]
+//!# async fn main() -> anyhow::Result<()> {
+//!# let (_server, options) = mock_chroma().await;
+//! // The options of the underlying client, e.g. ChromaClientOptions::default() for http://localhost:8000
+//! let store = ChromaStore::open(options, "my_store", Arc::new(MockEmbeddingProvider)).await?;
 //!
-//!# async fn doc_client_demo() -> anyhow::Result<()> {
+//! let ids = store.add_texts(vec!["9 octopus recipes", "Superman vs Superman"], None).await?;
+//! for hit in store.similarity_search("octopus recipes", 1).await? {
+//!     println!("{} {:?}", hit.id, hit.document);
+//! }
+//!
+//! store.delete_by_ids(&[ids[0].as_str()]).await?;
+//! assert_eq!(store.count().await?, 1);
+//! store.clear().await?;
+//!# Ok(())
+//!# }
+//! ```
+//! Use [ChromaClient] and [ChromaCollection] directly for everything else.
+//!
+//! ### Instantiating [ChromaClient](crate::ChromaClient)
+//! ```no_run
+//!# use chromadb::client::ChromaClient;
+//!# async fn doc_default_client() -> anyhow::Result<()> {
 //! // With default ChromaClientOptions
-//! // Defaults to http://localhost:8000
-//! let client: ChromaClient = ChromaClient::new(Default::default()).await.unwrap();
+//! // The URL defaults to the CHROMA_URL env, or http://localhost:8000
+//! let client: ChromaClient = ChromaClient::new(Default::default()).await?;
+//!# Ok(())
+//!# }
+//! ```
+//!
+//! ```
+//! use chromadb::client::{ChromaAuthMethod, ChromaClient, ChromaClientOptions};
 //!
+//!# include!("../doctests/mock_chroma.rs");
+//!# #[tokio::main(flavor = "current_thread")]
+//!# async fn main() -> anyhow::Result<()> {
+//!# let (server, _) = mock_chroma().await;
 //! // With custom ChromaClientOptions
-//! let auth = ChromaAuthMethod::TokenAuth {
-//!     token: "<TOKEN>".to_string(),
-//!     header: ChromaTokenHeader::Authorization
-//! };
-//! let client: ChromaClient = ChromaClient::new(ChromaClientOptions {
-//!     url: Some("<CHROMADB_URL>".to_string()),
-//!     database: "<DATABASE>".to_string(),
-//!     auth
-//! }).await.unwrap();
-//!
-//! # Ok(())
-//! # }
+//! // A bearer token, see also ChromaAuthMethod::x_chroma_token and ChromaAuthMethod::basic
+//! let auth = ChromaAuthMethod::token("<TOKEN>");
+//!# let chroma_url = server.uri();
+//! let options = ChromaClientOptions::builder()
+//!     .url(&chroma_url)
+//!     .database("my_database")
+//!     .auth(auth)
+//!     .build()?;
+//! let client: ChromaClient = ChromaClient::new(options).await.unwrap();
+//!
+//!# Ok(())
+//!# }
 //! ```
 //! Now that a client is instantiated, we can interface with the ChromaDB server and execute queries.
 //!
+//! ### Adding and searching text in one line
+//!
+//! ```
+//!# use std::sync::Arc;
+//!# use chromadb::ChromaClient;
+//!# use chromadb::embeddings::MockEmbeddingProvider;
+//!# include!("../doctests/mock_chroma.rs");
+//!# #[tokio::main(flavor = "current_thread")]
+//!# async fn main() -> anyhow::Result<()> {
+//!# let (_server, options) = mock_chroma().await;
+//!# let client = ChromaClient::new(options).await?;
+//! // Attach an embedding function to the collection once...
+//! let collection = client
+//!     .get_or_create_collection("my_collection", None)
+//!     .await?
+//!     .with_embedding_function(Arc::new(MockEmbeddingProvider));
+//!
+//! // ...add some texts, ids are generated for you...
+//! let ids = collection
+//!     .add_texts(vec!["9 octopus recipes", "Superman vs Superman"], None, None)
+//!     .await?;
+//!
+//! // ...then search with plain text and get back flattened hits.
+//! for hit in collection.similarity_search("octopus recipes", 3, None, None).await? {
+//!     println!("{} {:?} {:?}", hit.id, hit.distance, hit.document);
+//! }
+//!# Ok(())
+//!# }
+//! ```
+//!
 //! ### Collection Queries
 //!
 //! ```
 //!# use chromadb::ChromaClient;
-//!# use chromadb::collection::{ChromaCollection, GetResult, CollectionEntries, GetOptions};
+//!# use chromadb::collection::{ChromaCollection, GetResult, CollectionEntries, GetOptions, Include};
 //!# use serde_json::json;
-//!# async fn doc_client_create_collection(client: &ChromaClient) -> anyhow::Result<()> {
+//!# include!("../doctests/mock_chroma.rs");
+//!# #[tokio::main(flavor = "current_thread")]
+//!# async fn main() -> anyhow::Result<()> {
+//!# let (_server, options) = mock_chroma().await;
+//!# let client = ChromaClient::new(options).await?;
 //! // Get or create a collection with the given name and no metadata.
 //! let collection: ChromaCollection = client.get_or_create_collection("my_collection", None).await?;
 //!
@@ -72,7 +151,8 @@
 //!     limit: Some(1),
 //!     offset: None,
 //!     where_document: Some(where_document),
-//!     include: Some(vec!["documents".into(),"embeddings".into()])
+//!     include: Some(vec![Include::Documents, Include::Embeddings]),
+//!     sort: None,
 //! };
 //!
 //! let get_result: GetResult = collection.get(get_query).await?;
@@ -86,8 +166,13 @@
 //! ### Perform a similarity search.
 //! ```
 //!# use chromadb::collection::{ChromaCollection, QueryResult, QueryOptions};
-//!# use serde_json::json;
-//!# async fn doc_query_collection(collection: &ChromaCollection) -> anyhow::Result<()> {
+//!# use chromadb::ChromaClient;
+//!# include!("../doctests/mock_chroma.rs");
+//!# #[tokio::main(flavor = "current_thread")]
+//!# async fn main() -> anyhow::Result<()> {
+//!# let (_server, options) = mock_chroma().await;
+//!# let client = ChromaClient::new(options).await?;
+//!# let collection = client.get_or_create_collection("my_collection", None).await?;
 //! //Instantiate QueryOptions to perform a similarity search on the collection
 //! //Alternatively, an embedding_function can also be provided with query_texts to perform the search
 //! let query = QueryOptions {
@@ -97,25 +182,67 @@
 //!     where_document: None,
 //!     n_results: Some(5),
 //!     include: None,
+//!     exclude_ids: None,
+//!     exclude_overfetch: None,
+//!     timeout: None,
 //! };
 //!
 //! let query_result: QueryResult = collection.query(query, None).await?;
 //! println!("Query result: {:?}", query_result);
+//!
+//! // The same search, as a one-liner for the common case
+//! let query_result = collection.query_embeddings(vec![vec![0.0_f32; 768]], 5).await?;
+//!# Ok(())
+//!# }
+//! ```
+//!
+//! With an embedding function, query with plain text:
+//! ```
+//!# use chromadb::collection::ChromaCollection;
+//!# use chromadb::embeddings::MockEmbeddingProvider;
+//!# use chromadb::ChromaClient;
+//!# include!("../doctests/mock_chroma.rs");
+//!# #[tokio::main(flavor = "current_thread")]
+//!# async fn main() -> anyhow::Result<()> {
+//!# let (_server, options) = mock_chroma().await;
+//!# let client = ChromaClient::new(options).await?;
+//!# let collection = client.get_or_create_collection("my_collection", None).await?;
+//! let query_result = collection
+//!     .query_texts(&["octopus recipes"], 5, Box::new(MockEmbeddingProvider))
+//!     .await?;
 //!# Ok(())
 //!# }
 //! ```
 //!
 //! ### Support for Embedding providers
-//! This crate has built-in support for OpenAI and SBERT embeddings.
+//! This crate has built-in support for OpenAI, Cohere, Voyage AI, Jina AI, Google Gemini, Ollama and FastEmbed embeddings.
+//!
+//! To use [Cohere](https://docs.cohere.com/reference/embed) embeddings, enable the `cohere` feature in your Cargo.toml
+//! and pass [CohereEmbeddings](crate::embeddings::cohere::CohereEmbeddings) wherever an embedding function is accepted.
+//!
+//! To use [Voyage AI](https://docs.voyageai.com/reference/embeddings-api) embeddings, enable the `voyage` feature in your Cargo.toml
+//! and pass [VoyageEmbeddings](crate::embeddings::voyage::VoyageEmbeddings) wherever an embedding function is accepted.
+//!
+//! To use [Jina AI](https://jina.ai/embeddings/) embeddings, enable the `jina` feature in your Cargo.toml
+//! and pass [JinaEmbeddings](crate::embeddings::jina::JinaEmbeddings) wherever an embedding function is accepted.
+//!
+//! To use [Google Gemini](https://ai.google.dev/gemini-api/docs/embeddings) embeddings, enable the `gemini` feature in your Cargo.toml
+//! and pass [GeminiEmbeddings](crate::embeddings::gemini::GeminiEmbeddings) wherever an embedding function is accepted.
+//!
+//! To use local [Ollama](https://github.com/ollama/ollama/blob/main/docs/api.md#generate-embeddings) embeddings, like `nomic-embed-text`, enable the `ollama` feature in your Cargo.toml
+//! and pass [OllamaEmbeddings](crate::embeddings::ollama::OllamaEmbeddings) wherever an embedding function is accepted.
+//!
+//! To use local [FastEmbed](https://github.com/Anush008/fastembed-rs) embeddings, like `BAAI/bge-small-en-v1.5`, without network calls,
+//! enable the `fastembed` feature in your Cargo.toml and pass [FastEmbedEmbeddings](crate::embeddings::fastembed::FastEmbedEmbeddings) wherever an embedding function is accepted.
 //!
 //! To use [OpenAI](https://platform.openai.com/docs/guides/embeddings) embeddings, enable the `openai` feature in your Cargo.toml.
 //!
-//! ```ignore
-//!# use chromadb::ChromaClient;
-//!# use chromadb::collection::{ChromaCollection, GetResult, CollectionEntries, GetOptions};
-//!# use chromadb::embeddings::openai::OpenAIEmbeddings;
-//!# use serde_json::json;
-//!# async fn doc_client_create_collection(client: &ChromaClient) -> anyhow::Result<()> {
+//! ```no_run
+//!# #[cfg(feature = "openai")]
+//!# async fn doc_openai_upsert(client: &chromadb::ChromaClient) -> anyhow::Result<()> {
+//!# use chromadb::collection::{ChromaCollection, CollectionEntries};
+//! use chromadb::embeddings::openai::OpenAIEmbeddings;
+//!
 //! let collection: ChromaCollection = client.get_or_create_collection("openai_collection",
 //! None).await?;
 //!
@@ -135,42 +262,33 @@
 //!# }
 //! ```
 //!
-//! To use [SBERT](https://docs.rs/crate/rust-bert/latest) embeddings, enable the `bert` feature in your Cargo.toml.
-//!
-//! ```ignore
-//!# use chromadb::ChromaClient;
-//!# use chromadb::collection::{ChromaCollection, GetResult, CollectionEntries, GetOptions};
-//!# use serde_json::json;
-//!# use chromadb::embeddings::bert::{SentenceEmbeddingsBuilder, SentenceEmbeddingsModelType};
-//!# async fn doc_client_create_collection(client: &ChromaClient) -> anyhow::Result<()> {
-//! let collection: ChromaCollection = client.get_or_create_collection("sbert_collection",
-//! None).await?;
-//!
-//! let collection_entries = CollectionEntries {
-//!   ids: vec!["demo-id-1", "demo-id-2"],
-//!   embeddings: None,
-//!   metadatas: None,
-//!   documents: Some(vec![
-//!            "Some document about 9 octopus recipies",
-//!            "Some other document about DCEU Superman Vs CW Superman"])
-//! };
-//!
-//! // Use SBERT embeddings
-//! let sbert_embeddings = SentenceEmbeddingsBuilder::remote(
-//!                         SentenceEmbeddingsModelType::AllMiniLmL6V2
-//!                        ).create_model()?;
-//!
-//! collection.upsert(collection_entries, Some(Box::new(sbert_embeddings))).await?;
-//!# Ok(())
-//!# }
-//! ```
+//! To avoid re-embedding unchanged documents, wrap any embedding function in a
+//! [CachedEmbeddingFunction](crate::embeddings::cache::CachedEmbeddingFunction). An in-memory LRU cache,
+//! [InMemoryLruCache](crate::embeddings::cache::InMemoryLruCache), is enabled with the `embedding-cache` feature.
 
+#[cfg(feature = "blocking")]
+pub mod blocking;
 pub mod client;
 pub mod collection;
 pub mod embeddings;
+pub mod error;
+pub mod journal;
+pub mod middleware;
+#[cfg(feature = "quantization")]
+pub mod quantization;
+pub mod retry;
+pub mod schema;
+pub mod search;
+pub mod store;
+pub mod trace;
+pub mod version;
+pub mod warnings;
 
 mod api;
 mod commons;
 
 pub use client::ChromaClient;
 pub use collection::ChromaCollection;
+pub use error::ChromaError;
+pub use store::ChromaStore;
+pub use warnings::{Warning, Warnings};
@@ -0,0 +1,192 @@
+use std::fmt;
+use std::sync::Arc;
+
+use async_trait::async_trait;
+use reqwest::header::HeaderMap;
+use reqwest::Method;
+
+pub use super::api::ResponseMeta;
+
+/// Hooks around every HTTP request the client sends, e.g. to record latency per endpoint or to inject short-lived auth tokens.
+/// Set them with [ChromaClientOptions::middleware](crate::client::ChromaClientOptions::middleware).
+///
+/// They run in order before every attempt of a request, retries included, and after every response.
+/// They are awaited on the requesting task, so they should be quick.
+#[async_trait]
+pub trait Middleware: Send + Sync {
+    /// Called before the request is sent. Headers set here replace the ones of the same name, like the auth header.
+    async fn on_request(&self, _request: &mut RequestParts) {}
+
+    /// Called when a response arrives, whatever its status. Not called if the request could not be sent.
+    /// `elapsed` is the time of this attempt only.
+    async fn on_response(&self, _response: &ResponseMeta) {}
+}
+
+impl fmt::Debug for dyn Middleware {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("Middleware")
+    }
+}
+
+/// The method, path and extra headers of a request about to be sent, as passed to [Middleware::on_request].
+/// Only the headers can be changed.
+#[derive(Debug, Clone)]
+pub struct RequestParts {
+    method: Method,
+    path: String,
+    headers: HeaderMap,
+}
+
+impl RequestParts {
+    /// The HTTP method, like `POST`.
+    pub fn method(&self) -> &str {
+        self.method.as_str()
+    }
+
+    /// The path of the URL, like `/api/v2/tenants/default_tenant/databases/default_database/collections`.
+    pub fn path(&self) -> &str {
+        &self.path
+    }
+
+    /// The headers set by the middleware so far. The ones set by the client are not included.
+    pub fn headers(&self) -> &HeaderMap {
+        &self.headers
+    }
+
+    pub fn headers_mut(&mut self) -> &mut HeaderMap {
+        &mut self.headers
+    }
+}
+
+/// Run the [Middleware::on_request] hooks for a request to `url`, returning the headers they set.
+pub(crate) async fn on_request(
+    middleware: &[Arc<dyn Middleware>],
+    method: &Method,
+    url: &str,
+) -> HeaderMap {
+    if middleware.is_empty() {
+        return HeaderMap::new();
+    }
+    let path = match reqwest::Url::parse(url) {
+        Ok(url) => url.path().to_string(),
+        Err(_) => url.to_string(),
+    };
+    let mut request = RequestParts {
+        method: method.clone(),
+        path,
+        headers: HeaderMap::new(),
+    };
+    for middleware in middleware {
+        middleware.on_request(&mut request).await;
+    }
+    request.headers
+}
+
+/// Run the [Middleware::on_response] hooks.
+pub(crate) async fn on_response(middleware: &[Arc<dyn Middleware>], response: &ResponseMeta) {
+    for middleware in middleware {
+        middleware.on_response(response).await;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Mutex;
+    use std::time::Duration;
+
+    use reqwest::header::{HeaderValue, AUTHORIZATION};
+    use serde_json::json;
+    use wiremock::matchers::{header, method, path_regex};
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+
+    use super::*;
+    use crate::client::{ChromaAuthMethod, ChromaClientOptions};
+    use crate::retry::RetryPolicy;
+    use crate::ChromaClient;
+
+    /// Replaces the static token with a fresh one for every attempt, and records what it sees.
+    #[derive(Default)]
+    struct Recorder {
+        tokens: AtomicUsize,
+        requests: Mutex<Vec<String>>,
+        responses: Mutex<Vec<(u16, String)>>,
+    }
+
+    #[async_trait]
+    impl Middleware for Recorder {
+        async fn on_request(&self, request: &mut RequestParts) {
+            let token = self.tokens.fetch_add(1, Ordering::SeqCst);
+            request.headers_mut().insert(
+                AUTHORIZATION,
+                HeaderValue::from_str(&format!("Bearer fresh-{token}")).unwrap(),
+            );
+            self.requests
+                .lock()
+                .unwrap()
+                .push(format!("{} {}", request.method(), request.path()));
+        }
+
+        async fn on_response(&self, response: &ResponseMeta) {
+            assert!(response.elapsed < Duration::from_secs(5));
+            self.responses
+                .lock()
+                .unwrap()
+                .push((response.status, response.path.clone()));
+        }
+    }
+
+    #[tokio::test]
+    async fn test_middleware() {
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path_regex("/heartbeat$"))
+            .and(header("authorization", "Bearer fresh-1"))
+            .respond_with(ResponseTemplate::new(503))
+            .mount(&server)
+            .await;
+        Mock::given(method("GET"))
+            .and(path_regex("/heartbeat$"))
+            .and(header("authorization", "Bearer fresh-2"))
+            .respond_with(
+                ResponseTemplate::new(200).set_body_json(json!({"nanosecond heartbeat": 1})),
+            )
+            .mount(&server)
+            .await;
+
+        let recorder = Arc::new(Recorder::default());
+        let options = ChromaClientOptions::builder()
+            .url(server.uri())
+            .auth(ChromaAuthMethod::token("static"))
+            .tenant("default_tenant")
+            .database("default_database")
+            .retry_policy(RetryPolicy {
+                initial_delay: Duration::from_millis(1),
+                max_delay: Duration::from_millis(2),
+                ..Default::default()
+            })
+            .middleware(recorder.clone())
+            .build()
+            .unwrap();
+        let client = ChromaClient::new(options).await.unwrap();
+        assert_eq!(client.heartbeat().await.unwrap(), 1);
+
+        // The identity request goes through the middleware too, and is answered 404 without a mock.
+        assert_eq!(
+            *recorder.requests.lock().unwrap(),
+            [
+                "GET /api/v2/auth/identity",
+                "GET /api/v1/heartbeat",
+                "GET /api/v1/heartbeat"
+            ]
+        );
+        assert_eq!(
+            *recorder.responses.lock().unwrap(),
+            [
+                (404, "/api/v2/auth/identity".to_string()),
+                (503, "/api/v1/heartbeat".to_string()),
+                (200, "/api/v1/heartbeat".to_string()),
+            ]
+        );
+    }
+}
@@ -0,0 +1,290 @@
+//! Client-side support for int8-quantized embeddings.
+//!
+//! Chroma only stores `f32` embeddings: nothing here is server-native. Quantized embeddings are dequantized to floats
+//! before they are written, and their scale and offset are kept in the record's metadata under the `chromadb-rs:`
+//! keys below, so that [EmbeddingData::from_record] can recover the exact int8 values on read.
+//! Storage and search on the server are the same as for any float embedding.
+//!
+//! The round trip is exact as long as the floats are sent at full precision, i.e. without
+//! [ChromaCollection::with_float_precision](crate::ChromaCollection::with_float_precision).
+
+use serde_json::{Number, Value};
+
+use super::collection::{CollectionEntries, GetResult};
+use super::commons::{Documents, Embedding, Metadata, Metadatas};
+
+/// The record metadata key marking an embedding as int8-quantized.
+pub const QUANTIZATION_KEY: &str = "chromadb-rs:quantization";
+/// The record metadata key holding the scale of an int8-quantized embedding.
+pub const QUANTIZATION_SCALE_KEY: &str = "chromadb-rs:quantization_scale";
+/// The record metadata key holding the offset of an int8-quantized embedding.
+pub const QUANTIZATION_OFFSET_KEY: &str = "chromadb-rs:quantization_offset";
+
+const INT8: &str = "int8";
+const F32: &str = "f32";
+
+/// An embedding, either as floats or int8-quantized. Each float is `value as f32 * scale + offset`.
+#[derive(Debug, Clone, PartialEq)]
+pub enum EmbeddingData {
+    F32(Embedding),
+    I8 {
+        values: Vec<i8>,
+        scale: f32,
+        offset: f32,
+    },
+}
+
+impl EmbeddingData {
+    /// Quantize floats to int8, mapping their range onto -128..=127.
+    pub fn quantize(embedding: &[f32]) -> Self {
+        let (min, max) = embedding
+            .iter()
+            .fold((f32::INFINITY, f32::NEG_INFINITY), |(min, max), &x| {
+                (min.min(x), max.max(x))
+            });
+        if embedding.is_empty() || min == max {
+            return Self::I8 {
+                values: vec![0; embedding.len()],
+                scale: 1.0,
+                offset: if embedding.is_empty() { 0.0 } else { min },
+            };
+        }
+        let scale = (max - min) / 255.0;
+        let offset = min + 128.0 * scale;
+        let values = embedding
+            .iter()
+            .map(|&x| ((x - offset) / scale).round().clamp(-128.0, 127.0) as i8)
+            .collect();
+        Self::I8 {
+            values,
+            scale,
+            offset,
+        }
+    }
+
+    /// The embedding as floats, as sent to and stored by Chroma.
+    pub fn dequantize(&self) -> Embedding {
+        match self {
+            Self::F32(embedding) => embedding.clone(),
+            Self::I8 {
+                values,
+                scale,
+                offset,
+            } => values
+                .iter()
+                .map(|&v| (v as f64 * *scale as f64 + *offset as f64) as f32)
+                .collect(),
+        }
+    }
+
+    /// Recover the embedding of a record written from [QuantizedEntries], using the quantization kept in its metadata.
+    /// Records without it are returned as [EmbeddingData::F32].
+    pub fn from_record(embedding: &[f32], metadata: Option<&Metadata>) -> Self {
+        let quantization = metadata.and_then(|metadata| {
+            if metadata.get(QUANTIZATION_KEY)?.as_str()? != INT8 {
+                return None;
+            }
+            let scale = metadata.get(QUANTIZATION_SCALE_KEY)?.as_f64()?;
+            let offset = metadata.get(QUANTIZATION_OFFSET_KEY)?.as_f64()?;
+            Some((scale as f32, offset as f32))
+        });
+        match quantization {
+            Some((scale, offset)) if scale != 0.0 => Self::I8 {
+                values: embedding
+                    .iter()
+                    .map(|&x| {
+                        ((x as f64 - offset as f64) / scale as f64)
+                            .round()
+                            .clamp(-128.0, 127.0) as i8
+                    })
+                    .collect(),
+                scale,
+                offset,
+            },
+            _ => Self::F32(embedding.to_vec()),
+        }
+    }
+
+    /// Record the quantization in the record's metadata, so that it can be recovered on read.
+    /// Float embeddings are marked too, since Chroma rejects empty metadata in a batch that has some.
+    fn write_metadata(&self, metadata: &mut Metadata) {
+        let Self::I8 { scale, offset, .. } = self else {
+            metadata.insert(QUANTIZATION_KEY.to_string(), Value::from(F32));
+            return;
+        };
+        metadata.insert(QUANTIZATION_KEY.to_string(), Value::from(INT8));
+        for (key, value) in [
+            (QUANTIZATION_SCALE_KEY, scale),
+            (QUANTIZATION_OFFSET_KEY, offset),
+        ] {
+            // Scales and offsets of finite embeddings are finite.
+            let value = Number::from_f64(*value as f64).map_or(Value::Null, Value::Number);
+            metadata.insert(key.to_string(), value);
+        }
+    }
+}
+
+/// Entries with possibly quantized embeddings. Convert them into [CollectionEntries] to write them with any write method.
+#[derive(Debug, Default)]
+pub struct QuantizedEntries<'a> {
+    pub ids: Vec<&'a str>,
+    pub embeddings: Vec<EmbeddingData>,
+    pub metadatas: Option<Metadatas>,
+    pub documents: Option<Documents<'a>>,
+}
+
+impl<'a> From<QuantizedEntries<'a>> for CollectionEntries<'a> {
+    /// Dequantize the embeddings and add the quantization of each int8 embedding to its record's metadata.
+    fn from(entries: QuantizedEntries<'a>) -> Self {
+        let QuantizedEntries {
+            ids,
+            embeddings,
+            metadatas,
+            documents,
+        } = entries;
+        let quantized = embeddings
+            .iter()
+            .any(|embedding| matches!(embedding, EmbeddingData::I8 { .. }));
+        let metadatas = match metadatas {
+            Some(mut metadatas) => {
+                // Mismatched lengths are reported by the write's validation.
+                for (metadata, embedding) in metadatas.iter_mut().zip(&embeddings) {
+                    embedding.write_metadata(metadata);
+                }
+                Some(metadatas)
+            }
+            None if quantized => Some(
+                embeddings
+                    .iter()
+                    .map(|embedding| {
+                        let mut metadata = Metadata::new();
+                        embedding.write_metadata(&mut metadata);
+                        metadata
+                    })
+                    .collect(),
+            ),
+            None => None,
+        };
+        CollectionEntries {
+            ids,
+            metadatas,
+            documents,
+            embeddings: Some(embeddings.iter().map(EmbeddingData::dequantize).collect()),
+        }
+    }
+}
+
+impl GetResult {
+    /// The embeddings of the records, recovering the int8 values of those written from [QuantizedEntries].
+    /// `None` unless both embeddings and metadatas were included.
+    pub fn quantized_embeddings(&self) -> Option<Vec<Option<EmbeddingData>>> {
+        let embeddings = self.embeddings.as_ref()?;
+        let metadatas = self.metadatas.as_ref()?;
+        Some(
+            embeddings
+                .iter()
+                .zip(metadatas)
+                .map(|(embedding, metadata)| {
+                    let embedding = embedding.as_ref()?;
+                    Some(EmbeddingData::from_record(embedding, metadata.as_ref()))
+                })
+                .collect(),
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_quantize_round_trip() {
+        let embedding = vec![-0.5, -0.25, 0.0, 0.1, 0.75];
+        let quantized = EmbeddingData::quantize(&embedding);
+        let EmbeddingData::I8 {
+            values,
+            scale,
+            offset,
+        } = &quantized
+        else {
+            panic!("expected an int8 embedding");
+        };
+        assert_eq!(values.first(), Some(&-128));
+        assert_eq!(values.last(), Some(&127));
+        for (x, y) in embedding.iter().zip(quantized.dequantize()) {
+            assert!((x - y).abs() <= scale / 2.0 + f32::EPSILON, "{x} {y}");
+        }
+
+        // Every int8 value survives dequantization, JSON and re-quantization exactly.
+        let all = EmbeddingData::I8 {
+            values: (i8::MIN..=i8::MAX).collect(),
+            scale: *scale,
+            offset: *offset,
+        };
+        let mut metadata = Metadata::new();
+        all.write_metadata(&mut metadata);
+        let sent: Embedding = serde_json::from_value(json!(all.dequantize())).unwrap();
+        let metadata: Metadata = serde_json::from_value(json!(metadata)).unwrap();
+        assert_eq!(EmbeddingData::from_record(&sent, Some(&metadata)), all);
+
+        assert_eq!(
+            EmbeddingData::quantize(&[2.0, 2.0]).dequantize(),
+            vec![2.0, 2.0]
+        );
+        assert_eq!(
+            EmbeddingData::from_record(&[0.5], None),
+            EmbeddingData::F32(vec![0.5])
+        );
+    }
+
+    #[test]
+    fn test_quantized_entries() {
+        let int8 = EmbeddingData::I8 {
+            values: vec![-1, 0, 1],
+            scale: 0.5,
+            offset: 0.25,
+        };
+        let entries: CollectionEntries = QuantizedEntries {
+            ids: vec!["q", "f"],
+            embeddings: vec![int8.clone(), EmbeddingData::F32(vec![0.1, 0.2, 0.3])],
+            metadatas: None,
+            documents: None,
+        }
+        .into();
+        assert_eq!(
+            entries.embeddings,
+            Some(vec![vec![-0.25, 0.25, 0.75], vec![0.1, 0.2, 0.3]])
+        );
+        let metadatas = entries.metadatas.unwrap();
+        assert_eq!(
+            Value::Object(metadatas[0].clone()),
+            json!({
+                QUANTIZATION_KEY: "int8",
+                QUANTIZATION_SCALE_KEY: 0.5,
+                QUANTIZATION_OFFSET_KEY: 0.25,
+            })
+        );
+        assert_eq!(
+            Value::Object(metadatas[1].clone()),
+            json!({QUANTIZATION_KEY: "f32"})
+        );
+
+        let result = GetResult {
+            ids: vec!["q".into(), "f".into()],
+            embeddings: entries
+                .embeddings
+                .map(|e| e.into_iter().map(Some).collect()),
+            metadatas: Some(metadatas.into_iter().map(Some).collect()),
+            documents: None,
+            total: None,
+        };
+        assert_eq!(
+            result.quantized_embeddings(),
+            Some(vec![
+                Some(int8),
+                Some(EmbeddingData::F32(vec![0.1, 0.2, 0.3]))
+            ])
+        );
+    }
+}
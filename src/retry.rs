@@ -0,0 +1,236 @@
+use std::collections::hash_map::RandomState;
+use std::hash::{BuildHasher, Hasher};
+use std::time::Duration;
+
+/// How to retry requests that failed with a transient error.
+///
+/// Requests are retried when the server responds with one of the `retryable_statuses`,
+/// or when the connection fails or times out. The delay before each retry grows by `backoff_multiplier`,
+/// is capped at `max_delay`, and is jittered down by up to half so that clients do not retry in lockstep.
+/// When the response asks to wait longer with a `Retry-After` header, the retry waits that long instead,
+/// unless it is longer than `max_delay`: the request then fails with [ChromaError::RateLimited](crate::ChromaError::RateLimited)
+/// or [ChromaError::ServerError](crate::ChromaError::ServerError), for the caller to decide.
+#[derive(Debug, Clone, PartialEq)]
+pub struct RetryPolicy {
+    /// The total number of attempts, including the first one.
+    pub max_attempts: u32,
+    pub initial_delay: Duration,
+    pub max_delay: Duration,
+    pub backoff_multiplier: f64,
+    pub retryable_statuses: Vec<u16>,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            max_attempts: 3,
+            initial_delay: Duration::from_millis(200),
+            max_delay: Duration::from_secs(5),
+            backoff_multiplier: 2.0,
+            retryable_statuses: vec![429, 502, 503, 504],
+        }
+    }
+}
+
+impl RetryPolicy {
+    pub(crate) fn is_retryable_status(&self, status: u16) -> bool {
+        self.retryable_statuses.contains(&status)
+    }
+
+    pub(crate) fn is_retryable_error(&self, error: &reqwest::Error) -> bool {
+        error.is_connect() || error.is_timeout()
+    }
+
+    /// The delay before the retry that follows the given 1-based attempt, before jitter.
+    fn backoff(&self, attempt: u32) -> Duration {
+        let factor = self
+            .backoff_multiplier
+            .max(1.0)
+            .powi(attempt.saturating_sub(1) as i32);
+        self.initial_delay
+            .mul_f64(factor.min(u32::MAX as f64))
+            .min(self.max_delay)
+    }
+
+    /// The jittered delay before the retry that follows the given 1-based attempt.
+    pub(crate) fn delay(&self, attempt: u32) -> Duration {
+        let backoff = self.backoff(attempt);
+        let jitter = RandomState::new().build_hasher().finish() as f64 / u64::MAX as f64;
+        backoff.mul_f64(1.0 - jitter / 2.0)
+    }
+
+    /// The delay before the retry that follows the given 1-based attempt, at least the `Retry-After` of the response.
+    /// `None` if the response asks to wait longer than `max_delay`.
+    pub(crate) fn delay_after(
+        &self,
+        attempt: u32,
+        retry_after: Option<Duration>,
+    ) -> Option<Duration> {
+        match retry_after {
+            Some(retry_after) if retry_after > self.max_delay => None,
+            Some(retry_after) => Some(self.delay(attempt).max(retry_after)),
+            None => Some(self.delay(attempt)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::api::APIClientAsync;
+    use wiremock::matchers::method;
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+
+    fn fast_policy() -> RetryPolicy {
+        RetryPolicy {
+            initial_delay: Duration::from_millis(1),
+            max_delay: Duration::from_millis(2),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_retry_delay() {
+        let policy = RetryPolicy::default();
+        assert_eq!(policy.backoff(1), Duration::from_millis(200));
+        assert_eq!(policy.backoff(2), Duration::from_millis(400));
+        assert_eq!(policy.backoff(5), Duration::from_millis(3200));
+        assert_eq!(policy.backoff(6), Duration::from_secs(5));
+        assert_eq!(policy.backoff(1000), Duration::from_secs(5));
+        for attempt in 1..10 {
+            let delay = policy.delay(attempt);
+            assert!(delay <= policy.backoff(attempt));
+            assert!(delay >= policy.backoff(attempt) / 2);
+        }
+
+        assert!(policy.delay_after(1, None).unwrap() <= policy.backoff(1));
+        assert_eq!(
+            policy.delay_after(1, Some(Duration::from_secs(2))),
+            Some(Duration::from_secs(2))
+        );
+        assert!(policy.delay_after(3, Some(Duration::ZERO)).unwrap() >= policy.backoff(3) / 2);
+        assert_eq!(policy.delay_after(1, Some(Duration::from_secs(6))), None);
+    }
+
+    #[tokio::test]
+    async fn test_retry_transient_errors() {
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .respond_with(ResponseTemplate::new(503))
+            .up_to_n_times(2)
+            .expect(2)
+            .mount(&server)
+            .await;
+        Mock::given(method("GET"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(1))
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        let api = APIClientAsync::new(
+            server.uri(),
+            Default::default(),
+            "default_tenant".to_string(),
+            "default_database".to_string(),
+            Default::default(),
+        )
+        .unwrap()
+        .with_retry_policy(Some(fast_policy()));
+        let response = api.get_v2("/heartbeat").await.unwrap();
+        assert_eq!(response.status(), 200);
+        let stats = api.transport_stats();
+        assert_eq!((stats.requests, stats.retries), (3, 2));
+    }
+
+    #[tokio::test]
+    async fn test_retry_gives_up() {
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .respond_with(ResponseTemplate::new(503))
+            .expect(3)
+            .mount(&server)
+            .await;
+        Mock::given(method("POST"))
+            .respond_with(ResponseTemplate::new(500))
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        let api = APIClientAsync::new(
+            server.uri(),
+            Default::default(),
+            "default_tenant".to_string(),
+            "default_database".to_string(),
+            Default::default(),
+        )
+        .unwrap()
+        .with_retry_policy(Some(fast_policy()));
+        let error = api.get_v2("/heartbeat").await.unwrap_err();
+        assert!(matches!(
+            error,
+            crate::ChromaError::ServerError { status: 503, .. }
+        ));
+        let error = api.post_v2("/tenants", None).await.unwrap_err();
+        assert!(matches!(
+            error,
+            crate::ChromaError::ServerError { status: 500, .. }
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_retry_after() {
+        let server = MockServer::start().await;
+        let rate_limited = |retry_after: &str| {
+            ResponseTemplate::new(429)
+                .insert_header("Retry-After", retry_after)
+                .insert_header("X-RateLimit-Limit", "100")
+                .insert_header("X-RateLimit-Remaining", "0")
+                .set_body_string("Too Many Requests")
+        };
+        Mock::given(method("GET"))
+            .respond_with(rate_limited("0"))
+            .up_to_n_times(1)
+            .expect(1)
+            .mount(&server)
+            .await;
+        Mock::given(method("GET"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(1))
+            .expect(1)
+            .mount(&server)
+            .await;
+        // Longer than the max delay of the policy, so it is not retried.
+        Mock::given(method("POST"))
+            .respond_with(rate_limited("30"))
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        let api = APIClientAsync::new(
+            server.uri(),
+            Default::default(),
+            "default_tenant".to_string(),
+            "default_database".to_string(),
+            Default::default(),
+        )
+        .unwrap()
+        .with_retry_policy(Some(fast_policy()));
+        let response = api.get_v2("/heartbeat").await.unwrap();
+        assert_eq!(response.status(), 200);
+
+        let error = api.post_v2("/tenants", None).await.unwrap_err();
+        match error {
+            crate::ChromaError::RateLimited {
+                retry_after,
+                limit,
+                remaining,
+                body,
+            } => {
+                assert_eq!(retry_after, Some(Duration::from_secs(30)));
+                assert_eq!((limit, remaining), (Some(100), Some(0)));
+                assert!(body.starts_with("Too Many Requests"));
+            }
+            error => panic!("Expected a rate limit error, got {error}"),
+        }
+        assert_eq!(api.transport_stats().retries, 1);
+    }
+}
@@ -0,0 +1,180 @@
+use std::collections::BTreeMap;
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use super::commons::Metadata;
+use super::error::ChromaError;
+
+/// The collection metadata key under which [ChromaCollection::save_metadata_schema](crate::ChromaCollection::save_metadata_schema) stores the schema,
+/// so that every writer of the collection can load and enforce the same one.
+pub const METADATA_SCHEMA_KEY: &str = "chromadb-rs:metadata_schema";
+
+/// The type of a metadata value.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum MetadataType {
+    String,
+    /// A whole number.
+    Int,
+    /// Any number, whole or not.
+    Float,
+    Bool,
+}
+
+impl MetadataType {
+    fn matches(&self, value: &Value) -> bool {
+        match self {
+            Self::String => value.is_string(),
+            Self::Int => value.is_i64() || value.is_u64(),
+            Self::Float => value.is_number(),
+            Self::Bool => value.is_boolean(),
+        }
+    }
+}
+
+/// The keys and types every record's metadata must have, checked client-side before add, upsert and update.
+/// See [ChromaCollection::with_metadata_schema](crate::ChromaCollection::with_metadata_schema).
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq)]
+pub struct MetadataSchema {
+    /// The keys every record must have, with the type of their value.
+    pub required: BTreeMap<String, MetadataType>,
+    /// Whether records may carry keys not listed in `required`.
+    #[serde(default)]
+    pub allow_extra_keys: bool,
+}
+
+impl MetadataSchema {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Require every record to have `key`, with a value of type `metadata_type`.
+    pub fn require(mut self, key: impl Into<String>, metadata_type: MetadataType) -> Self {
+        self.required.insert(key.into(), metadata_type);
+        self
+    }
+
+    pub fn allow_extra_keys(mut self, allow_extra_keys: bool) -> Self {
+        self.allow_extra_keys = allow_extra_keys;
+        self
+    }
+
+    /// Read the schema stored in collection metadata, if any.
+    ///
+    /// # Errors
+    ///
+    /// * If the stored value is not a valid schema
+    pub fn from_collection_metadata(
+        metadata: Option<&Metadata>,
+    ) -> Result<Option<Self>, ChromaError> {
+        let Some(value) = metadata.and_then(|metadata| metadata.get(METADATA_SCHEMA_KEY)) else {
+            return Ok(None);
+        };
+        let Some(json) = value.as_str() else {
+            return Err(ChromaError::invalid_argument(format!(
+                "The {METADATA_SCHEMA_KEY} collection metadata must be a JSON string"
+            )));
+        };
+        serde_json::from_str(json).map(Some).map_err(|e| {
+            ChromaError::invalid_argument(format!(
+                "The {METADATA_SCHEMA_KEY} collection metadata is not a valid schema: {e}"
+            ))
+        })
+    }
+
+    /// Store the schema in collection metadata. Chroma only accepts scalar metadata values, so it is stored as a JSON string.
+    pub fn write_to_collection_metadata(&self, metadata: &mut Metadata) {
+        // Serializing maps with string keys and unit enums cannot fail.
+        let json = serde_json::to_string(self).expect("Failed to serialize the metadata schema");
+        metadata.insert(METADATA_SCHEMA_KEY.to_string(), Value::String(json));
+    }
+
+    /// Describe every way a record's metadata breaks the schema.
+    /// With `partial`, as for an update that merges into the existing metadata, missing required keys are allowed.
+    pub(crate) fn violations(&self, metadata: Option<&Metadata>, partial: bool) -> Vec<String> {
+        let mut violations = vec![];
+        let empty = Metadata::new();
+        let metadata = metadata.unwrap_or(&empty);
+        for (key, metadata_type) in &self.required {
+            match metadata.get(key) {
+                Some(value) if !metadata_type.matches(value) => violations.push(format!(
+                    "Metadata key {key:?} must be of type {metadata_type:?}, got {value}"
+                )),
+                Some(_) => {}
+                None if partial => {}
+                None => violations.push(format!("Missing required metadata key {key:?}")),
+            }
+        }
+        if !self.allow_extra_keys {
+            for key in metadata
+                .keys()
+                .filter(|key| !self.required.contains_key(*key))
+            {
+                violations.push(format!("Metadata key {key:?} is not in the schema"));
+            }
+        }
+        violations
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn schema() -> MetadataSchema {
+        MetadataSchema::new()
+            .require("source", MetadataType::String)
+            .require("tenant_id", MetadataType::Int)
+            .require("created_at", MetadataType::Float)
+    }
+
+    fn metadata(value: Value) -> Metadata {
+        value.as_object().unwrap().clone()
+    }
+
+    #[test]
+    fn test_metadata_schema_violations() {
+        let schema = schema();
+        let valid = metadata(json!({"source": "web", "tenant_id": 7, "created_at": 1700000000}));
+        assert!(schema.violations(Some(&valid), false).is_empty());
+
+        let invalid = metadata(json!({"source": 1, "tenant_id": 7.5, "extra": true}));
+        assert_eq!(
+            schema.violations(Some(&invalid), false),
+            vec![
+                "Missing required metadata key \"created_at\"",
+                "Metadata key \"source\" must be of type String, got 1",
+                "Metadata key \"tenant_id\" must be of type Int, got 7.5",
+                "Metadata key \"extra\" is not in the schema",
+            ]
+        );
+        assert_eq!(schema.violations(None, false).len(), 3);
+        assert!(schema.violations(None, true).is_empty());
+        assert!(schema
+            .clone()
+            .allow_extra_keys(true)
+            .violations(Some(&metadata(json!({"extra": 1}))), true)
+            .is_empty());
+    }
+
+    #[test]
+    fn test_metadata_schema_round_trip() {
+        let schema = schema();
+        let mut collection_metadata = metadata(json!({"owner": "search"}));
+        schema.write_to_collection_metadata(&mut collection_metadata);
+        assert!(collection_metadata[METADATA_SCHEMA_KEY].is_string());
+        assert_eq!(
+            MetadataSchema::from_collection_metadata(Some(&collection_metadata)).unwrap(),
+            Some(schema)
+        );
+        assert_eq!(
+            MetadataSchema::from_collection_metadata(None).unwrap(),
+            None
+        );
+
+        let corrupt = metadata(json!({METADATA_SCHEMA_KEY: "{"}));
+        assert!(MetadataSchema::from_collection_metadata(Some(&corrupt)).is_err());
+    }
+}
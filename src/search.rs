@@ -0,0 +1,215 @@
+use serde::Deserialize;
+use serde_json::{json, Map, Value};
+
+use super::collection::{Include, DEFAULT_N_RESULTS};
+use super::commons::{Embedding, Metadata};
+
+/// A search of [ChromaCollection::search](crate::ChromaCollection::search), the richer endpoint of Chroma 1.x servers.
+///
+/// Only a subset of the endpoint is modeled: a metadata filter, ranking by distance to an embedding, paging and the fields to return.
+/// Set the fields that are not modeled, like other rank expressions, with [raw](SearchRequestBuilder::raw).
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct SearchRequest {
+    /// Rank the records by their distance to this embedding, nearest first. Unranked if `None`.
+    pub query_embedding: Option<Embedding>,
+    /// Filter by metadata, e.g. with a [WhereFilter](crate::collection::WhereFilter).
+    pub where_metadata: Option<Value>,
+    /// The number of records to return. Defaults to 10.
+    pub limit: Option<usize>,
+    /// The number of records to skip.
+    pub offset: Option<usize>,
+    /// What to return besides the ids. [Include::Distances] returns the ranking scores. Defaults to documents and metadatas.
+    pub include: Option<Vec<Include>>,
+    /// Set as is in the search, replacing the fields built from the others, e.g. `"rank"` for a hybrid rank expression.
+    pub raw: Map<String, Value>,
+}
+
+impl SearchRequest {
+    /// Start building a [SearchRequest], only setting the fields you need.
+    pub fn builder() -> SearchRequestBuilder {
+        SearchRequestBuilder::default()
+    }
+
+    /// The body of the request, with this as its only search.
+    pub(crate) fn to_json(&self) -> Value {
+        let limit = self.limit.unwrap_or(DEFAULT_N_RESULTS);
+        let offset = self.offset.unwrap_or(0);
+        let mut search = Map::new();
+        if let Some(where_metadata) = &self.where_metadata {
+            search.insert("filter".into(), where_metadata.clone());
+        }
+        if let Some(query_embedding) = &self.query_embedding {
+            search.insert(
+                "rank".into(),
+                json!({"$knn": {"query": query_embedding, "key": "#embedding", "limit": offset + limit}}),
+            );
+        }
+        search.insert("limit".into(), json!({"limit": limit, "offset": offset}));
+        let include = self
+            .include
+            .as_deref()
+            .unwrap_or(&[Include::Documents, Include::Metadatas]);
+        let keys: Vec<&str> = include
+            .iter()
+            .filter_map(|include| match include {
+                Include::Documents => Some("#document"),
+                Include::Embeddings => Some("#embedding"),
+                Include::Metadatas => Some("#metadata"),
+                Include::Distances => Some("#score"),
+                Include::Uris => None,
+            })
+            .collect();
+        search.insert("select".into(), json!({"keys": keys}));
+        search.extend(self.raw.clone());
+        json!({"searches": [search]})
+    }
+}
+
+/// A builder for [SearchRequest]. See [SearchRequest::builder].
+#[derive(Debug, Default)]
+pub struct SearchRequestBuilder {
+    request: SearchRequest,
+}
+
+impl SearchRequestBuilder {
+    /// Rank by distance to the embedding. See [SearchRequest::query_embedding].
+    pub fn query_embedding(mut self, query_embedding: Embedding) -> Self {
+        self.request.query_embedding = Some(query_embedding);
+        self
+    }
+
+    /// Filter by metadata, e.g. with a [WhereFilter](crate::collection::WhereFilter).
+    pub fn where_metadata(mut self, where_metadata: impl Into<Value>) -> Self {
+        self.request.where_metadata = Some(where_metadata.into());
+        self
+    }
+
+    pub fn limit(mut self, limit: usize) -> Self {
+        self.request.limit = Some(limit);
+        self
+    }
+
+    pub fn offset(mut self, offset: usize) -> Self {
+        self.request.offset = Some(offset);
+        self
+    }
+
+    pub fn include(mut self, include: Vec<Include>) -> Self {
+        self.request.include = Some(include);
+        self
+    }
+
+    /// Set a field of the search as is. See [SearchRequest::raw].
+    pub fn raw(mut self, key: impl Into<String>, value: impl Into<Value>) -> Self {
+        self.request.raw.insert(key.into(), value.into());
+        self
+    }
+
+    pub fn build(self) -> SearchRequest {
+        self.request
+    }
+}
+
+/// The records found by [ChromaCollection::search](crate::ChromaCollection::search), in rank order.
+/// The other fields are only populated when requested with [SearchRequest::include], and in the same order as `ids`.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct SearchResult {
+    pub ids: Vec<String>,
+    pub documents: Option<Vec<Option<String>>>,
+    pub metadatas: Option<Vec<Option<Metadata>>>,
+    pub embeddings: Option<Vec<Option<Embedding>>>,
+    /// The ranking score of every record, the distance to the query embedding when ranked by it. Lower is better.
+    pub scores: Option<Vec<Option<f32>>>,
+}
+
+/// The response of the search endpoint, with one entry per search in every field.
+#[derive(Deserialize)]
+pub(crate) struct SearchResponse {
+    ids: Vec<Vec<String>>,
+    #[serde(default)]
+    documents: Vec<Option<Vec<Option<String>>>>,
+    #[serde(default)]
+    metadatas: Vec<Option<Vec<Option<Metadata>>>>,
+    #[serde(default)]
+    embeddings: Vec<Option<Vec<Option<Embedding>>>>,
+    #[serde(default)]
+    scores: Vec<Option<Vec<Option<f32>>>>,
+}
+
+impl SearchResponse {
+    /// The result of the only search of the request.
+    pub(crate) fn into_result(self) -> SearchResult {
+        fn first<T>(sets: Vec<Option<T>>) -> Option<T> {
+            sets.into_iter().next().flatten()
+        }
+        SearchResult {
+            ids: self.ids.into_iter().next().unwrap_or_default(),
+            documents: first(self.documents),
+            metadatas: first(self.metadatas),
+            embeddings: first(self.embeddings),
+            scores: first(self.scores),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_search_request_json() {
+        assert_eq!(
+            SearchRequest::default().to_json(),
+            json!({"searches": [{
+                "limit": {"limit": 10, "offset": 0},
+                "select": {"keys": ["#document", "#metadata"]},
+            }]})
+        );
+
+        let request = SearchRequest::builder()
+            .query_embedding(vec![0.5, 1.0])
+            .where_metadata(json!({"color": "red"}))
+            .limit(5)
+            .offset(10)
+            .include(vec![Include::Documents, Include::Distances, Include::Uris])
+            .build();
+        assert_eq!(
+            request.to_json(),
+            json!({"searches": [{
+                "filter": {"color": "red"},
+                "rank": {"$knn": {"query": [0.5, 1.0], "key": "#embedding", "limit": 15}},
+                "limit": {"limit": 5, "offset": 10},
+                "select": {"keys": ["#document", "#score"]},
+            }]})
+        );
+
+        // Raw fields replace the modeled ones.
+        let rrf = json!({"$rrf": {"ranks": []}});
+        let request = SearchRequest::builder()
+            .query_embedding(vec![0.5])
+            .raw("rank", rrf.clone())
+            .build();
+        assert_eq!(request.to_json()["searches"][0]["rank"], rrf);
+    }
+
+    #[test]
+    fn test_search_response() {
+        let response: SearchResponse = serde_json::from_value(json!({
+            "ids": [["a", "b"]],
+            "documents": [["doc a", null]],
+            "metadatas": [null],
+            "scores": [[0.1, 0.2]],
+            "select": [["#document", "#score"]],
+        }))
+        .unwrap();
+        let result = response.into_result();
+        assert_eq!(result.ids, vec!["a", "b"]);
+        assert_eq!(
+            result.documents,
+            Some(vec![Some("doc a".to_string()), None])
+        );
+        assert_eq!(result.metadatas, None);
+        assert_eq!(result.embeddings, None);
+        assert_eq!(result.scores, Some(vec![Some(0.1), Some(0.2)]));
+    }
+}
@@ -0,0 +1,197 @@
+use std::sync::Arc;
+
+use super::{
+    client::{ChromaClient, ChromaClientOptions},
+    collection::{generate_ids, AddOptions, ChromaCollection, CollectionEntries, QueryHits},
+    commons::{Metadatas, Result},
+    embeddings::EmbeddingFunction,
+};
+
+/// The number of texts embedded and written per request by [ChromaStore::add_texts].
+const ADD_CHUNK_SIZE: usize = 100;
+
+/// A vector store over a single collection and embedding function, for when "add texts, search texts" is all you need.
+///
+/// It is a thin layer over [ChromaClient] and [ChromaCollection], which remain available for everything else.
+pub struct ChromaStore {
+    collection: ChromaCollection,
+}
+
+impl ChromaStore {
+    /// Connect with the given options and get or create the collection, embedding every text with `embedding`.
+    ///
+    /// # Errors
+    ///
+    /// * The same as [ChromaClient::new] and [ChromaClient::get_or_create_collection]
+    pub async fn open(
+        options: ChromaClientOptions,
+        collection_name: &str,
+        embedding: Arc<dyn EmbeddingFunction>,
+    ) -> Result<Self> {
+        let client = ChromaClient::new(options).await?;
+        let collection = client
+            .get_or_create_collection(collection_name, None)
+            .await?
+            .with_embedding_function(embedding);
+        Ok(Self { collection })
+    }
+
+    /// Embed and add the texts under freshly generated ids, a chunk at a time. Returns the ids, in the same order as `texts`.
+    ///
+    /// # Errors
+    ///
+    /// * If the length of texts and metadatas don't match
+    /// * [ChromaError::ChunkFailed](crate::ChromaError::ChunkFailed) if a chunk could not be embedded or written. The chunks before it were written.
+    pub async fn add_texts(
+        &self,
+        texts: Vec<&str>,
+        metadatas: Option<Metadatas>,
+    ) -> Result<Vec<String>> {
+        let ids = generate_ids(texts.len());
+        let collection_entries = CollectionEntries {
+            ids: ids.iter().map(String::as_str).collect(),
+            metadatas,
+            documents: Some(texts),
+            embeddings: None,
+        };
+        self.collection
+            .add_chunked(
+                collection_entries,
+                ADD_CHUNK_SIZE,
+                self.embedding_function(),
+                AddOptions::default(),
+            )
+            .await?;
+        Ok(ids)
+    }
+
+    /// Return the `k` texts most similar to `text`, with their metadata and distance.
+    pub async fn similarity_search(&self, text: &str, k: usize) -> Result<QueryHits> {
+        self.collection.similarity_search(text, k, None, None).await
+    }
+
+    /// Delete the texts with the given ids. Unknown ids are ignored.
+    pub async fn delete_by_ids(&self, ids: &[&str]) -> Result<()> {
+        self.collection.delete(Some(ids.to_vec()), None, None).await
+    }
+
+    /// The number of texts in the store.
+    pub async fn count(&self) -> Result<usize> {
+        self.collection.count().await
+    }
+
+    /// Delete every text in the store, keeping the collection itself.
+    pub async fn clear(&self) -> Result<()> {
+        self.collection.delete_all().await?;
+        Ok(())
+    }
+
+    fn embedding_function(&self) -> Option<Box<dyn EmbeddingFunction>> {
+        self.collection
+            .embedding_function()
+            .map(|embedding_function| {
+                Box::new(embedding_function.clone()) as Box<dyn EmbeddingFunction>
+            })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::api::APIClientAsync;
+    use crate::embeddings::MockEmbeddingProvider;
+    use serde_json::json;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use wiremock::matchers::{body_partial_json, method, path_regex};
+    use wiremock::{Mock, MockServer, Request, ResponseTemplate};
+
+    fn mock_store(server: &MockServer) -> ChromaStore {
+        let collection = ChromaCollection::mock(
+            APIClientAsync::new(
+                server.uri(),
+                Default::default(),
+                "default_tenant".to_string(),
+                "default_database".to_string(),
+                Default::default(),
+            )
+            .unwrap(),
+        )
+        .with_embedding_function(Arc::new(MockEmbeddingProvider));
+        ChromaStore { collection }
+    }
+
+    #[tokio::test]
+    async fn test_store_add_and_search() {
+        let server = MockServer::start().await;
+        let added = Arc::new(AtomicUsize::new(0));
+        let added_in_mock = added.clone();
+        Mock::given(method("POST"))
+            .and(path_regex("/add$"))
+            .respond_with(move |request: &Request| {
+                let body: serde_json::Value = request.body_json().unwrap();
+                assert_eq!(body["embeddings"][0].as_array().unwrap().len(), 768);
+                let n = body["ids"].as_array().unwrap().len();
+                added_in_mock.fetch_add(n, Ordering::SeqCst);
+                ResponseTemplate::new(201).set_body_json(json!({}))
+            })
+            .mount(&server)
+            .await;
+        Mock::given(method("POST"))
+            .and(path_regex("/query$"))
+            .and(body_partial_json(json!({"n_results": 2})))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "ids": [["a", "b"]],
+                "documents": [["frog", "cow"]],
+                "distances": [[0.1, 0.2]],
+            })))
+            .mount(&server)
+            .await;
+        Mock::given(method("GET"))
+            .and(path_regex("/count$"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(250))
+            .mount(&server)
+            .await;
+
+        let store = mock_store(&server);
+        let texts = vec!["text"; 250];
+        let ids = store.add_texts(texts, None).await.unwrap();
+        assert_eq!(ids.len(), 250);
+        assert_eq!(added.load(Ordering::SeqCst), 250);
+        assert_eq!(server.received_requests().await.unwrap().len(), 3);
+        assert_eq!(store.count().await.unwrap(), 250);
+
+        let hits = store.similarity_search("frog", 2).await.unwrap();
+        assert_eq!(hits.len(), 2);
+        assert_eq!(hits[0].document.as_deref(), Some("frog"));
+    }
+
+    #[tokio::test]
+    async fn test_store_delete_and_clear() {
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path_regex("/get$"))
+            .and(body_partial_json(json!({"include": []})))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({"ids": ["a", "b"]})))
+            .expect(1)
+            .mount(&server)
+            .await;
+        Mock::given(method("POST"))
+            .and(path_regex("/delete$"))
+            .and(body_partial_json(json!({"ids": ["a", "b"]})))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!(null)))
+            .expect(1)
+            .mount(&server)
+            .await;
+        Mock::given(method("POST"))
+            .and(path_regex("/delete$"))
+            .and(body_partial_json(json!({"ids": null, "where": null})))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!(null)))
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        let store = mock_store(&server);
+        store.delete_by_ids(&["a", "b"]).await.unwrap();
+        store.clear().await.unwrap();
+    }
+}
@@ -0,0 +1,158 @@
+use std::fs::{File, OpenOptions};
+use std::io::Write;
+use std::path::Path;
+use std::sync::Mutex;
+use std::time::Duration;
+
+use serde::{Serialize, Serializer};
+use serde_json::Value;
+
+use super::commons::{Embeddings, Result};
+
+/// A hook invoked after every successful query of a collection handle, e.g. to export the queries and their results
+/// for offline relevance evaluation. See [with_query_logger](crate::ChromaCollection::with_query_logger).
+///
+/// It is called on the querying task, so it should be quick.
+pub trait QueryLogger: Send + Sync {
+    fn on_query(&self, trace: &QueryTrace);
+
+    /// Whether to record the query embeddings in the traces. They are large, so they are left out by default.
+    fn include_embeddings(&self) -> bool {
+        false
+    }
+}
+
+/// A query and its results, as passed to a [QueryLogger].
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct QueryTrace {
+    /// The name of the queried collection.
+    pub collection: String,
+    pub query_texts: Option<Vec<String>>,
+    /// Only recorded when [QueryLogger::include_embeddings] is true.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub query_embeddings: Option<Embeddings>,
+    /// The number of results requested per query, excluded ids not counted.
+    pub n_results: usize,
+    pub where_metadata: Option<Value>,
+    pub where_document: Option<Value>,
+    /// The ids returned for each query.
+    pub ids: Vec<Vec<String>>,
+    /// The distances returned for each query, when requested.
+    pub distances: Option<Vec<Vec<f32>>>,
+    /// The time from sending the request to receiving the response, serialized in milliseconds.
+    #[serde(rename = "latency_ms", serialize_with = "serialize_millis")]
+    pub latency: Duration,
+}
+
+fn serialize_millis<S: Serializer>(
+    latency: &Duration,
+    serializer: S,
+) -> std::result::Result<S::Ok, S::Error> {
+    serializer.serialize_f64(latency.as_secs_f64() * 1000.0)
+}
+
+/// A [QueryLogger] appending each trace to a file as a line of JSON.
+///
+/// ```no_run
+/// use std::sync::Arc;
+/// use chromadb::trace::JsonlQueryLogger;
+///
+/// # async fn doc_query_logger(client: &chromadb::ChromaClient) -> Result<(), chromadb::ChromaError> {
+/// let logger = JsonlQueryLogger::new("queries.jsonl")?;
+/// let collection = client
+///     .get_collection("my_collection")
+///     .await?
+///     .with_query_logger(Arc::new(logger));
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug)]
+pub struct JsonlQueryLogger {
+    file: Mutex<File>,
+    include_embeddings: bool,
+}
+
+impl JsonlQueryLogger {
+    /// Append the traces to the file at `path`, creating it if needed.
+    ///
+    /// # Errors
+    ///
+    /// * [ChromaError::Io](crate::ChromaError::Io) if the file cannot be opened
+    pub fn new(path: impl AsRef<Path>) -> Result<Self> {
+        let file = OpenOptions::new().create(true).append(true).open(path)?;
+        Ok(Self {
+            file: Mutex::new(file),
+            include_embeddings: false,
+        })
+    }
+
+    /// Also record the query embeddings. Defaults to false.
+    pub fn with_embeddings(mut self, include_embeddings: bool) -> Self {
+        self.include_embeddings = include_embeddings;
+        self
+    }
+}
+
+impl QueryLogger for JsonlQueryLogger {
+    fn on_query(&self, trace: &QueryTrace) {
+        let mut line = match serde_json::to_vec(trace) {
+            Ok(line) => line,
+            Err(e) => return log::warn!("Failed to serialize a query trace: {e}"),
+        };
+        line.push(b'\n');
+        let mut file = self.file.lock().unwrap_or_else(|e| e.into_inner());
+        if let Err(e) = file.write_all(&line) {
+            log::warn!("Failed to write a query trace: {e}");
+        }
+    }
+
+    fn include_embeddings(&self) -> bool {
+        self.include_embeddings
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_jsonl_query_logger() {
+        let path =
+            std::env::temp_dir().join(format!("chromadb-trace-{}.jsonl", uuid::Uuid::new_v4()));
+        let logger = JsonlQueryLogger::new(&path).unwrap();
+        let trace = QueryTrace {
+            collection: "mock".to_string(),
+            query_texts: Some(vec!["frog".to_string()]),
+            query_embeddings: None,
+            n_results: 2,
+            where_metadata: None,
+            where_document: None,
+            ids: vec![vec!["a".to_string(), "b".to_string()]],
+            distances: Some(vec![vec![0.5, 0.25]]),
+            latency: Duration::from_millis(3),
+        };
+        logger.on_query(&trace);
+        logger.on_query(&trace);
+
+        let contents = std::fs::read_to_string(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        let lines: Vec<Value> = contents
+            .lines()
+            .map(|line| serde_json::from_str(line).unwrap())
+            .collect();
+        assert_eq!(lines.len(), 2);
+        assert_eq!(
+            lines[0],
+            serde_json::json!({
+                "collection": "mock",
+                "query_texts": ["frog"],
+                "n_results": 2,
+                "where_metadata": null,
+                "where_document": null,
+                "ids": [["a", "b"]],
+                "distances": [[0.5, 0.25]],
+                "latency_ms": 3.0,
+            })
+        );
+    }
+}
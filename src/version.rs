@@ -0,0 +1,141 @@
+use std::cmp::Ordering;
+use std::fmt;
+
+use super::error::ChromaError;
+
+/// The version of a Chroma server, as returned by [ChromaClient::version_info](crate::ChromaClient::version_info).
+///
+/// Parsed as semver, tolerating Python-style suffixes like `0.6.3.dev12`, which is treated as the pre-release `0.6.3-dev12`.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct ChromaVersion {
+    version: semver::Version,
+    raw: String,
+}
+
+impl ChromaVersion {
+    /// Parse a version string reported by Chroma.
+    ///
+    /// # Errors
+    ///
+    /// * If the string does not start with a `major.minor.patch` version
+    pub fn parse(version: &str) -> Result<Self, ChromaError> {
+        let raw = version.trim();
+        let trimmed = raw.trim_start_matches('v');
+        // The end of the numeric `major.minor.patch` prefix.
+        let mut dots = 0;
+        let end = trimmed
+            .char_indices()
+            .find(|&(_, c)| {
+                if c == '.' {
+                    dots += 1;
+                    dots > 2
+                } else {
+                    !c.is_ascii_digit()
+                }
+            })
+            .map_or(trimmed.len(), |(i, _)| i);
+        let (core, suffix) = trimmed.split_at(end);
+        let suffix = suffix.trim_start_matches(['.', '-', '+']);
+        let normalized = if suffix.is_empty() {
+            core.to_string()
+        } else {
+            let suffix: String = suffix
+                .chars()
+                .map(|c| if c.is_ascii_alphanumeric() { c } else { '.' })
+                .collect();
+            format!("{core}-{suffix}")
+        };
+        let version = semver::Version::parse(&normalized).map_err(|e| {
+            ChromaError::invalid_argument(format!("Invalid Chroma version {raw:?}: {e}"))
+        })?;
+        Ok(Self {
+            version,
+            raw: raw.to_string(),
+        })
+    }
+
+    pub fn major(&self) -> u64 {
+        self.version.major
+    }
+
+    pub fn minor(&self) -> u64 {
+        self.version.minor
+    }
+
+    pub fn patch(&self) -> u64 {
+        self.version.patch
+    }
+
+    /// The suffix after `major.minor.patch`, e.g. `dev12` for `0.6.3.dev12`. Empty for releases.
+    pub fn pre(&self) -> &str {
+        self.version.pre.as_str()
+    }
+
+    /// Whether this is at least the given release. Pre-releases of that release do not count.
+    pub fn at_least(&self, major: u64, minor: u64, patch: u64) -> bool {
+        self.version >= semver::Version::new(major, minor, patch)
+    }
+
+    /// Whether the server serves the `/api/v2` endpoints used by this crate, added in Chroma 0.6.
+    pub fn supports_v2_api(&self) -> bool {
+        self.at_least(0, 6, 0)
+    }
+
+    /// Whether the server can fork collections, added in Chroma 1.0.
+    pub fn supports_fork(&self) -> bool {
+        self.at_least(1, 0, 0)
+    }
+}
+
+impl PartialOrd for ChromaVersion {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for ChromaVersion {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.version.cmp(&other.version)
+    }
+}
+
+impl fmt::Display for ChromaVersion {
+    /// The version as reported by the server.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.raw)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_chroma_version() {
+        let version = ChromaVersion::parse("0.6.3.dev12").unwrap();
+        assert_eq!(
+            (version.major(), version.minor(), version.patch()),
+            (0, 6, 3)
+        );
+        assert_eq!(version.pre(), "dev12");
+        assert_eq!(version.to_string(), "0.6.3.dev12");
+        assert!(version.supports_v2_api());
+        assert!(!version.supports_fork());
+
+        let version = ChromaVersion::parse("1.0.12").unwrap();
+        assert_eq!(version.pre(), "");
+        assert!(version.supports_fork());
+        assert!(ChromaVersion::parse("0.5.23").unwrap() < version);
+        assert!(!ChromaVersion::parse("0.5.23").unwrap().supports_v2_api());
+        assert!(!ChromaVersion::parse("1.0.0rc1").unwrap().supports_fork());
+        assert_eq!(ChromaVersion::parse("v1.2.3").unwrap().minor(), 2);
+        assert_eq!(
+            ChromaVersion::parse("0.4.24-post_1").unwrap().pre(),
+            "post.1"
+        );
+
+        for invalid in ["", "latest", "1.2", "1..3"] {
+            assert!(ChromaVersion::parse(invalid).is_err(), "{invalid}");
+        }
+    }
+}
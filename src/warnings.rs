@@ -0,0 +1,127 @@
+use std::fmt;
+use std::ops::Deref;
+use std::sync::Arc;
+
+/// A non-fatal condition the caller may want to know about.
+#[derive(Debug, Clone, PartialEq)]
+#[non_exhaustive]
+pub enum Warning {
+    /// The server returned fewer hits than requested, usually because the collection or the filter holds fewer entries.
+    FewerResults { requested: usize, returned: usize },
+    /// `preflight_embeddings` was set, but there was no embedding function to check.
+    PreflightSkipped,
+    /// A write or delete was called with no ids, so no request was sent.
+    EmptyBatch { operation: &'static str },
+    /// The query was embedded with another model than the one recorded for the collection, so its distances are meaningless.
+    EmbeddingFunctionMismatch { collection: String, query: String },
+    /// Embeddings were requested, but the server left them out for `missing` of the `total` records returned,
+    /// as some managed deployments do for large results.
+    EmbeddingsUnavailable { missing: usize, total: usize },
+}
+
+impl fmt::Display for Warning {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::FewerResults {
+                requested,
+                returned,
+            } => write!(f, "Requested {requested} results, got {returned}"),
+            Self::PreflightSkipped => write!(
+                f,
+                "Skipped the embedding preflight check: no embedding function was provided"
+            ),
+            Self::EmptyBatch { operation } => {
+                write!(f, "Skipped {operation}: no ids were provided")
+            }
+            Self::EmbeddingFunctionMismatch { collection, query } => write!(
+                f,
+                "The collection was embedded with {collection}, but the query with {query}"
+            ),
+            Self::EmbeddingsUnavailable { missing, total } => write!(
+                f,
+                "Embeddings were requested, but the server returned none for {missing} of {total} records"
+            ),
+        }
+    }
+}
+
+/// The warnings attached to a result. Empty, and not allocated, in the common case.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Warnings(Vec<Warning>);
+
+impl Warnings {
+    /// Record a warning and pass it to the client's warning callback, if any.
+    pub(crate) fn push(&mut self, warning: Warning, callback: Option<&WarningCallback>) {
+        if let Some(callback) = callback {
+            callback.call(&warning);
+        }
+        self.0.push(warning);
+    }
+}
+
+impl Deref for Warnings {
+    type Target = [Warning];
+
+    fn deref(&self) -> &[Warning] {
+        &self.0
+    }
+}
+
+impl IntoIterator for Warnings {
+    type Item = Warning;
+    type IntoIter = std::vec::IntoIter<Warning>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.0.into_iter()
+    }
+}
+
+/// A callback invoked with every warning produced through a client, e.g. to log them in one place.
+#[derive(Clone)]
+pub struct WarningCallback(Arc<dyn Fn(&Warning) + Send + Sync>);
+
+impl WarningCallback {
+    pub fn new(callback: impl Fn(&Warning) + Send + Sync + 'static) -> Self {
+        Self(Arc::new(callback))
+    }
+
+    pub(crate) fn call(&self, warning: &Warning) {
+        (self.0)(warning)
+    }
+}
+
+impl fmt::Debug for WarningCallback {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("WarningCallback")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Mutex;
+
+    #[test]
+    fn test_warnings() {
+        let warnings = Warnings::default();
+        assert!(warnings.is_empty());
+        assert_eq!(warnings.0.capacity(), 0);
+
+        let seen = Arc::new(Mutex::new(vec![]));
+        let callback_seen = seen.clone();
+        let callback = WarningCallback::new(move |warning| {
+            callback_seen.lock().unwrap().push(warning.to_string());
+        });
+        let mut warnings = Warnings::default();
+        warnings.push(
+            Warning::FewerResults {
+                requested: 5,
+                returned: 2,
+            },
+            Some(&callback),
+        );
+        warnings.push(Warning::PreflightSkipped, None);
+        assert_eq!(warnings.len(), 2);
+        assert_eq!(*seen.lock().unwrap(), vec!["Requested 5 results, got 2"]);
+    }
+}